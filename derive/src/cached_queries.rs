@@ -0,0 +1,47 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{ImplItem, ItemImpl, parse_macro_input, parse_quote};
+
+/// Name of the attribute which opts a method out of `#[cached_queries]`.
+const OPT_OUT_ATTRIBUTE: &str = "uncached";
+
+pub(crate) fn cached_queries(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = proc_macro2::TokenStream::from(args);
+    let mut input = parse_macro_input!(input as ItemImpl);
+
+    for item in &mut input.items {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+
+        // Associated functions have no receiver to retrieve the database from.
+        if method.sig.receiver().is_none() {
+            continue;
+        }
+
+        let attr_count = method.attrs.len();
+        method.attrs.retain(|attr| !attr.path().is_ident(OPT_OUT_ATTRIBUTE));
+
+        if method.attrs.len() != attr_count {
+            continue;
+        }
+
+        // Methods with their own attribute override the defaults.
+        if method.attrs.iter().any(is_cached_query_attr) {
+            continue;
+        }
+
+        method.attrs.push(parse_quote! {
+            #[::lume_architect::cached_query(#args)]
+        });
+    }
+
+    quote! { #input }.into()
+}
+
+fn is_cached_query_attr(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "cached_query")
+}
@@ -7,16 +7,47 @@ use syn::spanned::Spanned;
 use syn::{Expr, ItemFn, ReturnType, Signature, parse_macro_input};
 
 #[derive(Debug, FromMeta)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "each flag maps to a separate attribute argument"
+)]
 struct CacheMacroArgs {
     #[darling(default)]
     db_expr: Option<Expr>,
 
+    #[darling(default)]
+    db_name: Option<String>,
+
+    #[darling(default)]
+    db_ident: Option<syn::Ident>,
+
     #[darling(default)]
     key: Option<Expr>,
 
     #[darling(default)]
     result: bool,
 
+    #[darling(default)]
+    cache_errors: bool,
+
+    #[darling(default)]
+    max_retries: Option<u32>,
+
+    #[darling(default)]
+    retry_after: Option<Expr>,
+
+    #[darling(default)]
+    check_determinism: bool,
+
+    #[darling(default)]
+    debug_key: bool,
+
+    #[darling(default)]
+    disable_if: Option<Expr>,
+
+    #[darling(default)]
+    trace: bool,
+
     #[darling(flatten)]
     flags: CacheMacroFlags,
 }
@@ -108,15 +139,13 @@ pub(crate) fn cached_query(args: TokenStream, input: TokenStream) -> TokenStream
 }
 
 fn build_block(args: &CacheMacroArgs, input: &ItemFn) -> proc_macro2::TokenStream {
-    let ItemFn { sig, block, .. } = &input;
-    let Signature { ident, .. } = sig;
-
+    let ItemFn { block, .. } = &input;
     let query_name = determine_query_name(input);
 
     let db_expr = if let Some(db_expr) = &args.db_expr {
         db_expr.into_token_stream()
     } else if let Some(receiver) = input.sig.receiver() {
-        receiver.self_token.to_token_stream()
+        receiver_ref(receiver)
     } else {
         return quote_spanned! {
             input.span() =>
@@ -124,56 +153,282 @@ fn build_block(args: &CacheMacroArgs, input: &ItemFn) -> proc_macro2::TokenStrea
         };
     };
 
+    let db = if let Some(db_name) = &args.db_name {
+        quote! { ::lume_architect::MultiDatabaseContext::db_named(#db_expr, #db_name) }
+    } else {
+        quote! { ::lume_architect::DatabaseContext::db(#db_expr) }
+    };
+
     let query_flags = get_query_flags(args);
 
     let keys = if let Some(keys) = &args.key {
-        keys.into_token_stream()
+        quote! { (#keys) }
     } else {
         get_default_cache_keys(&input.sig.inputs)
     };
 
-    let calculate_hash_expr = quote! { {
-        use std::hash::Hash;
-        use std::hash::Hasher;
+    // The key is hashed in a single pass, using the same hasher as the
+    // database, and passed on as-is, so it isn't hashed again.
+    let calculate_hash_expr = quote! {
+        ::lume_architect::ResultKey::from_hashable(&#keys)
+    };
+
+    let cache_errors = args.cache_errors || args.max_retries.is_some() || args.retry_after.is_some();
+
+    if cache_errors && !args.result {
+        return quote_spanned! {
+            input.span() =>
+            compile_error!("`cache_errors`, `max_retries` and `retry_after` require the `result` attribute");
+        };
+    }
+
+    if args.check_determinism && args.result {
+        return quote_spanned! {
+            input.span() =>
+            compile_error!("`check_determinism` cannot be combined with the `result` attribute");
+        };
+    }
+
+    // When tracing, the body records that it was run, so the span can report
+    // whether the result was found in the cache.
+    let body = if args.trace {
+        let stmts = &block.stmts;
+
+        quote! { { __computed.set(true); #(#stmts)* } }
+    } else {
+        block.into_token_stream()
+    };
+
+    let execute_query = if cache_errors {
+        let max_retries = args.max_retries.unwrap_or_default();
+        let retry_after = if let Some(expr) = &args.retry_after {
+            quote! { ::core::option::Option::Some(#expr) }
+        } else {
+            quote! { ::core::option::Option::None }
+        };
+
+        quote! {
+            __db.execute_query_result_cached_by_key(
+                __query_name,
+                __hash,
+                ::lume_architect::ErrorPolicy { max_retries: #max_retries, retry_after: #retry_after },
+                || { #body }
+            )
+        }
+    } else if args.result {
+        quote! { __db.execute_query_result_by_key(__query_name, __hash, || { #body }) }
+    } else if args.check_determinism {
+        quote! { __db.execute_query_checked_by_key(__query_name, __hash, || { #body }) }
+    } else {
+        quote! { __db.execute_query_by_key(__query_name, __hash, || { #body }) }
+    };
+
+    // Wraps the execution in a span, which covers cached calls as well, unlike
+    // `#[tracing::instrument]` on the method itself.
+    let execute_query = if args.trace {
+        quote! {
+            let __computed = ::core::cell::Cell::new(false);
+            let __span = ::tracing::debug_span!(
+                "cached_query",
+                query = %::lume_architect::QueryName::to_query_name(__query_name),
+                status = ::tracing::field::Empty,
+            );
+
+            let __result = {
+                let _entered = __span.enter();
+                #execute_query
+            };
+
+            __span.record("status", if __computed.get() { "miss" } else { "hit" });
+            __result
+        }
+    } else {
+        execute_query
+    };
 
-        let mut s = std::hash::DefaultHasher::new();
+    // Binds the database to the name requested by the user, so the method
+    // body can execute other queries against it.
+    let db_binding = args.db_ident.as_ref().map(|ident| {
+        quote! { let #ident: &::lume_architect::Database = __db; }
+    });
+
+    // Captures the `Debug` representation of the key in debug builds, so
+    // diagnostics can show it instead of the hash.
+    let label_key = args.debug_key.then(|| {
+        quote! {
+            #[cfg(debug_assertions)]
+            __db.label_key_by_key(__query_name, __hash, || ::std::format!("{:?}", #keys));
+        }
+    });
+
+    // Registers where the query is defined in debug builds, so two methods
+    // which map to the same query name are reported instead of sharing
+    // results. Names of free functions are constant, so they are registered
+    // statically, while other names are registered when first executed.
+    let site = quote_spanned! { input.sig.ident.span() =>
+        ::core::concat!(::core::file!(), ":", ::core::line!(), ":", ::core::column!())
+    };
+
+    let constant_name = input.sig.receiver().is_none() && get_generic_type_names(&input.sig).is_empty();
 
-        let fn_name = &stringify!(#ident);
-        fn_name.hash(&mut s);
-        &#keys.hash(&mut s);
+    // Hashes the name of the query into its ID only once, at compile-time if
+    // the name is constant, or once per instance of the method otherwise.
+    // Names of const arguments are formatted on every call, so their names
+    // are hashed on every call as well.
+    let has_const_generics = input
+        .sig
+        .generics
+        .params
+        .iter()
+        .any(|param| matches!(param, syn::GenericParam::Const(_)));
 
-        s.finish()
-    } };
+    let query_name_binding = if constant_name {
+        quote! {
+            let __query_name = &const { ::lume_architect::PrehashedName::from_static(#query_name) };
+        }
+    } else if has_const_generics {
+        quote! { let __query_name = #query_name; }
+    } else {
+        quote! {
+            static __QUERY_NAMES: ::lume_architect::QueryNameCache = ::lume_architect::QueryNameCache::new();
+            let __query_parts = #query_name;
+            let __query_name = &__QUERY_NAMES.get(__query_parts);
+        }
+    };
 
-    let execute_query = if args.result {
-        quote! { __db.execute_query_result(#query_name, &__hash, || { #block }) }
+    let define_query = if constant_name {
+        quote! { ::lume_architect::__define_query!(static #query_name, #site); }
     } else {
-        quote! { __db.execute_query(#query_name, &__hash, || { #block }) }
+        quote! { ::lume_architect::__define_query!(__query_name, #site); }
     };
 
+    // Runs the method body without touching the cache when the condition
+    // holds. The body is wrapped in a closure, so it behaves exactly like it
+    // does when it is cached, including any `return` statements.
+    let bypass = args.disable_if.as_ref().map(|condition| {
+        quote! {
+            #[allow(clippy::redundant_closure_call, reason = "auto-generated")]
+            if #condition {
+                return (|| { #block })();
+            }
+        }
+    });
+
     quote! {
         let __hash = #calculate_hash_expr;
-        let __db = ::lume_architect::DatabaseContext::db(#db_expr);
+        let __db = #db;
+        #query_name_binding
+        #db_binding
+        #bypass
+
+        __db.ensure_query_exists(__query_name, || { #query_flags });
 
-        __db.ensure_query_exists(#query_name, || { #query_flags });
+        #[cfg(debug_assertions)]
+        #define_query
+        #label_key
 
         #execute_query
     }
 }
 
+/// Gets an expression which borrows the receiver of the method as `&Self`.
+fn receiver_ref(receiver: &syn::Receiver) -> proc_macro2::TokenStream {
+    let rec = receiver.self_token;
+
+    if receiver.reference.is_some() || matches!(*receiver.ty, syn::Type::Reference(_)) {
+        // `&self`, `&mut self` and `self: &Self`
+        quote! { #rec }
+    } else if receiver.colon_token.is_some() {
+        // `self: Box<Self>`, `self: Rc<Self>`, etc.
+        quote! { &*#rec }
+    } else {
+        // `self`
+        quote! { &#rec }
+    }
+}
+
+/// Gets an expression for the name of the query, which implements
+/// `QueryName`.
+///
+/// Names which depend on the receiver or generic arguments are given as an
+/// array of parts, so they don't need to be formatted on every call.
 fn determine_query_name(input: &ItemFn) -> proc_macro2::TokenStream {
     let ident = input.sig.ident.to_token_stream();
+    let generics = get_generic_type_names(&input.sig);
 
-    if let Some(receiver) = input.sig.receiver() {
-        let rec = receiver.self_token;
+    let mut parts = if let Some(receiver) = input.sig.receiver() {
+        let rec = receiver_ref(receiver);
 
-        quote! { &format!("{}::{}",
-            ::std::any::type_name_of_val(#rec),
-            stringify!(#ident)
-        ) }
+        vec![
+            quote! { ::std::any::type_name_of_val(#rec) },
+            quote! { "::" },
+            quote! { stringify!(#ident) },
+        ]
     } else {
-        quote! { stringify!(#ident) }
+        // Free functions with the same name may be defined in different
+        // modules, so the module path is made part of the query name.
+        let path = quote! { concat!(module_path!(), "::", stringify!(#ident)) };
+
+        if generics.is_empty() {
+            return path;
+        }
+
+        vec![path]
+    };
+
+    // Generic methods are monomorphized into separate functions, which must not
+    // share results with each other, so the type arguments are made part of
+    // the query name.
+    if !generics.is_empty() {
+        parts.push(quote! { "<" });
+
+        for (idx, generic) in generics.into_iter().enumerate() {
+            if idx > 0 {
+                parts.push(quote! { ", " });
+            }
+
+            parts.push(generic);
+        }
+
+        parts.push(quote! { ">" });
     }
+
+    quote! { &[#(#parts),*] }
+}
+
+/// Gets expressions for the names of all type and const arguments of the
+/// method, including anonymous types from `impl Trait` arguments.
+fn get_generic_type_names(sig: &Signature) -> Vec<proc_macro2::TokenStream> {
+    let mut names = Vec::new();
+
+    for param in &sig.generics.params {
+        match param {
+            syn::GenericParam::Type(ty) => {
+                let ident = &ty.ident;
+
+                names.push(quote! { ::std::any::type_name::<#ident>() });
+            }
+            syn::GenericParam::Const(c) => {
+                let ident = &c.ident;
+
+                names.push(quote! { &*::std::string::ToString::to_string(&#ident) });
+            }
+            syn::GenericParam::Lifetime(_) => {}
+        }
+    }
+
+    for input in &sig.inputs {
+        if let syn::FnArg::Typed(pat_type) = input
+            && let syn::Type::ImplTrait(_) = *pat_type.ty
+            && let syn::Pat::Ident(ref pat_ident) = *pat_type.pat
+        {
+            let ident = &pat_ident.ident;
+
+            names.push(quote! { ::std::any::type_name_of_val(&#ident) });
+        }
+    }
+
+    names
 }
 
 fn get_default_cache_keys(inputs: &Punctuated<syn::FnArg, syn::Token![,]>) -> proc_macro2::TokenStream {
@@ -186,13 +441,15 @@ fn get_default_cache_keys(inputs: &Punctuated<syn::FnArg, syn::Token![,]>) -> pr
             // state, which shouldn't impact the cache key.
             syn::FnArg::Receiver(_) => None,
             syn::FnArg::Typed(pat_type) => match *pat_type.pat {
-                syn::Pat::Ident(ref pat_ident) => Some(pat_ident.ident.to_string()),
+                syn::Pat::Ident(ref pat_ident) => Some(format!("&{},", pat_ident.ident)),
                 _ => None,
             },
         })
         .collect::<Vec<_>>();
 
-    let tuple = format!("({})", keys.join(", "));
+    // Arguments are borrowed, so non-`Copy` arguments are not moved into the
+    // key before the function body is executed.
+    let tuple = format!("({})", keys.join(" "));
     let ident = syn::parse_str::<syn::Expr>(&tuple).expect("unable to parse \"key\" expression");
 
     quote_spanned!(inputs.span() => #ident)
@@ -1,4 +1,6 @@
+mod cached_queries;
 mod cached_query;
+mod query_module;
 
 use proc_macro::TokenStream;
 
@@ -6,16 +8,44 @@ use proc_macro::TokenStream;
 /// results of the method in a database cache store. Cached results are
 /// keyed from the method name and arguments.
 ///
+/// In debug builds, the location of the method is registered, so two methods
+/// which map to the same query name are reported with both locations, instead
+/// of silently sharing their results. See
+/// [`lume_architect::Database::check_query_names`].
+///
 /// # Attributes
-/// - `db`: (optional, expr) specify the value which should be used to get the
-///   database instance. Defaults to `self`.
+/// - `db_expr`: (optional, expr) specify the value which should be used to get
+///   the database instance. Defaults to `self`.
 ///
 ///   NOTE: the resulting expression **must** implement
 ///   [`lume_architect::DatabaseContext`].
 ///
+///   Methods which take `&mut self` should point this at the field holding the
+///   database, so that the method body can still borrow `self` mutably.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(db_expr = &self.db)]
+///   ```
+///
+/// - `db_name`: (optional, string) specify the name of the database instance
+///   which should be used, for contexts holding multiple databases.
+///
+///   NOTE: the value given by `db_expr` **must** implement
+///   [`lume_architect::MultiDatabaseContext`] instead.
+///
 ///   Example:
 ///   ```rs
-///   #[cached_query(db = self.db())]
+///   #[cached_query(db_name = "hir")]
+///   ```
+///
+/// - `db_ident`: (optional, ident) specify a name which the database instance
+///   is bound to within the method body, so the body can execute other queries
+///   against the same database which is caching the method.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(db_ident = db)]
 ///   ```
 ///
 /// - `key`: (optional, expr) specify the value(s) which should be used to
@@ -36,7 +66,165 @@ use proc_macro::TokenStream;
 ///   ```rs
 ///   #[cached_query(result)]
 ///   ```
+///
+/// - `cache_errors`: (optional, boolean) specifies that errors returned from
+///   the method should be cached as well, instead of re-running the method on
+///   every call. Requires `result`.
+///
+///   NOTE: the error type **must** implement [`Clone`].
+///
+///   Example:
+///   ```rs
+///   #[cached_query(result, cache_errors)]
+///   ```
+///
+/// - `max_retries`: (optional, integer) specifies how many times the method is
+///   re-run after returning an error, before the error is cached. Implies
+///   `cache_errors`.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(result, max_retries = 3)]
+///   ```
+///
+/// - `retry_after`: (optional, expr) specifies a [`std::time::Duration`] after
+///   which a cached error expires, so the method is re-run. Implies
+///   `cache_errors`.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(result, retry_after = Duration::from_secs(30))]
+///   ```
+///
+/// - `check_determinism`: (optional, boolean) specifies that the method body
+///   should be run twice and have its results compared, when determinism checks
+///   are enabled on the database. Cannot be combined with `result`.
+///
+///   NOTE: the return type **must** implement [`std::hash::Hash`] and the
+///   method body must be callable more than once.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(check_determinism)]
+///   ```
+///
+/// - `debug_key`: (optional, boolean) specifies that the [`Debug`]
+///   representation of the cache key should be captured in debug builds, so
+///   diagnostics can show it instead of a bare hash. Only captured while
+///   [`lume_architect::Database::enable_key_labels`] is enabled.
+///
+///   NOTE: the cache key **must** implement [`std::fmt::Debug`].
+///
+///   Example:
+///   ```rs
+///   #[cached_query(debug_key)]
+///   ```
+///
+/// - `disable_if`: (optional, expr) specifies a condition under which caching
+///   is skipped entirely, so the method body is run on every call without
+///   looking up or storing any results. The condition is evaluated on every
+///   call, so it may be a constant such as `cfg!(debug_assertions)`, or a
+///   runtime check.
+///
+///   NOTE: the resulting expression **must** be of type [`bool`].
+///
+///   Example:
+///   ```rs
+///   #[cached_query(disable_if = cfg!(debug_assertions))]
+///   ```
+///
+/// - `trace`: (optional, boolean) specifies that every call of the method
+///   should be wrapped in a `tracing` span at the debug level, named
+///   `cached_query`. The span records the name of the query, and whether the
+///   result was found in the cache as `status`, which is either `"hit"` or
+///   `"miss"`.
+///
+///   NOTE: the crate defining the method **must** depend on the `tracing`
+///   crate. The method should not be annotated with `#[tracing::instrument]`
+///   as well, since it would wrap the method in a second span.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(trace)]
+///   ```
+///
+/// - `always`: (optional, boolean) specifies that the method body should be run
+///   on every call, even if a result is cached. Sets
+///   [`lume_architect::QueryFlags::ALWAYS`] on the query.
+///
+///   NOTE: flags are merged with the flags of any existing query of the same
+///   name, so the flag applies to every method which maps to the query.
+///
+///   Example:
+///   ```rs
+///   #[cached_query(always)]
+///   ```
 #[proc_macro_attribute]
 pub fn cached_query(args: TokenStream, input: TokenStream) -> TokenStream {
     cached_query::cached_query(args, input)
 }
+
+/// Defines every method within an `impl` block as a memoized query, as if
+/// each of them was annotated with [`macro@cached_query`].
+///
+/// Any attributes given are used as the default attributes for every method.
+/// Methods which are annotated with their own `#[cached_query(...)]`
+/// attribute use that instead, while methods annotated with `#[uncached]` are
+/// left untouched. Associated functions without a `self` receiver are
+/// skipped.
+///
+/// Example:
+/// ```rs
+/// #[cached_queries]
+/// impl Provider {
+///     pub fn parse(&self, file: FileId) -> Ast { ... }
+///
+///     #[cached_query(result)]
+///     pub fn typecheck(&self, item: ItemId) -> Result<Type> { ... }
+///
+///     #[uncached]
+///     pub fn file_name(&self, file: FileId) -> &str { ... }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn cached_queries(args: TokenStream, input: TokenStream) -> TokenStream {
+    cached_queries::cached_queries(args, input)
+}
+
+/// Declares a module of memoized queries, as a trait with cached default
+/// implementations.
+///
+/// For every query declared in the trait, two methods are generated:
+/// - `compute_<name>`, which must be implemented and computes the result of the
+///   query, without caching.
+/// - `<name>`, which returns the cached result of the query, if one exists.
+///   Otherwise, `compute_<name>` is invoked and its result is cached.
+///
+/// The trait also provides `register_queries`, which registers all queries of
+/// the module in a [`lume_architect::Database`]. It must be invoked before any
+/// query of the module is executed, so executing a query doesn't have to
+/// ensure that the query exists. The names of the queries are hashed at
+/// compile-time, and the arguments are only hashed once per call.
+///
+/// NOTE: the implementing type **must** implement
+/// [`lume_architect::DatabaseContext`], and the arguments of each query
+/// **must** implement [`std::hash::Hash`].
+///
+/// Example:
+/// ```rs
+/// query_module! {
+///     pub trait Frontend {
+///         fn parse(file: FileId) -> Ast;
+///         fn typecheck(item: ItemId) -> Type;
+///     }
+/// }
+///
+/// impl Frontend for Provider {
+///     fn compute_parse(&self, file: FileId) -> Ast { ... }
+///     fn compute_typecheck(&self, item: ItemId) -> Type { ... }
+/// }
+/// ```
+#[proc_macro]
+pub fn query_module(input: TokenStream) -> TokenStream {
+    query_module::query_module(input)
+}
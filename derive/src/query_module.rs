@@ -0,0 +1,98 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{ItemTrait, ReturnType, TraitItem, parse_macro_input};
+
+pub(crate) fn query_module(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemTrait);
+    let ItemTrait {
+        attrs,
+        vis,
+        ident: trait_ident,
+        items,
+        ..
+    } = &input;
+
+    let mut methods = Vec::new();
+    let mut query_names = Vec::new();
+
+    for item in items {
+        let TraitItem::Fn(method) = item else {
+            return quote_spanned! {
+                item.span() => compile_error!("query modules may only contain query declarations");
+            }
+            .into();
+        };
+
+        if let Some(receiver) = method.sig.receiver() {
+            return quote_spanned! {
+                receiver.span() => compile_error!("query declarations must not have a receiver");
+            }
+            .into();
+        }
+
+        let mut arg_idents = Vec::new();
+
+        for input in &method.sig.inputs {
+            if let syn::FnArg::Typed(pat_type) = input
+                && let syn::Pat::Ident(pat_ident) = &*pat_type.pat
+            {
+                arg_idents.push(pat_ident.ident.clone());
+            } else {
+                return quote_spanned! {
+                    input.span() => compile_error!("query arguments must be plain identifiers");
+                }
+                .into();
+            }
+        }
+
+        let method_attrs = &method.attrs;
+        let ident = &method.sig.ident;
+        let inputs = &method.sig.inputs;
+        let compute_ident = format_ident!("compute_{}", ident);
+
+        let output = match &method.sig.output {
+            ReturnType::Default => quote! { -> () },
+            ReturnType::Type(arrow, ty) => quote! { #arrow #ty },
+        };
+
+        let query_name = quote! {
+            concat!(module_path!(), "::", stringify!(#trait_ident), "::", stringify!(#ident))
+        };
+
+        methods.push(quote! {
+            #[doc = concat!("Computes the result of [`Self::", stringify!(#ident), "`], without caching.")]
+            fn #compute_ident(&self, #inputs) #output;
+
+            #(#method_attrs)*
+            fn #ident(&self, #inputs) #output {
+                const __QUERY_NAME: ::lume_architect::PrehashedName<'static, str> =
+                    ::lume_architect::PrehashedName::from_static(#query_name);
+
+                let __key = ::lume_architect::ResultKey::from_hashable(&(#(&#arg_idents,)*));
+
+                ::lume_architect::DatabaseContext::db(self)
+                    .execute_query_by_key(&__QUERY_NAME, __key, || self.#compute_ident(#(#arg_idents),*))
+            }
+        });
+
+        query_names.push(query_name);
+    }
+
+    quote! {
+        #(#attrs)*
+        #vis trait #trait_ident: ::lume_architect::DatabaseContext {
+            #(#methods)*
+
+            /// Registers all queries within the module in the given database,
+            /// which must be done before any of them is executed.
+            fn register_queries(db: &::lume_architect::Database)
+            where
+                Self: Sized,
+            {
+                #(db.ensure_query_exists(#query_names, ::lume_architect::QueryFlags::empty);)*
+            }
+        }
+    }
+    .into()
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("is_even", || QueryFlags::ADAPTIVE);
+    db.ensure_query_exists("layout", || QueryFlags::ADAPTIVE);
+
+    // Checking whether a number is even is much cheaper than looking up a
+    // cached result, so the query stops caching after a while. The threshold
+    // is raised, so a busy machine doesn't make the query seem expensive.
+    db.set_adaptive_threshold("is_even", Duration::from_millis(1));
+    db.ensure_query_exists("describe", QueryFlags::empty);
+
+    let describe = db.execute_query("describe", &0_u32, || {
+        if db.execute_query("is_even", &0_u32, || true) {
+            "even"
+        } else {
+            "odd"
+        }
+    });
+
+    assert_eq!(describe, "even");
+
+    for n in 1..32_u32 {
+        db.execute_query("is_even", &n, || n % 2 == 0);
+    }
+
+    // Once caching is bypassed, the cached results are invalidated, along
+    // with all results which depend on them.
+    assert!(!db.query("is_even").caches_results());
+    assert_eq!(db.query("is_even").len(), 0);
+    assert!(!db.contains("describe", &0_u32));
+
+    // Slow queries keep being cached, even after as many results as it takes
+    // a cheap query to stop caching.
+    for n in 0..32_u32 {
+        db.execute_query("layout", &n, || {
+            std::thread::sleep(Duration::from_micros(50));
+
+            format!("layout of {n}")
+        });
+    }
+
+    assert!(db.query("layout").caches_results());
+    assert_eq!(db.query("layout").len(), 32);
+
+    // Caching resumes once a result is no longer cheap to compute.
+    db.set_adaptive_threshold("is_even", Duration::ZERO);
+
+    db.execute_query("is_even", &64_u32, || true);
+    assert!(db.query("is_even").caches_results());
+    assert!(db.contains("is_even", &64_u32));
+}
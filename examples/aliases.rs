@@ -0,0 +1,18 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("user_name", QueryFlags::empty);
+
+    // `get_name` was renamed to `user_name`, but is still referenced by
+    // existing tooling.
+    db.add_alias("get_name", "user_name");
+
+    db.execute_query("user_name", &1, || String::from("Admin"));
+
+    let result = db.execute_query("get_name", &1, || String::from("Username"));
+    assert_eq!(result, String::from("Admin"));
+
+    assert!(db.contains("get_name", &1));
+    assert_eq!(db.query("get_name").name(), "user_name");
+}
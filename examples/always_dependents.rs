@@ -0,0 +1,67 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    ticks: Cell<u64>,
+    reports: Cell<usize>,
+}
+
+impl Context {
+    fn epoch(&self) -> u64 {
+        self.db.execute_query("epoch", &(), || 1000)
+    }
+
+    /// Reads the current time, which must never be cached.
+    fn now(&self) -> u64 {
+        self.db.execute_query("now", &(), || {
+            self.ticks.set(self.ticks.get() + 1);
+
+            self.epoch() + self.ticks.get()
+        })
+    }
+
+    fn report(&self) -> String {
+        self.db.execute_query("report", &(), || {
+            self.reports.set(self.reports.get() + 1);
+
+            format!("built at {}", self.now())
+        })
+    }
+
+    fn banner(&self) -> String {
+        self.db
+            .execute_query("banner", &(), || format!("== {} ==", self.report()))
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        ticks: Cell::new(0),
+        reports: Cell::new(0),
+    };
+
+    ctx.db.ensure_query_exists("epoch", QueryFlags::empty);
+    ctx.db.ensure_query_exists("now", || QueryFlags::ALWAYS);
+    ctx.db.ensure_query_exists("report", QueryFlags::empty);
+    ctx.db.ensure_query_exists("banner", QueryFlags::empty);
+
+    // Results which depend on `now` are recomputed on every access, all the
+    // way up, instead of returning a stale time.
+    assert_eq!(ctx.banner(), "== built at 1001 ==");
+    assert_eq!(ctx.banner(), "== built at 1002 ==");
+    assert_eq!(ctx.reports.get(), 2);
+
+    // The dependents of `now` are still recorded, while `now` is a leaf.
+    assert_eq!(ctx.db.dependencies_of("report", &()), [(
+        String::from("now"),
+        ResultKey::from_hashable(&())
+    )]);
+    assert!(ctx.db.dependencies_of("now", &()).is_empty());
+
+    // Results which don't depend on `now` are cached as usual.
+    assert_eq!(ctx.epoch(), 1000);
+    assert_eq!(ctx.db.stats().query("epoch").unwrap().misses, 1);
+}
@@ -0,0 +1,98 @@
+use std::sync::{Arc, Mutex};
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn len(&self, file: &str) -> usize {
+        self.db.execute_query("len", &file, || self.source(file).len())
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    let db = &ctx.db;
+    let notifications = Arc::new(Mutex::new(Vec::new()));
+
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    {
+        let notifications = Arc::clone(&notifications);
+        db.add_observer(move |changes: &ChangeSet| notifications.lock().unwrap().push(changes.clone()));
+    }
+
+    // A single insert notifies observers right away.
+    db.insert("source", &"main.rs", String::from("fn main() {}"));
+    assert_eq!(notifications.lock().unwrap().len(), 1);
+
+    // Switching branches touches many files at once, which are reported as a
+    // single change set, and committed as a single revision.
+    let before = db.current_revision();
+
+    db.batch(|db| {
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            db.insert("source", &file, String::new());
+        }
+
+        db.invalidate("source", &"main.rs");
+    });
+
+    {
+        let notifications = notifications.lock().unwrap();
+        let changes = &notifications[1];
+
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(changes.inserted.len(), 3);
+        assert_eq!(changes.invalidated, [(
+            String::from("source"),
+            ResultKey::from_hashable(&"main.rs")
+        )]);
+        assert_eq!(changes.revision, before.next());
+        assert_eq!(db.current_revision(), before.next());
+    }
+
+    // Results computed within a batch are still recomputed once their inputs
+    // change again within the same batch.
+    db.batch(|db| {
+        db.insert("source", &"d.rs", String::from("ab"));
+        assert_eq!(ctx.len("d.rs"), 2);
+
+        db.insert("source", &"d.rs", String::from("abc"));
+        assert_eq!(ctx.len("d.rs"), 3);
+    });
+
+    assert_eq!(ctx.len("d.rs"), 3);
+    assert_eq!(notifications.lock().unwrap().len(), 3);
+
+    // Mutations are never rolled back, so a batch which panics still reports
+    // the mutations made before the panic.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        db.batch(|db| {
+            db.insert("source", &"e.rs", String::new());
+            panic!("failed to switch branches");
+        });
+    }));
+
+    let _ = std::panic::take_hook();
+
+    assert!(result.is_err());
+    assert_eq!(db.get_cached::<_, String>("source", &"e.rs"), Some(String::new()));
+
+    let notifications = notifications.lock().unwrap();
+    assert_eq!(notifications.len(), 4);
+    assert_eq!(notifications[3].inserted, [(
+        String::from("source"),
+        ResultKey::from_hashable(&"e.rs")
+    )]);
+    assert_eq!(notifications[3].revision, db.current_revision());
+}
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use lume_architect::*;
+
+// Results are cloned whenever they are returned from the cache, so large
+// binary artifacts should be stored behind a reference-counted pointer, such
+// as `Arc<[u8]>` or `bytes::Bytes`, instead of a `Vec<u8>`.
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("object_file", QueryFlags::empty);
+
+    let emit = || -> Arc<[u8]> { vec![0x7f, b'E', b'L', b'F'].into() };
+
+    let first = db.execute_query("object_file", &"main.lm", emit);
+    let second = db.execute_query("object_file", &"main.lm", emit);
+
+    // Both results refer to the same buffer, which was never copied.
+    assert!(Arc::ptr_eq(&first, &second));
+}
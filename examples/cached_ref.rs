@@ -0,0 +1,25 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    let tokens = db.register_query::<&str, Vec<String>>("tokens", QueryFlags::empty());
+
+    tokens.execute(&db, &"main.lm", || {
+        "fn main ( ) { }".split(' ').map(String::from).collect()
+    });
+
+    // The tokens are borrowed from the database, instead of being cloned.
+    {
+        let all = db.get_ref::<_, Vec<String>>("tokens", &"main.lm").unwrap();
+        assert_eq!(all.len(), 6);
+
+        let first = CachedRef::map(all, |tokens| tokens[0].as_str());
+        assert_eq!(&*first, "fn");
+    }
+
+    assert!(tokens.get_ref(&db, &"lib.lm").is_none());
+
+    // Once all references are dropped, the database can be mutated again.
+    db.insert("tokens", &"lib.lm", Vec::<String>::new());
+    assert!(tokens.get_ref(&db, &"lib.lm").unwrap().is_empty());
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Arc::new(Database::new());
+    let done = Arc::new(AtomicBool::new(false));
+
+    db.ensure_query_exists("version", QueryFlags::empty);
+    db.ensure_query_exists("checksum", QueryFlags::empty);
+    db.insert("version", &(), 0u64);
+    db.insert("checksum", &(), 0u64);
+
+    // Keeps committing new versions, each along with its checksum, within a
+    // single batch.
+    let writer = {
+        let db = Arc::clone(&db);
+        let done = Arc::clone(&done);
+
+        thread::spawn(move || {
+            let mut version = 0u64;
+
+            while !done.load(Ordering::Relaxed) {
+                version += 1;
+
+                db.batch(|db| {
+                    db.insert("version", &(), version);
+                    db.insert("checksum", &(), version * 31);
+                });
+            }
+
+            version
+        })
+    };
+
+    for _ in 0..50 {
+        db.read_txn(|view| {
+            let revision = view.revision();
+            let version = view.get_cached::<_, u64>("version", &()).unwrap();
+
+            // Give the writer a chance to commit in between both lookups.
+            thread::sleep(Duration::from_micros(200));
+
+            let checksum = view.get_ref::<_, u64>("checksum", &()).unwrap();
+
+            assert_eq!(*checksum, version * 31);
+            assert_eq!(view.revision(), revision);
+        });
+    }
+
+    done.store(true, Ordering::Relaxed);
+
+    let version = writer.join().unwrap();
+
+    db.read_txn(|view| {
+        assert_eq!(view.get_cached::<_, u64>("version", &()), Some(version));
+        assert_eq!(view.get_cached::<_, u64>("checksum", &()), Some(version * 31));
+    });
+
+    // Transactions on other threads share the lock, so they don't wait for
+    // each other.
+    db.read_txn(|_| {
+        let db = Arc::clone(&db);
+        let reader = thread::spawn(move || db.read_txn(|view| view.get_cached::<_, u64>("version", &())));
+
+        assert_eq!(reader.join().unwrap(), Some(version));
+    });
+
+    // Within a transaction, the database itself serves cached results, while
+    // lookups which would have to compute a result fail, instead of waiting
+    // for the transaction to finish.
+    db.ensure_query_exists("double", QueryFlags::empty);
+
+    db.read_txn(|_| {
+        assert_eq!(db.execute_query("version", &(), || 0u64), version);
+        assert_eq!(
+            db.try_execute_query("double", &(), || version * 2),
+            Err(QueryError::ReadOnly {
+                query: String::from("double")
+            })
+        );
+    });
+
+    assert_eq!(db.execute_query("double", &(), || version * 2), version * 2);
+
+    // Mutations within a transaction panic, instead of deadlocking.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        db.read_txn(|_| db.insert("version", &(), 0u64));
+    }));
+
+    let _ = std::panic::take_hook();
+
+    assert!(result.is_err());
+    assert_eq!(db.get_cached::<_, u64>("version", &()), Some(version));
+}
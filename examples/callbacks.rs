@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("get_name", QueryFlags::empty);
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let misses = Arc::new(AtomicUsize::new(0));
+
+    {
+        let mut query = db.query_mut("get_name");
+
+        let counter = hits.clone();
+        query.on_hit(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let counter = misses.clone();
+        query.on_miss(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    for _ in 0..3 {
+        db.execute_query("get_name", &1, || String::from("Admin"));
+    }
+
+    assert_eq!(hits.load(Ordering::Relaxed), 2);
+    assert_eq!(misses.load(Ordering::Relaxed), 1);
+}
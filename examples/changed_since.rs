@@ -0,0 +1,29 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    db.insert("source", &"lib.lm", String::from("fn lib() {}"));
+
+    let observed = db.current_revision();
+    assert!(db.entries_changed_since("source", observed).is_empty());
+
+    db.insert("source", &"lib.lm", String::from("fn lib() { 1 }"));
+    db.insert("source", &"util.lm", String::from("fn util() {}"));
+
+    // Only results inserted after the observed revision are reprocessed.
+    let changed = db.entries_changed_since("source", observed);
+
+    assert_eq!(changed.len(), 2);
+    assert!(changed.contains(&ResultKey::from_hashable(&"lib.lm")));
+    assert!(changed.contains(&ResultKey::from_hashable(&"util.lm")));
+
+    let observed = db.current_revision();
+    db.insert("source", &"main.lm", String::from("fn main() { 1 }"));
+
+    assert_eq!(db.entries_changed_since("source", observed), vec![
+        ResultKey::from_hashable(&"main.lm")
+    ]);
+}
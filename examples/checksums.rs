@@ -0,0 +1,41 @@
+use std::hash::{Hash, Hasher};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lume_architect::*;
+
+/// Counter which is shared between clones, so mutating any clone mutates
+/// the cached result as well.
+#[derive(Debug, Clone, Default)]
+struct SharedCounter(Arc<AtomicUsize>);
+
+impl Hash for SharedCounter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.load(Ordering::Relaxed).hash(state);
+    }
+}
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("references", QueryFlags::empty);
+    db.enable_checksums::<SharedCounter>("references");
+
+    let counter = db.execute_query("references", &"main", SharedCounter::default);
+    assert!(db.get_cached::<_, SharedCounter>("references", &"main").is_some());
+
+    // Mutating the cached result through interior mutability is caught on
+    // the next access.
+    counter.0.fetch_add(1, Ordering::Relaxed);
+
+    if cfg!(debug_assertions) {
+        std::panic::set_hook(Box::new(|_| {}));
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            db.get_cached::<_, SharedCounter>("references", &"main")
+        }));
+        let _ = std::panic::take_hook();
+
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("was mutated after being cached"));
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lume_architect::*;
+
+const CHUNK_SIZE: usize = 100;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("symbols", QueryFlags::empty);
+    db.ensure_query_exists("count", QueryFlags::empty);
+
+    let computed = Arc::new(AtomicUsize::new(0));
+
+    let symbols_in_chunk = |index: usize| {
+        computed.fetch_add(1, Ordering::Relaxed);
+
+        (index * CHUNK_SIZE..(index + 1) * CHUNK_SIZE).collect::<Vec<_>>()
+    };
+
+    let symbols = db.execute_chunked("symbols", &"main", 10, symbols_in_chunk);
+    assert_eq!(symbols.len(), 1_000);
+    assert_eq!(computed.load(Ordering::Relaxed), 10);
+
+    // Only the invalidated chunk is recomputed.
+    assert!(db.invalidate_chunk("symbols", &"main", 4));
+
+    let symbols = db.execute_chunked("symbols", &"main", 10, symbols_in_chunk);
+    assert_eq!(symbols.len(), 1_000);
+    assert_eq!(computed.load(Ordering::Relaxed), 11);
+
+    // Results which depend on the invalidated chunk are invalidated as well.
+    let invalidated = Arc::new(AtomicUsize::new(0));
+    let observed = Arc::clone(&invalidated);
+    db.add_observer(move |changes| {
+        observed.fetch_add(changes.invalidated.len(), Ordering::Relaxed);
+    });
+
+    let count = || db.execute_chunked("symbols", &"main", 10, symbols_in_chunk).len();
+    assert_eq!(db.execute_query("count", &"main", count), 1_000);
+
+    assert!(db.invalidate_chunk("symbols", &"main", 4));
+    assert!(!db.contains("count", &"main"));
+    assert_eq!(invalidated.load(Ordering::Relaxed), 2);
+}
@@ -0,0 +1,65 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    checks: Cell<usize>,
+}
+
+impl Context {
+    /// Type-checks a single item within a file, keyed by the file and the
+    /// name of the item.
+    fn typecheck(&self, file: u32, item: &str) -> String {
+        let key = (file, item.to_string());
+
+        self.db.execute_query_keyed("typecheck", &key, || {
+            self.checks.set(self.checks.get() + 1);
+
+            format!("{file}::{item}: ok")
+        })
+    }
+
+    fn summary(&self, file: u32) -> String {
+        self.db.execute_query("summary", &file, || {
+            format!("{}, {}", self.typecheck(file, "main"), self.typecheck(file, "helper"))
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        checks: Cell::new(0),
+    };
+
+    ctx.db.ensure_query_exists("typecheck", QueryFlags::empty);
+    ctx.db.ensure_query_exists("summary", QueryFlags::empty);
+
+    ctx.summary(1);
+    ctx.summary(2);
+    assert_eq!(ctx.checks.get(), 4);
+
+    let query = ctx.db.query("typecheck");
+    let mut items = query
+        .keys_with_prefix(&(1u32,))
+        .cloned()
+        .collect::<Vec<(u32, String)>>();
+    items.sort();
+    assert_eq!(items, [(1, String::from("helper")), (1, String::from("main"))]);
+    drop(query);
+
+    // Everything derived from file 1 is invalidated, while file 2 is kept.
+    assert_eq!(ctx.db.clear_prefix::<(u32, String)>("typecheck", &(1u32,)), 2);
+    assert!(!ctx.db.contains("summary", &1u32));
+    assert!(ctx.db.contains("summary", &2u32));
+
+    ctx.summary(1);
+    ctx.summary(2);
+    assert_eq!(ctx.checks.get(), 6);
+
+    // The full key is a prefix of itself.
+    let key = (2u32, String::from("main"));
+    assert_eq!(ctx.db.clear_prefix::<(u32, String)>("typecheck", &key), 1);
+    assert_eq!(ctx.db.clear_prefix::<(u32, String)>("typecheck", &(3u32,)), 0);
+}
@@ -0,0 +1,26 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("tokens", QueryFlags::empty);
+    db.ensure_query_exists("parse", QueryFlags::empty);
+    db.ensure_query_exists("codegen", QueryFlags::empty);
+
+    db.execute_query("tokens", &"main.lm", || vec!["fn", "main"]);
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+    db.execute_query("codegen", &"main.lm", || vec![0xC3_u8]);
+
+    // The worker only needs the results of the front-end queries.
+    let worker = db.clone_subset(&["tokens", "parse"]);
+
+    assert_eq!(
+        worker.get_cached::<_, String>("parse", &"main.lm"),
+        Some(String::from("fn main() {}"))
+    );
+    assert!(worker.contains("tokens", &"main.lm"));
+    assert!(!worker.contains("codegen", &"main.lm"));
+
+    // Both databases are independent of each other.
+    worker.clear("parse");
+    assert!(db.contains("parse", &"main.lm"));
+}
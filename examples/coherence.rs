@@ -0,0 +1,35 @@
+use lume_architect::*;
+
+fn main() {
+    // Dependencies are tracked, but results are recomputed once they depend on
+    // more than a single input, to check that both paths stay coherent.
+    let db = Database::new();
+    db.ensure_query_exists("coherence::sum", QueryFlags::empty);
+    db.set_max_dependencies("coherence::sum", 1);
+
+    for seed in 0..64 {
+        let operations = Operation::random_sequence(seed, 256);
+
+        if let Err(err) = db.check_coherence(operations) {
+            panic!("seed {seed}: {err}");
+        }
+    }
+
+    // Operations can also be written by hand, such as to reproduce a failure.
+    Database::new()
+        .check_coherence([
+            Operation::Insert { input: 1, value: 10 },
+            Operation::Execute { derived: 0 },
+            Operation::Invalidate { input: 1 },
+            Operation::Execute { derived: 0 },
+            Operation::Insert { input: 2, value: 5 },
+            Operation::ExecuteChunked { derived: 1 },
+            Operation::InvalidateChunk { derived: 1, chunk: 0 },
+            Operation::ReleaseSoft,
+            Operation::Insert { input: 2, value: 6 },
+            Operation::ExecuteChunked { derived: 1 },
+            Operation::MapReduce { derived: 1 },
+            Operation::Merge { derived: 2 },
+        ])
+        .unwrap();
+}
@@ -0,0 +1,29 @@
+use lume_architect::*;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Diagnostic(String);
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", QueryFlags::empty);
+    db.ensure_query_exists("resolve", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    db.execute_query("parse", &"main.lm", || Diagnostic(String::from("expected `;`")));
+    db.execute_query("resolve", &"main.lm", || Diagnostic(String::from("unknown type `Foo`")));
+    db.execute_query("resolve", &"lib.lm", || Diagnostic(String::from("unused import")));
+    db.execute_query("len", &"main.lm", || 12_usize);
+
+    // Diagnostics are gathered from every query, while other results are
+    // skipped.
+    let diagnostics = db.collect_values::<Diagnostic>();
+
+    assert_eq!(diagnostics, [
+        Diagnostic(String::from("expected `;`")),
+        Diagnostic(String::from("unknown type `Foo`")),
+        Diagnostic(String::from("unused import")),
+    ]);
+
+    assert_eq!(db.collect_values::<usize>(), [12]);
+    assert!(db.collect_values::<String>().is_empty());
+}
@@ -0,0 +1,17 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("square", QueryFlags::empty);
+
+    for i in 0..1_000_u64 {
+        db.execute_query("square", &i, || i * i);
+    }
+
+    db.clear("square");
+
+    let reclaimed = db.compact();
+    assert!(reclaimed > 0);
+
+    println!("reclaimed {reclaimed} bytes");
+}
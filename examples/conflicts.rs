@@ -0,0 +1,46 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+
+    // Two plugins register the same query, without knowing about each other.
+    db.add_query("resolve_imports", QueryFlags::empty(), ConflictPolicy::Error)
+        .unwrap();
+
+    db.add_query(
+        "resolve_imports",
+        QueryFlags::empty(),
+        ConflictPolicy::IgnoreIfFlagsMatch,
+    )
+    .unwrap();
+
+    let err = db
+        .add_query(
+            "resolve_imports",
+            QueryFlags::ALWAYS,
+            ConflictPolicy::IgnoreIfFlagsMatch,
+        )
+        .unwrap_err();
+
+    assert_eq!(err, QueryError::DuplicateQuery {
+        query: String::from("resolve_imports")
+    });
+
+    db.ensure_query_exists("check", QueryFlags::empty);
+    db.query_mut("resolve_imports")
+        .set_key_normalizer(|key: &i32| key.abs());
+    db.execute_query("check", &0, || db.execute_query_keyed("resolve_imports", &0, || 1) + 1);
+
+    // Replacing a query discards all of its results, along with the results
+    // which depend on them, and resets its settings.
+    db.add_query("resolve_imports", QueryFlags::ALWAYS, ConflictPolicy::Replace)
+        .unwrap();
+
+    assert!(!db.contains("resolve_imports", &0));
+    assert!(!db.contains("check", &0));
+    assert_eq!(db.query("resolve_imports").flags(), QueryFlags::ALWAYS);
+
+    // Without the key normalizer, keys no longer have to be given through
+    // `execute_query_keyed`.
+    assert_eq!(db.execute_query("resolve_imports", &-1, || 2), 2);
+}
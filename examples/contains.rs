@@ -0,0 +1,21 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("highlight", QueryFlags::empty);
+
+    assert!(!db.contains("missing", &"main.lm"));
+    assert!(!db.contains("source", &"main.lm"));
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    assert!(db.contains("source", &"main.lm"));
+    assert!(!db.contains("source", &"lib.lm"));
+
+    // Checking whether a result is cached doesn't depend on its value.
+    db.execute_query("highlight", &"main.lm", || db.contains("source", &"main.lm"));
+    assert!(db.dependencies_of("highlight", &"main.lm").is_empty());
+
+    assert!(db.invalidate("source", &"main.lm"));
+    assert!(!db.contains("source", &"main.lm"));
+}
@@ -0,0 +1,36 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    // `type Alias = Other; type Other = Alias;` refers back to itself.
+    fn resolve_alias(&self, name: &'static str) -> QueryResult<String> {
+        self.db.try_execute_query("resolve_alias", &name, || {
+            let target = if name == "Alias" { "Other" } else { "Alias" };
+
+            match self.resolve_alias(target) {
+                Ok(resolved) => resolved,
+                Err(err) => {
+                    if let QueryError::Cycle { query, path, .. } = &err {
+                        assert_eq!(query, "resolve_alias");
+                        assert_eq!(path.len(), 2);
+                    }
+
+                    err.to_string()
+                }
+            }
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("resolve_alias", QueryFlags::empty);
+
+    let message = ctx.resolve_alias("Alias").unwrap();
+
+    println!("{message}");
+    assert!(message.starts_with("cycle detected"));
+}
@@ -0,0 +1,31 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // The body executes an ad-hoc query against the same database which
+    // caches the method.
+    #[cached_query(db_ident = db)]
+    pub fn line_count(&self, file: &'static str) -> usize {
+        db.ensure_query_exists("source", QueryFlags::empty);
+
+        let source = db.execute_query("source", &file, || String::from("fn main() {\n}"));
+
+        source.lines().count()
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+
+    assert_eq!(ctx.line_count("main.lm"), 2);
+    assert!(ctx.db.contains("source", &"main.lm"));
+}
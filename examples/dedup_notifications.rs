@@ -0,0 +1,33 @@
+use std::sync::{Arc, Mutex};
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("config", QueryFlags::empty);
+
+    // Saving a file without changing it, or reloading an unchanged config,
+    // shouldn't notify anyone.
+    db.enable_equality_checks::<String>("source");
+    db.enable_checksums::<Vec<String>>("config");
+
+    let inserted = Arc::new(Mutex::new(Vec::new()));
+
+    {
+        let inserted = Arc::clone(&inserted);
+        db.add_observer(move |changes: &ChangeSet| inserted.lock().unwrap().extend(changes.inserted.clone()));
+    }
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    db.insert("source", &"main.lm", String::from("fn main() { loop {} }"));
+
+    db.insert("config", &(), vec![String::from("--release")]);
+    db.insert("config", &(), vec![String::from("--release")]);
+
+    let inserted = inserted.lock().unwrap();
+    let names = inserted.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+
+    assert_eq!(names, ["source", "source", "config"]);
+}
@@ -0,0 +1,19 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", QueryFlags::empty);
+    db.ensure_query_exists("lower", QueryFlags::empty);
+
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+
+    // The lowered item is computed outside of the database, but should be
+    // invalidated along with the parsed file.
+    db.insert("lower", &"main", String::from("main: fn()"));
+    db.declare_dependency(("lower", &"main"), ("parse", &"main.lm"));
+
+    assert_eq!(db.dependencies_of("lower", &"main").len(), 1);
+
+    assert!(db.invalidate("parse", &"main.lm"));
+    assert!(!db.contains("lower", &"main"));
+}
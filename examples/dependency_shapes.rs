@@ -0,0 +1,63 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn parse(&self, file: &str) -> Vec<String> {
+        self.db.execute_query("parse", &file, || {
+            self.source(file).split_whitespace().map(String::from).collect()
+        })
+    }
+
+    fn program(&self) -> usize {
+        self.db.execute_query("program", &(), || {
+            ["main.lm", "lib.lm"].iter().map(|file| self.parse(file).len()).sum()
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+
+    for name in ["source", "parse", "program"] {
+        ctx.db.ensure_query_exists(name, QueryFlags::empty);
+    }
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main"));
+    ctx.db.insert("source", &"lib.lm", String::from("fn a fn b"));
+
+    assert_eq!(ctx.program(), 6);
+
+    for shape in ctx.db.dependency_shapes() {
+        println!(
+            "{}: {} results, depth {}, fan-out {:.1}, {} dependents",
+            shape.query,
+            shape.results,
+            shape.max_depth,
+            shape.average_fan_out(),
+            shape.dependents
+        );
+    }
+
+    let shapes = ctx.db.dependency_shapes();
+
+    assert_eq!(shapes[0], DependencyShape {
+        query: String::from("parse"),
+        results: 2,
+        max_depth: 1,
+        dependencies: 2,
+        dependents: 1,
+    });
+
+    assert_eq!(shapes[1].max_depth, 2);
+    assert!((shapes[1].average_fan_out() - 2.0).abs() < f64::EPSILON);
+
+    assert_eq!(shapes[2].dependents, 2);
+    assert_eq!(shapes[2].average_fan_out(), 0.0);
+}
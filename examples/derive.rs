@@ -18,6 +18,13 @@ impl Context {
 
         "A".repeat(count)
     }
+
+    // arguments are borrowed for the cache key, so they can be moved
+    // into the result.
+    #[cached_query]
+    pub fn greeting(&self, name: String, suffix: String) -> String {
+        name + &suffix
+    }
 }
 
 fn main() {
@@ -27,4 +34,10 @@ fn main() {
     let r2 = ctx.slow_method(10);
 
     assert_eq!(r1, r2);
+
+    let g1 = ctx.greeting(String::from("Hello"), String::from("!"));
+    let g2 = ctx.greeting(String::from("Hello"), String::from("?"));
+
+    assert_eq!(g1, "Hello!");
+    assert_eq!(g2, "Hello?");
 }
@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // iterating a `HashSet` has no defined order, so the result depends on
+    // the random state of each set.
+    #[cached_query(check_determinism)]
+    pub fn symbols(&self, count: usize) -> Vec<usize> {
+        (0..count).collect::<HashSet<_>>().into_iter().collect()
+    }
+
+    #[cached_query(check_determinism)]
+    pub fn sorted_symbols(&self, count: usize) -> Vec<usize> {
+        let mut symbols = self.symbols(count);
+        symbols.sort_unstable();
+
+        symbols
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db().enable_determinism_checks();
+
+    ctx.sorted_symbols(64);
+
+    let report = ctx.db().nondeterminism_report();
+
+    assert_eq!(report.len(), 1);
+    assert!(report[0].query.ends_with("symbols"));
+}
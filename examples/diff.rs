@@ -0,0 +1,73 @@
+use std::collections::BTreeSet;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn files(&self) -> BTreeSet<&'static str> {
+        self.db.execute_query("files", &(), BTreeSet::new)
+    }
+
+    fn exports(&self) -> (BTreeSet<&'static str>, Diff<&'static str>) {
+        self.db.execute_query_diff("exports", &"main", || self.files())
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    let db = &ctx.db;
+
+    db.ensure_query_exists("files", QueryFlags::empty);
+    db.ensure_query_exists("exports", QueryFlags::empty);
+    db.insert("files", &(), BTreeSet::from(["a", "b"]));
+
+    let (_, diff) = ctx.exports();
+    assert_eq!(diff.added, vec!["a", "b"]);
+
+    // Unchanged results produce an empty diff.
+    let (_, diff) = ctx.exports();
+    assert!(diff.is_empty());
+
+    // After the input changes, only the changes are reported.
+    db.insert("files", &(), BTreeSet::from(["b", "c"]));
+
+    let (_, diff) = ctx.exports();
+    assert_eq!(diff.added, vec!["c"]);
+    assert_eq!(diff.removed, vec!["a"]);
+
+    // Changes are kept until they are diffed, even if the result is
+    // recomputed by a lookup which doesn't diff, possibly more than once.
+    db.insert("files", &(), BTreeSet::from(["c", "d"]));
+    db.execute_query("exports", &"main", || ctx.files());
+
+    db.insert("files", &(), BTreeSet::from(["c", "d", "e"]));
+    db.execute_query("exports", &"main", || ctx.files());
+
+    let (_, diff) = ctx.exports();
+    assert_eq!(diff.added, vec!["d", "e"]);
+    assert_eq!(diff.removed, vec!["b"]);
+
+    // The change of a cached result can also be taken without executing the
+    // query, which marks it as diffed as well.
+    db.insert("files", &(), BTreeSet::from(["e"]));
+    db.execute_query("exports", &"main", || ctx.files());
+
+    let diff = db.take_diff::<_, BTreeSet<&str>>("exports", &"main").unwrap();
+    assert_eq!(diff.removed, vec!["c", "d"]);
+    assert!(
+        db.take_diff::<_, BTreeSet<&str>>("exports", &"main")
+            .unwrap()
+            .is_empty()
+    );
+
+    // Once the result is removed from the cache, there is nothing to compare
+    // against, so all items are reported as added.
+    db.clear("exports");
+
+    let (_, diff) = ctx.exports();
+    assert_eq!(diff.added, vec!["e"]);
+    assert!(diff.removed.is_empty());
+}
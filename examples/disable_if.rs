@@ -0,0 +1,63 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    bypass: Cell<bool>,
+    runs: Cell<usize>,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // debug builds run the body on every call, which makes it easier to
+    // step through.
+    #[cached_query(disable_if = cfg!(debug_assertions))]
+    pub fn layout(&self, width: usize) -> String {
+        "-".repeat(width)
+    }
+
+    // the condition may also be checked at runtime.
+    #[cached_query(result, disable_if = self.bypass.get())]
+    pub fn parse(&self, source: &str) -> Result<i64, String> {
+        self.runs.set(self.runs.get() + 1);
+
+        if source.is_empty() {
+            return Err(String::from("empty source"));
+        }
+
+        source.trim().parse::<i64>().map_err(|err| err.to_string())
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        bypass: Cell::new(false),
+        runs: Cell::new(0),
+    };
+
+    assert_eq!(ctx.layout(3), "---");
+    assert_eq!(ctx.db.stats().misses, u64::from(!cfg!(debug_assertions)));
+
+    assert_eq!(ctx.parse("42"), Ok(42));
+    assert_eq!(ctx.parse("42"), Ok(42));
+    assert_eq!(ctx.runs.get(), 1);
+
+    // While bypassed, the body is run on every call, and nothing is cached.
+    ctx.bypass.set(true);
+
+    assert_eq!(ctx.parse("7"), Ok(7));
+    assert_eq!(ctx.parse("7"), Ok(7));
+    assert_eq!(ctx.parse(""), Err(String::from("empty source")));
+    assert_eq!(ctx.runs.get(), 4);
+
+    ctx.bypass.set(false);
+    assert!(ctx.db.contains("disable_if::Context::parse", &("42",)));
+    assert!(!ctx.db.contains("disable_if::Context::parse", &("7",)));
+}
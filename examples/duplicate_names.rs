@@ -0,0 +1,87 @@
+use lume_architect::*;
+
+struct Shapes {
+    db: Database,
+}
+
+impl DatabaseContext for Shapes {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+fn square(shapes: &Shapes, side: u64) -> u64 {
+    // functions nested within other functions are named after the module,
+    // so both of these map to the same query.
+    #[cached_query(db_expr = shapes, key = side)]
+    fn area(shapes: &Shapes, side: u64) -> u64 {
+        side * side
+    }
+
+    area(shapes, side)
+}
+
+fn triangle(shapes: &Shapes, side: u64) -> u64 {
+    #[cached_query(db_expr = shapes, key = side)]
+    fn area(shapes: &Shapes, side: u64) -> u64 {
+        side * side / 2
+    }
+
+    area(shapes, side)
+}
+
+trait Perimeter {
+    fn perimeter(&self, side: u64) -> u64;
+}
+
+impl Shapes {
+    #[cached_query]
+    fn perimeter(&self, side: u64) -> u64 {
+        side * 4
+    }
+}
+
+impl Perimeter for Shapes {
+    // Methods are named after the type of their receiver, so this maps to the
+    // same query as the inherent method.
+    #[cached_query]
+    fn perimeter(&self, side: u64) -> u64 {
+        side * 3
+    }
+}
+
+fn main() {
+    let shapes = Shapes { db: Database::new() };
+
+    if !cfg!(debug_assertions) {
+        assert!(shapes.db.check_query_names().is_empty());
+        return;
+    }
+
+    // Free functions are registered before they are executed.
+    let duplicates = shapes.db.check_query_names();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].query, "duplicate_names::area");
+    assert_eq!(duplicates[0].sites.len(), 2);
+
+    println!("{}", duplicates[0]);
+    assert!(duplicates[0].sites[0].contains("duplicate_names.rs:17"));
+    assert!(duplicates[0].sites[1].contains("duplicate_names.rs:26"));
+
+    // Methods are registered once they are executed.
+    assert_eq!(square(&shapes, 4), 16);
+    assert_eq!(triangle(&shapes, 4), 16);
+    assert_eq!(Shapes::perimeter(&shapes, 4), 16);
+    assert_eq!(Perimeter::perimeter(&shapes, 4), 16);
+
+    let duplicates = shapes.db.check_query_names();
+    assert_eq!(duplicates.len(), 2);
+
+    let perimeter = duplicates
+        .iter()
+        .find(|duplicate| duplicate.query.ends_with("Shapes::perimeter"))
+        .unwrap();
+
+    assert!(perimeter.sites[0].contains("duplicate_names.rs:39"));
+    assert!(perimeter.sites[1].contains("duplicate_names.rs:48"));
+}
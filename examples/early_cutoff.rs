@@ -0,0 +1,70 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    summaries: Cell<usize>,
+}
+
+fn source(db: &Database, file: &'static str) -> String {
+    db.execute_query("source", &file, String::new)
+}
+
+fn count_lines(db: &Database, file: &'static str) -> usize {
+    source(db, file).lines().count()
+}
+
+impl Context {
+    fn line_count(&self, file: &'static str) -> usize {
+        self.db
+            .execute_query_keyed("line_count", &file, || count_lines(&self.db, file))
+    }
+
+    fn summary(&self, file: &'static str) -> String {
+        self.db.execute_query("summary", &file, || {
+            self.summaries.set(self.summaries.get() + 1);
+
+            format!("{file}: {} lines", self.line_count(file))
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        summaries: Cell::new(0),
+    };
+
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("line_count", QueryFlags::empty);
+    ctx.db.ensure_query_exists("summary", QueryFlags::empty);
+
+    // Line counts are cheap to hash, so they can be used as fingerprints.
+    // Since they can be recomputed from their key alone, they are verified
+    // whenever a result which depends on them is requested.
+    ctx.db.enable_checksums::<usize>("line_count");
+    ctx.db
+        .query_mut("line_count")
+        .set_recompute(|db, file: &&'static str| count_lines(db, file));
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    assert_eq!(ctx.summary("main.lm"), "main.lm: 1 lines");
+
+    // Changing the source without changing the line count stops the dirty
+    // wave at `line_count`, so the summary is reused, even though only the
+    // summary itself is requested.
+    ctx.db
+        .insert("source", &"main.lm", String::from("fn main() { loop {} }"));
+    assert!(ctx.db.is_dirty("summary", &"main.lm"));
+
+    assert_eq!(ctx.summary("main.lm"), "main.lm: 1 lines");
+    assert_eq!(ctx.summaries.get(), 1);
+    assert!(!ctx.db.is_dirty("line_count", &"main.lm"));
+
+    // Changing the line count recomputes the summary.
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {\n}"));
+
+    assert_eq!(ctx.summary("main.lm"), "main.lm: 2 lines");
+    assert_eq!(ctx.summaries.get(), 2);
+}
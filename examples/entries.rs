@@ -0,0 +1,33 @@
+use lume_architect::*;
+
+fn main() {
+    let mut query = Query::new(String::from("references"), QueryFlags::empty());
+
+    // Vacant entries are filled in place.
+    let count = query.entry::<_, u32>(&"main").unwrap().or_insert_with(|| 1);
+    assert_eq!(*count, 1);
+
+    let generation = query.generation(&"main").unwrap();
+
+    // Occupied entries are kept, unless they are modified.
+    assert_eq!(*query.entry::<_, u32>(&"main").unwrap().or_insert(10), 1);
+    assert_eq!(query.generation(&"main"), Some(generation));
+
+    query.entry::<_, u32>(&"main").unwrap().and_modify(|count| *count += 1);
+
+    assert_eq!(query.get::<_, u32>(&"main"), Some(&2));
+    assert!(query.generation(&"main").unwrap() > generation);
+
+    // Entries of another type are rejected.
+    assert!(matches!(
+        query.entry::<_, String>(&"main"),
+        Err(QueryError::TypeMismatch { .. })
+    ));
+
+    let Entry::Occupied(entry) = query.entry::<_, u32>(&"main").unwrap() else {
+        panic!("expected an occupied entry");
+    };
+
+    assert_eq!(entry.remove(), 2);
+    assert!(query.is_empty());
+}
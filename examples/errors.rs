@@ -0,0 +1,45 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    attempts: Cell<usize>,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // downloading metadata is slow and the registry is down, so only try
+    // twice before giving up.
+    #[cached_query(result, max_retries = 1, retry_after = Duration::from_millis(50))]
+    pub fn download(&self, package: &'static str) -> Result<String, String> {
+        self.attempts.set(self.attempts.get() + 1);
+
+        Err(format!("could not download `{package}`"))
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        attempts: Cell::new(0),
+    };
+
+    for _ in 0..5 {
+        assert!(ctx.download("std").is_err());
+    }
+
+    assert_eq!(ctx.attempts.get(), 2);
+
+    // once the cached error has expired, the download is attempted again.
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(ctx.download("std").is_err());
+
+    assert_eq!(ctx.attempts.get(), 3);
+}
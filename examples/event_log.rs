@@ -0,0 +1,96 @@
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+
+use lume_architect::*;
+
+/// Buffer which is shared with the panic hook.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn line_count(&self, file: &str) -> usize {
+        self.db.execute_query("line_count", &file, || {
+            let count = self.source(file).lines().count();
+            assert!(count < 3, "line count of {file} is off");
+
+            count
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("line_count", QueryFlags::empty);
+
+    ctx.db.enable_key_labels();
+    ctx.db.enable_event_log(1024);
+
+    ctx.db.label_key("source", &"main.lm", || String::from("main.lm"));
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.db.label_key("line_count", &"main.lm", || String::from("main.lm"));
+    assert_eq!(ctx.line_count("main.lm"), 1);
+
+    ctx.db
+        .insert("source", &"main.lm", String::from("fn a() {}\nfn b() {}\nfn c() {}"));
+    ctx.db.invalidate("source", &"main.lm");
+    ctx.db
+        .insert("source", &"main.lm", String::from("fn a() {}\nfn b() {}\nfn c() {}"));
+
+    // When a query misbehaves, the history which led up to it is dumped.
+    let dump = SharedBuffer::default();
+
+    std::panic::set_hook(Box::new(|_| {}));
+    ctx.db.install_panic_dump(dump.clone());
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| ctx.line_count("main.lm")));
+    let _ = std::panic::take_hook();
+
+    assert!(result.is_err());
+
+    let dump = String::from_utf8(dump.0.lock().unwrap().clone()).unwrap();
+    println!("{dump}");
+
+    let mut written = Vec::new();
+    ctx.db.dump_event_log(&mut written).unwrap();
+    assert_eq!(dump.as_bytes(), written);
+
+    let kinds = ctx.db.event_log().iter().map(|event| event.kind).collect::<Vec<_>>();
+
+    assert!(matches!(kinds[..], [
+        EventKind::Inserted,
+        EventKind::Executed { .. },
+        EventKind::Inserted,
+        EventKind::Invalidated,
+        EventKind::Invalidated,
+        EventKind::Inserted,
+    ]));
+
+    // Every invalidation records the result which caused it.
+    assert!(dump.contains("invalidated `source` with key `main.lm`, since it was invalidated"));
+    assert!(
+        dump.contains("invalidated `line_count` with key `main.lm`, since `source` with key `main.lm` was invalidated")
+    );
+
+    let executed = &ctx.db.event_log()[1];
+    assert_eq!(executed.reason, Some(Reason::Missing));
+}
@@ -0,0 +1,123 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &'static str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn line_count(&self, file: &'static str) -> usize {
+        self.db
+            .execute_query("line_count", &file, || self.source(file).lines().count())
+    }
+
+    fn config(&self) -> bool {
+        self.db.execute_query("config", &(), || false)
+    }
+
+    fn summary(&self, file: &'static str) -> String {
+        self.db.execute_query("summary", &file, || {
+            format!("{file}: {} lines, release: {}", self.line_count(file), self.config())
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    let db = &ctx.db;
+
+    for name in ["source", "line_count", "config", "summary"] {
+        db.ensure_query_exists(name, QueryFlags::empty);
+    }
+
+    db.enable_key_labels();
+    db.enable_event_log(64);
+    db.label_key("source", &"main.lm", || String::from("main.lm"));
+    db.label_key("line_count", &"main.lm", || String::from("main.lm"));
+
+    assert_eq!(db.explain("summary", &"main.lm").verdict, Verdict::Computed);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.summary("main.lm");
+
+    let explanation = db.explain("summary", &"main.lm");
+
+    assert_eq!(explanation.verdict, Verdict::Reused);
+    assert!(explanation.causes.is_empty());
+
+    // Changing the source is traced through the line count, while the
+    // unchanged config isn't mentioned.
+    db.insert("source", &"main.lm", String::from("fn main() {}\n"));
+
+    let explanation = db.explain("summary", &"main.lm");
+    println!("{explanation}");
+
+    assert_eq!(explanation.verdict, Verdict::Verified);
+
+    let causes = explanation
+        .causes
+        .iter()
+        .map(|cause| (cause.query.as_str(), cause.label.as_deref(), cause.kind, cause.depth))
+        .collect::<Vec<_>>();
+
+    assert_eq!(causes, [
+        ("line_count", Some("main.lm"), CauseKind::Verified, 0),
+        ("source", Some("main.lm"), CauseKind::Changed, 1),
+    ]);
+
+    // Results which were discarded are recomputed.
+    db.invalidate("config", &());
+
+    let explanation = db.explain("summary", &"main.lm");
+    assert_eq!(explanation.verdict, Verdict::Computed);
+
+    ctx.summary("main.lm");
+
+    let explanation = db.explain("summary", &"main.lm");
+    println!("{explanation}");
+
+    assert_eq!(explanation.verdict, Verdict::Reused);
+
+    // The event log records why the result was computed and invalidated.
+    let history = explanation
+        .history
+        .iter()
+        .map(|event| (event.kind, event.reason.clone()))
+        .filter(|(kind, _)| !matches!(kind, EventKind::Executed { .. }))
+        .collect::<Vec<_>>();
+
+    assert_eq!(history, [(
+        EventKind::Invalidated,
+        Some(Reason::Cascaded(Culprit {
+            query: String::from("config"),
+            key: ResultKey::from_hashable(&()),
+            label: None,
+        }))
+    )]);
+
+    let reasons = explanation
+        .history
+        .iter()
+        .filter(|event| matches!(event.kind, EventKind::Executed { .. }))
+        .map(|event| event.reason.clone())
+        .collect::<Vec<_>>();
+
+    assert_eq!(reasons, [Some(Reason::Missing), Some(Reason::Missing)]);
+
+    // Results which are recomputed, since a dependency changed, record the
+    // result which changed.
+    db.insert("source", &"main.lm", String::from("fn main() {}\n\n"));
+    ctx.summary("main.lm");
+
+    let explanation = db.explain("summary", &"main.lm");
+    let recomputed = explanation.history.last().unwrap();
+
+    assert!(matches!(recomputed.kind, EventKind::Executed { .. }));
+    assert!(matches!(
+        &recomputed.reason,
+        Some(Reason::Changed(culprit)) if culprit.query == "source" && culprit.label.as_deref() == Some("main.lm")
+    ));
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+use lume_architect::*;
+
+const EDGES: [(u32, u32); 4] = [(0, 1), (1, 2), (2, 0), (2, 3)];
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    // Nodes which are reachable from the given node, which is computed by
+    // re-entering the query through the cycle between nodes 0, 1 and 2.
+    fn reachable(&self, node: u32) -> BTreeSet<u32> {
+        self.db.execute_query("reachable", &node, || {
+            let mut reachable = BTreeSet::from([node]);
+
+            for (_, to) in EDGES.iter().filter(|(from, _)| *from == node) {
+                reachable.extend(self.reachable(*to));
+            }
+
+            reachable
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db
+        .ensure_query_exists("reachable", || QueryFlags::ALWAYS | QueryFlags::RECURSIVE);
+
+    // Seed every node with a provisional result, which is returned whenever
+    // the query is re-entered with the same node.
+    for node in 0..4_u32 {
+        ctx.db.insert("reachable", &node, BTreeSet::from([node]));
+    }
+
+    let mut previous = BTreeSet::new();
+
+    loop {
+        let reachable = ctx.reachable(0);
+
+        if reachable == previous {
+            break;
+        }
+
+        previous = reachable;
+    }
+
+    assert_eq!(previous, BTreeSet::from([0, 1, 2, 3]));
+
+    // Keys without a provisional result still fail with a cycle.
+    ctx.db.ensure_query_exists("unseeded", || QueryFlags::RECURSIVE);
+
+    let result = ctx.db.execute_query("unseeded", &0_u32, || {
+        ctx.db.try_execute_query("unseeded", &0_u32, || 0_u32)
+    });
+
+    assert!(matches!(result, Err(QueryError::Cycle { .. })));
+}
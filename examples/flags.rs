@@ -0,0 +1,19 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+
+    // One call site adds the query without any flags...
+    db.ensure_query_exists("now", QueryFlags::empty);
+    db.execute_query("now", &(), || 1);
+
+    // ...while another call site requires it to always be recomputed.
+    db.ensure_query_exists("now", || QueryFlags::ALWAYS);
+
+    assert_eq!(db.query("now").flags(), QueryFlags::ALWAYS);
+    assert_eq!(db.execute_query("now", &(), || 2), 2);
+
+    // Flags are only ever added, never removed.
+    db.ensure_query_exists("now", QueryFlags::empty);
+    assert_eq!(db.query("now").flags(), QueryFlags::ALWAYS);
+}
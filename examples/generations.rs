@@ -0,0 +1,38 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    let generation = |name: &str| db.query(name).generation(&"main.lm");
+    let len = || {
+        db.execute_query("len", &"main.lm", || {
+            db.execute_query("source", &"main.lm", String::new).len()
+        })
+    };
+
+    assert_eq!(generation("len"), None);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    assert_eq!(len(), 12);
+
+    let first = generation("len").unwrap();
+
+    // Cache hits don't recompute the result.
+    assert_eq!(len(), 12);
+    assert_eq!(generation("len"), Some(first));
+
+    db.insert("source", &"main.lm", String::from("fn main() { 1 }"));
+    assert_eq!(len(), 15);
+
+    let second = generation("len").unwrap();
+    assert!(second > first);
+
+    // Generations keep increasing after the query is cleared.
+    db.clear("len");
+    assert_eq!(generation("len"), None);
+
+    assert_eq!(len(), 15);
+    assert!(generation("len").unwrap() > second);
+}
@@ -0,0 +1,104 @@
+use std::fmt::Display;
+use std::hash::Hash;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    counter: usize,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    pub fn first<'a, T>(&self, items: &'a [T]) -> Option<String>
+    where
+        T: Hash + Display + 'a,
+    {
+        items.first().map(ToString::to_string)
+    }
+
+    #[cached_query]
+    pub fn describe<T>(&self, value: T) -> String
+    where
+        T: Hash + Display,
+    {
+        format!("<{value}>")
+    }
+
+    #[cached_query]
+    pub fn display(&self, value: impl Hash + Display) -> String {
+        value.to_string()
+    }
+
+    #[cached_query]
+    pub fn sum(&self, mut values: Vec<u32>) -> u32 {
+        values.push(1);
+        values.into_iter().sum()
+    }
+
+    #[cached_query]
+    pub fn size_of<T: 'static>(&self) -> usize {
+        std::mem::size_of::<T>()
+    }
+
+    #[cached_query]
+    pub fn repeat<const N: usize>(&self, value: char) -> String {
+        value.to_string().repeat(N)
+    }
+
+    // the database is borrowed separately from the rest of `self`, so the
+    // body is free to mutate other fields.
+    #[cached_query(db_expr = &self.db)]
+    pub fn bump(&mut self, amount: usize) -> usize {
+        self.counter += amount;
+        self.counter
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Handle<'db> {
+    db: &'db Database,
+}
+
+impl DatabaseContext for Handle<'_> {
+    fn db(&self) -> &Database {
+        self.db
+    }
+}
+
+impl Handle<'_> {
+    #[cached_query]
+    pub fn double(self, value: usize) -> usize {
+        value * 2
+    }
+}
+
+fn main() {
+    let mut ctx = Context {
+        db: Database::new(),
+        counter: 0,
+    };
+
+    assert_eq!(ctx.first(&["abc", "de"]), Some(String::from("abc")));
+    assert_eq!(ctx.first(&[1, 2]), Some(String::from("1")));
+    assert_eq!(ctx.describe(1), "<1>");
+    assert_eq!(ctx.describe("a"), "<a>");
+    assert_eq!(ctx.display(2), "2");
+    assert_eq!(ctx.display('c'), "c");
+    assert_eq!(ctx.sum(vec![1, 2]), 4);
+    assert_eq!(ctx.size_of::<u8>(), 1);
+    assert_eq!(ctx.size_of::<u64>(), 8);
+    assert_eq!(ctx.repeat::<2>('a'), "aa");
+    assert_eq!(ctx.repeat::<3>('a'), "aaa");
+    assert_eq!(ctx.bump(2), 2);
+    assert_eq!(ctx.bump(2), 2);
+
+    let handle = Handle { db: &ctx.db };
+    assert_eq!(handle.double(4), 8);
+}
@@ -0,0 +1,27 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    // Nothing is computed by a lookup.
+    assert_eq!(db.get_cached::<_, usize>("len", &"main.lm"), None);
+    assert_eq!(db.get_cached::<_, usize>("missing", &"main.lm"), None);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+
+    let len = db.execute_query("len", &"main.lm", || {
+        db.get_cached::<_, String>("source", &"main.lm")
+            .map_or(0, |source| source.len())
+    });
+
+    assert_eq!(len, 12);
+    assert_eq!(db.get_cached::<_, usize>("len", &"main.lm"), Some(12));
+    assert_eq!(db.get_cached::<_, String>("len", &"main.lm"), None);
+
+    // Lookups aren't recorded as dependencies.
+    assert!(db.dependencies_of("len", &"main.lm").is_empty());
+
+    assert_eq!(db.peek("source", &"main.lm", String::len), Some(12));
+}
@@ -0,0 +1,21 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    let get_name = db.register_query::<u32, String>("get_name", QueryFlags::empty());
+
+    let _ = get_name.execute(&db, &1, || String::from("Admin"));
+    let result = get_name.execute(&db, &1, || String::from("Username"));
+
+    assert_eq!(result, String::from("Admin"));
+    assert_eq!(get_name.get_cached(&db, &1), Some(String::from("Admin")));
+
+    // Handles remain valid after the database is cleared.
+    db.clear_all();
+
+    assert_eq!(get_name.get_cached(&db, &1), None);
+    assert_eq!(
+        get_name.execute(&db, &1, || String::from("Username")),
+        String::from("Username")
+    );
+}
@@ -0,0 +1,36 @@
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lume_architect::*;
+
+static HASHED: AtomicUsize = AtomicUsize::new(0);
+
+/// Key which counts how many times it is hashed.
+struct Path(&'static str);
+
+impl Hash for Path {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        HASHED.fetch_add(1, Ordering::Relaxed);
+        self.0.hash(state);
+    }
+}
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    let len = |path: &Path| db.execute_query("len", path, || path.0.len());
+
+    // Keys are hashed once per execution, on misses and hits alike.
+    assert_eq!(len(&Path("main.lm")), 7);
+    assert_eq!(HASHED.swap(0, Ordering::Relaxed), 1);
+
+    assert_eq!(len(&Path("main.lm")), 7);
+    assert_eq!(HASHED.swap(0, Ordering::Relaxed), 1);
+
+    let source = |path: &Path| db.execute_query_result("source", path, || Ok::<_, ()>(String::from(path.0)));
+
+    assert_eq!(source(&Path("lib.lm")), Ok(String::from("lib.lm")));
+    assert_eq!(HASHED.swap(0, Ordering::Relaxed), 1);
+}
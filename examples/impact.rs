@@ -0,0 +1,26 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("parse", QueryFlags::empty);
+    db.ensure_query_exists("typecheck", QueryFlags::empty);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+
+    // Dependencies between queries executed within each other are recorded
+    // automatically.
+    db.execute_query("typecheck", &"main", || {
+        db.execute_query("parse", &"main.lm", || {
+            db.execute_query("source", &"main.lm", String::new).len()
+        })
+    });
+
+    let impact = db.estimate_impact("source", &"main.lm");
+
+    assert_eq!(impact.dependents, 2);
+    assert_eq!(impact.cached, 2);
+
+    // Estimating the impact doesn't change anything.
+    assert!(db.contains("typecheck", &"main"));
+}
@@ -0,0 +1,51 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    names: Vec<String>,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[cached_queries]
+impl Context {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            db: Database::new(),
+            names,
+        }
+    }
+
+    pub fn name_length(&self, index: usize) -> usize {
+        println!("running name_length");
+
+        self.names[index].len()
+    }
+
+    #[cached_query(result)]
+    pub fn name(&self, index: usize) -> Result<String, String> {
+        self.names
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("no name at {index}"))
+    }
+
+    #[uncached]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+fn main() {
+    let ctx = Context::new(vec![String::from("Alice"), String::from("Bob")]);
+
+    assert_eq!(ctx.name_length(0), 5);
+    assert_eq!(ctx.name_length(0), 5);
+    assert_eq!(ctx.name(1), Ok(String::from("Bob")));
+    assert!(ctx.name(2).is_err());
+    assert_eq!(ctx.names().len(), 2);
+}
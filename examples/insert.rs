@@ -0,0 +1,32 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    // Queries are created on the first insertion.
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+
+    assert!(db.contains("source", &"main.lm"));
+    assert_eq!(db.query("source").flags(), QueryFlags::empty());
+
+    let len = || {
+        db.execute_query("len", &"main.lm", || {
+            db.execute_query("source", &"main.lm", String::new).len()
+        })
+    };
+
+    assert_eq!(len(), 12);
+
+    // Inserting a result overwrites the old one, and marks its dependents as
+    // dirty.
+    db.insert("source", &"main.lm", String::from("fn main() { 1 }"));
+
+    assert_eq!(
+        db.get_cached::<_, String>("source", &"main.lm").unwrap(),
+        "fn main() { 1 }"
+    );
+    assert!(db.is_dirty("len", &"main.lm"));
+    assert_eq!(len(), 15);
+    assert!(!db.is_dirty("len", &"main.lm"));
+}
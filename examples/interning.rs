@@ -0,0 +1,65 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+/// Signature of a function, which is expensive to hash on every call.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Signature {
+    name: String,
+    params: Vec<String>,
+    ret: String,
+}
+
+struct Context {
+    db: Database,
+    checks: Cell<usize>,
+}
+
+impl Context {
+    fn check(&self, sig: Signature) -> Interned<Signature> {
+        self.db.intern_and_query("check_signature", sig, |id| {
+            self.checks.set(self.checks.get() + 1);
+
+            // Other queries are keyed by the ID, rather than the signature.
+            self.mangle(id);
+
+            id
+        })
+    }
+
+    fn mangle(&self, id: Interned<Signature>) -> String {
+        self.db.query_interned("mangle", id, |id| {
+            let sig = self.db.lookup_interned(id);
+
+            format!("_Z{}{}", sig.name.len(), sig.name)
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        checks: Cell::new(0),
+    };
+
+    ctx.db.ensure_query_exists("check_signature", QueryFlags::empty);
+    ctx.db.ensure_query_exists("mangle", QueryFlags::empty);
+
+    let sig = Signature {
+        name: String::from("add"),
+        params: vec![String::from("i32"), String::from("i32")],
+        ret: String::from("i32"),
+    };
+
+    let first = ctx.check(sig.clone());
+    let second = ctx.check(sig.clone());
+
+    assert_eq!(first, second);
+    assert_eq!(ctx.checks.get(), 1);
+    assert_eq!(ctx.mangle(first), "_Z3add");
+
+    // Interned keys outlive the results which are keyed by them.
+    ctx.db.clear_all();
+    assert_eq!(ctx.db.intern(sig.clone()), first);
+    assert_eq!(ctx.db.lookup_interned(first), sig);
+}
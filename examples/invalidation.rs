@@ -0,0 +1,29 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source_file", QueryFlags::empty);
+    db.ensure_query_exists("type_of", QueryFlags::empty);
+    db.ensure_query_exists("diagnostics", QueryFlags::empty);
+
+    // The type of an item is derived from its source file, which the
+    // database doesn't know about.
+    db.add_invalidation_rule("source_file", "type_of", |file: &u32| file * 10);
+    db.add_invalidation_rule("type_of", "diagnostics", |item: &u32| *item);
+
+    db.execute_query("source_file", &1_u32, || String::from("fn main() {}"));
+    db.execute_query("type_of", &10_u32, || String::from("fn()"));
+    db.execute_query("diagnostics", &10_u32, Vec::<String>::new);
+    db.execute_query("type_of", &20_u32, || String::from("i32"));
+
+    assert!(db.invalidate("source_file", &1_u32));
+
+    assert!(!db.contains("source_file", &1_u32));
+    assert!(!db.contains("type_of", &10_u32));
+    assert!(!db.contains("diagnostics", &10_u32));
+    assert!(db.contains("type_of", &20_u32));
+
+    // Clearing a query clears all dependent queries entirely.
+    db.clear("source_file");
+    assert!(!db.contains("type_of", &20_u32));
+}
@@ -0,0 +1,31 @@
+use lume_architect::*;
+
+fn main() {
+    let mut query = Query::new(String::from("symbols"), QueryFlags::empty());
+
+    query.insert(&"main", String::from("fn main"));
+    query.insert(&"count", 3_u32);
+    query.insert(&"lib", String::from("fn lib"));
+
+    // Results of other types are skipped.
+    let names = query.values::<String>().cloned().collect::<Vec<_>>();
+    assert_eq!(names, ["fn main", "fn lib"]);
+
+    assert_eq!(query.values::<u32>().copied().collect::<Vec<_>>(), [3]);
+
+    // Results are yielded in insertion order, along with their keys.
+    let mut keys = Vec::new();
+
+    for (key, value) in &query {
+        keys.push(key);
+        assert!(value.is::<String>() || value.is::<u32>());
+    }
+
+    assert_eq!(keys, [
+        ResultKey::from_hashable(&"main"),
+        ResultKey::from_hashable(&"count"),
+        ResultKey::from_hashable(&"lib"),
+    ]);
+
+    assert_eq!(query.iter().len(), 3);
+}
@@ -0,0 +1,49 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // The `Debug` representation of the key is captured in debug builds.
+    #[cached_query(debug_key)]
+    fn line_length(&self, file: &str, line: u32) -> usize {
+        file.len() + line as usize
+    }
+
+    // `type Alias = Other; type Other = Alias;` refers back to itself.
+    fn resolve_alias(&self, name: &'static str) -> QueryResult<String> {
+        self.db.label_key("resolve_alias", &name, || format!("{name:?}"));
+
+        self.db.try_execute_query("resolve_alias", &name, || {
+            let target = if name == "Alias" { "Other" } else { "Alias" };
+
+            self.resolve_alias(target).unwrap_or_else(|err| err.to_string())
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("resolve_alias", QueryFlags::empty);
+    ctx.db.enable_key_labels();
+
+    // Cycle errors show the label of the key, instead of its hash.
+    let message = ctx.resolve_alias("Alias").unwrap();
+    assert!(message.starts_with("cycle detected when executing query `resolve_alias` with key `\"Alias\"`"));
+
+    ctx.line_length("main.lm", 3);
+
+    let name = "key_labels::Context::line_length";
+    let key = ctx.db.query(name).iter().next().unwrap().0;
+
+    if cfg!(debug_assertions) {
+        assert_eq!(ctx.db.key_label(name, key), Some(String::from("(\"main.lm\", 3)")));
+    }
+}
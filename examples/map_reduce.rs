@@ -0,0 +1,54 @@
+use lume_architect::*;
+
+static FILES: [&str; 3] = ["main.lm", "lib.lm", "util.lm"];
+
+fn main() {
+    let db = Database::new();
+    let files = &FILES[..];
+
+    let line_count = |file: &&str| file.len();
+
+    let total = db.map_reduce("total_lines", "line_count", files, line_count, 0, |acc, n| acc + n);
+    assert_eq!(total, 20);
+
+    // The aggregate depends on every individual result, so it is verified,
+    // and recomputed, once any of them change.
+    assert_eq!(db.dependencies_of("total_lines", &files).len(), 3);
+
+    db.insert("line_count", &"lib.lm", 100_usize);
+    assert!(db.is_dirty("total_lines", &files));
+
+    let total = db.map_reduce("total_lines", "line_count", files, line_count, 0, |acc, n| acc + n);
+    assert_eq!(total, 114);
+
+    // Outdated aggregates are replaced, rather than kept next to the new one.
+    let stats = db.stats();
+    let aggregate = stats
+        .per_query
+        .iter()
+        .find(|query| query.name == "total_lines")
+        .unwrap();
+    assert_eq!(aggregate.entries, 1);
+
+    // Invalidating an individual result invalidates the aggregate as well.
+    db.invalidate("line_count", &"util.lm");
+    assert!(!db.contains("total_lines", &files));
+
+    let total = db.map_reduce("total_lines", "line_count", files, line_count, 0, |acc, n| acc + n);
+    assert_eq!(total, 114);
+
+    // Results which are evaluated in parallel are recorded as dependencies
+    // of the aggregate as well.
+    #[cfg(all(feature = "rayon", feature = "sync"))]
+    {
+        let total = db.par_map_reduce("par_total_lines", "line_count", files, line_count, 0, |acc, n| acc + n);
+        assert_eq!(total, 114);
+        assert_eq!(db.dependencies_of("par_total_lines", &files).len(), 3);
+
+        db.insert("line_count", &"main.lm", 1_usize);
+        assert!(db.is_dirty("par_total_lines", &files));
+
+        let total = db.par_map_reduce("par_total_lines", "line_count", files, line_count, 0, |acc, n| acc + n);
+        assert_eq!(total, 108);
+    }
+}
@@ -0,0 +1,36 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("file", QueryFlags::empty);
+    db.ensure_query_exists("index", QueryFlags::empty);
+
+    // The index depends on every file in the project, so tracking all of its
+    // dependencies would cost more than recomputing it.
+    db.set_max_dependencies("index", 8);
+
+    let runs = Cell::new(0);
+    let index = |count: usize| {
+        db.execute_query("index", &count, || {
+            runs.set(runs.get() + 1);
+
+            (0..count)
+                .map(|file| db.execute_query("file", &file, || file * 2))
+                .sum::<usize>()
+        })
+    };
+
+    // Small indices are memoized as usual.
+    assert_eq!(index(4), 12);
+    assert_eq!(index(4), 12);
+    assert_eq!(runs.get(), 1);
+
+    // Large indices exceed the limit, so they're recomputed on every access.
+    assert_eq!(index(100), 9900);
+    assert_eq!(index(100), 9900);
+    assert_eq!(runs.get(), 3);
+
+    assert!(db.dependencies_of("index", &100).is_empty());
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("get_name", QueryFlags::empty);
+    db.ensure_query_exists("expensive", QueryFlags::empty);
+
+    // Log every computed query, along with how long it took.
+    db.add_middleware(|call: &QueryCall, next: Next<'_>| {
+        let start = Instant::now();
+        let computed = next.run();
+
+        println!("computed {} in {:?}", call.query, start.elapsed());
+        computed
+    });
+
+    let computed = Arc::new(AtomicUsize::new(0));
+    let counter = computed.clone();
+
+    db.add_middleware(move |_: &QueryCall, next: Next<'_>| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        next.run()
+    });
+
+    // Cancel computations of expensive queries, instead of running them.
+    db.add_middleware(|call: &QueryCall, next: Next<'_>| {
+        if call.query == "expensive" {
+            return Err(QueryError::Cancelled {
+                query: call.query.clone(),
+            });
+        }
+
+        next.run()
+    });
+
+    db.execute_query("get_name", &1, || String::from("Admin"));
+    db.execute_query("get_name", &1, || String::from("Admin"));
+    db.execute_query("get_name", &2, || String::from("User"));
+
+    assert_eq!(computed.load(Ordering::Relaxed), 2);
+
+    let result = db.try_execute_query("expensive", &1, || 42);
+
+    assert_eq!(
+        result,
+        Err(QueryError::Cancelled {
+            query: String::from("expensive")
+        })
+    );
+    assert!(!db.contains("expensive", &1));
+}
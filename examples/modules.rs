@@ -0,0 +1,40 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+mod hir {
+    use super::*;
+
+    #[cached_query(db_expr = ctx, key = id)]
+    pub fn lower(ctx: &Context, id: u32) -> String {
+        format!("hir::{id}")
+    }
+}
+
+mod mir {
+    use super::*;
+
+    #[cached_query(db_expr = ctx, key = id)]
+    pub fn lower(ctx: &Context, id: u32) -> String {
+        format!("mir::{id}")
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+
+    // Both functions are named `lower`, but live in different modules, so
+    // they don't share results.
+    assert_eq!(hir::lower(&ctx, 1), "hir::1");
+    assert_eq!(mir::lower(&ctx, 1), "mir::1");
+
+    assert_eq!(ctx.db.query("modules::hir::lower").len(), 1);
+}
@@ -0,0 +1,45 @@
+use lume_architect::*;
+
+struct Context {
+    /// Results which live for the entire session.
+    hir: Database,
+
+    /// Results which are discarded after every build.
+    build: Database,
+}
+
+impl MultiDatabaseContext for Context {
+    fn db_named(&self, name: &str) -> &Database {
+        match name {
+            "hir" => &self.hir,
+            "build" => &self.build,
+            _ => panic!("unknown database: {name}"),
+        }
+    }
+}
+
+impl Context {
+    #[cached_query(db_name = "hir")]
+    pub fn lower(&self, item: u32) -> String {
+        format!("item{item}")
+    }
+
+    #[cached_query(db_name = "build")]
+    pub fn codegen(&self, item: u32) -> Vec<u8> {
+        self.lower(item).into_bytes()
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        hir: Database::new(),
+        build: Database::new(),
+    };
+
+    assert_eq!(ctx.codegen(1), b"item1".to_vec());
+
+    ctx.build.clear_all();
+
+    assert_eq!(ctx.hir.query("multiple_databases::Context::lower").len(), 1);
+    assert!(ctx.build.query("multiple_databases::Context::codegen").is_empty());
+}
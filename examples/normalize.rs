@@ -0,0 +1,45 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    let read_file = db.register_query::<String, usize>("read_file", QueryFlags::empty());
+
+    // Paths with different separators refer to the same file.
+    db.set_key_normalizer("read_file", |path: &String| path.replace('\\', "/"));
+
+    let mut reads = 0;
+
+    let a = read_file.execute(&db, &String::from("src/main.rs"), || {
+        reads += 1;
+        128
+    });
+
+    let b = read_file.execute(&db, &String::from("src\\main.rs"), || {
+        reads += 1;
+        256
+    });
+
+    assert_eq!(a, b);
+    assert_eq!(reads, 1);
+    assert_eq!(read_file.get_cached(&db, &String::from("src\\main.rs")), Some(128));
+
+    // Every method which is given an owned key normalizes it.
+    let windows = String::from("src\\main.rs");
+
+    assert!(db.contains("read_file", &windows));
+    assert_eq!(db.get_cached::<_, usize>("read_file", &windows), Some(128));
+
+    db.pin("read_file", [&windows]);
+    assert!(db.query("read_file").is_pinned(&String::from("src/main.rs")));
+
+    // Keys which may borrow can't be normalized, so they are rejected,
+    // instead of missing the cached result.
+    assert!(matches!(
+        db.try_execute_query("read_file", &"src\\main.rs", || 0_usize),
+        Err(QueryError::Unnormalized { .. })
+    ));
+
+    // Invalidations are normalized as well.
+    assert!(db.invalidate("read_file", &windows));
+    assert_eq!(read_file.get_cached(&db, &String::from("src/main.rs")), None);
+}
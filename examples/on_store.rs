@@ -0,0 +1,18 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("exports", QueryFlags::empty);
+
+    // Exports are sorted before they are stored, so the cached result doesn't
+    // depend on the order in which they were discovered.
+    db.query_mut("exports")
+        .on_store(|exports: &mut Vec<String>| exports.sort());
+
+    let exports = db.execute_query("exports", &"lib.rs", || {
+        vec![String::from("parse"), String::from("format"), String::from("lex")]
+    });
+
+    assert_eq!(exports, ["format", "lex", "parse"]);
+    assert_eq!(db.get_cached::<_, Vec<String>>("exports", &"lib.rs"), Some(exports));
+}
@@ -0,0 +1,17 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("symbols", QueryFlags::empty);
+
+    for name in ["main", "parse", "lex", "emit"] {
+        db.execute_query_keyed("symbols", &name.to_string(), || name.len());
+    }
+
+    // Results are iterated in the order they were inserted, so dumps of the
+    // database are reproducible across runs.
+    let query = db.query("symbols");
+    let names = query.keys_typed::<String>().map(String::as_str).collect::<Vec<_>>();
+
+    assert_eq!(names, ["main", "parse", "lex", "emit"]);
+}
@@ -0,0 +1,18 @@
+use lume_architect::*;
+use rayon::prelude::*;
+
+fn main() {
+    let mut query = Query::new(String::from("line_count"), QueryFlags::empty());
+
+    for file in 0..1000_u32 {
+        query.insert(&file, file as usize);
+    }
+
+    query.insert(&"config", String::from("--release"));
+
+    // Results of other types are skipped.
+    let total = query.par_values::<usize>().sum::<usize>();
+
+    assert_eq!(total, query.values::<usize>().sum::<usize>());
+    assert_eq!(query.par_values::<String>().count(), 1);
+}
@@ -0,0 +1,21 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("highlight", QueryFlags::empty);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+
+    db.execute_query("highlight", &"main.lm", || {
+        // Only used as a hint, so changes to the source shouldn't invalidate
+        // the highlighting.
+        db.peek("source", &"main.lm", String::len).unwrap_or_default()
+    });
+
+    assert!(db.dependencies_of("highlight", &"main.lm").is_empty());
+
+    db.insert("source", &"main.lm", String::from("fn main() { 1 }"));
+    assert!(!db.is_dirty("highlight", &"main.lm"));
+    assert_eq!(db.get_cached::<_, usize>("highlight", &"main.lm"), Some(12));
+}
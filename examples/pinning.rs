@@ -0,0 +1,41 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", || QueryFlags::SOFT);
+
+    for file in ["main.lm", "lib.lm", "util.lm"] {
+        db.execute_query("parse", &file, || format!("(tree {file})"));
+    }
+
+    // The files which are open in the editor are kept, even under memory
+    // pressure.
+    db.pin("parse", &["main.lm", "lib.lm"]);
+    db.unpin("parse", &["lib.lm"]);
+
+    assert_eq!(db.release_soft_entries(), 2);
+    assert!(db.contains("parse", &"main.lm"));
+    assert!(!db.contains("parse", &"lib.lm"));
+
+    let stats = db.stats();
+    assert_eq!(stats.pinned, 1);
+    assert_eq!(stats.evictions, 2);
+
+    db.unpin_all("parse");
+    assert_eq!(db.release_soft_entries(), 1);
+
+    // Whole queries can be pinned as well, regardless of their keys.
+    db.ensure_query_exists("intern", || QueryFlags::SOFT | QueryFlags::PINNED);
+
+    for name in ["i32", "bool", "str"] {
+        db.execute_query("intern", &name, || name.len());
+    }
+
+    assert!(db.query("intern").is_pinned(&"bool"));
+    assert_eq!(db.release_soft_entries(), 0);
+    assert!(db.contains("intern", &"bool"));
+
+    // Pinned results can still be invalidated explicitly.
+    assert!(db.invalidate("intern", &"bool"));
+    assert!(!db.contains("intern", &"bool"));
+}
@@ -0,0 +1,31 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+    let inserted = db.current_revision();
+
+    db.execute_query("len", &"main.lm", || {
+        db.execute_query("source", &"main.lm", String::new).len()
+    });
+
+    // Inserted results weren't computed, so they have no duration.
+    let source = db.provenance("source", &"main.lm").unwrap();
+
+    assert_eq!(source.query, "source");
+    assert_eq!(source.revision, inserted);
+    assert_eq!(source.duration, None);
+
+    let len = db.provenance("len", &"main.lm").unwrap();
+
+    assert_eq!(len.query, "len");
+    assert_eq!(len.revision, db.current_revision());
+    assert!(len.revision > source.revision);
+    assert!(len.duration.is_some());
+    assert_eq!(Some(len.generation), db.query("len").generation(&"main.lm"));
+
+    assert_eq!(db.provenance("len", &"lib.lm"), None);
+}
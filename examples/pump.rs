@@ -0,0 +1,82 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+    counts: Cell<usize>,
+}
+
+fn source(db: &Database, file: &'static str) -> String {
+    db.execute_query("source", &file, String::new)
+}
+
+impl Context {
+    fn line_count(&self, file: &'static str) -> usize {
+        self.db.execute_query_keyed("line_count", &file, || {
+            self.counts.set(self.counts.get() + 1);
+
+            source(&self.db, file).lines().count()
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        counts: Cell::new(0),
+    };
+
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("line_count", QueryFlags::empty);
+
+    ctx.db
+        .query_mut("line_count")
+        .set_recompute(|db, file: &&'static str| source(db, file).lines().count());
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}"));
+
+    assert_eq!(ctx.line_count("main.lm"), 1);
+    assert_eq!(ctx.line_count("lib.lm"), 1);
+    assert_eq!(ctx.counts.get(), 2);
+
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}\nfn b() {}"));
+    assert!(ctx.db.is_dirty("line_count", &"lib.lm"));
+
+    // Without any budget, no work is done.
+    assert_eq!(ctx.db.pump(Duration::ZERO).remaining, 1);
+
+    // Between keystrokes, the editor recomputes the dirty results, so they
+    // are up-to-date once they are requested.
+    let progress = ctx.db.pump(Duration::from_secs(1));
+    assert_eq!(progress, Pump {
+        verified: 0,
+        recomputed: 1,
+        remaining: 0,
+        preempted: false,
+    });
+
+    assert!(!ctx.db.is_dirty("line_count", &"lib.lm"));
+    assert_eq!(ctx.line_count("lib.lm"), 2);
+    assert_eq!(ctx.counts.get(), 2);
+
+    // Pumping yields to interactive queries, such as the one the user of the
+    // editor is waiting on, even if they are executed on another thread.
+    ctx.db.ensure_query_exists("status", QueryFlags::empty);
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {\n}"));
+
+    let status = ctx
+        .db
+        .execute_with_priority("status", &"main.lm", Priority::Interactive, || {
+            let progress = ctx.db.pump(Duration::from_secs(1));
+            assert!(progress.preempted);
+            assert_eq!(progress.remaining, 1);
+
+            format!("{} lines", ctx.line_count("main.lm"))
+        });
+
+    assert_eq!(status, "2 lines");
+    assert!(!ctx.db.pump(Duration::from_secs(1)).preempted);
+}
@@ -0,0 +1,23 @@
+use lume_architect::*;
+
+/// Computed at compile-time, so the name isn't hashed on every lookup.
+const PARSE: QueryId = QueryId::from_name("parse_file");
+
+fn main() {
+    // IDs only depend on the full name, not on how it was split up.
+    assert_eq!(PARSE, QueryId::from_parts(&["parse", "_", "file"]));
+    assert_eq!(PARSE, QueryId::from_parts(&["", "parse_file"]));
+    assert_ne!(PARSE, QueryId::from_name("parse"));
+
+    assert_eq!(PARSE, "parse_file".query_id());
+    assert_eq!(PARSE, ["parse", "_file"].query_id());
+
+    let db = Database::new();
+    let parse = db.register_query::<&str, usize>("parse_file", QueryFlags::empty());
+
+    assert_eq!(parse.id(), PARSE);
+
+    // Results are shared by all names which hash to the same ID.
+    db.execute_query(&["parse", "_file"], &"main.lm", || 12_usize);
+    assert_eq!(parse.get_cached(&db, &"main.lm"), Some(12));
+}
@@ -0,0 +1,48 @@
+use lume_architect::*;
+
+query_module! {
+    /// Queries which make up the front-end of the compiler.
+    pub trait Frontend {
+        /// Splits the given source file into words.
+        fn words(file: usize) -> Vec<String>;
+
+        /// Counts the words within the given source file.
+        fn word_count(file: usize) -> usize;
+    }
+}
+
+struct Context {
+    db: Database,
+    sources: Vec<&'static str>,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Frontend for Context {
+    fn compute_words(&self, file: usize) -> Vec<String> {
+        println!("running words");
+
+        self.sources[file].split_whitespace().map(String::from).collect()
+    }
+
+    fn compute_word_count(&self, file: usize) -> usize {
+        self.words(file).len()
+    }
+}
+
+fn main() {
+    let ctx = Context {
+        db: Database::new(),
+        sources: vec!["fn main() {}", "let a = 1;"],
+    };
+
+    Context::register_queries(ctx.db());
+
+    assert_eq!(ctx.word_count(0), 3);
+    assert_eq!(ctx.word_count(1), 4);
+    assert_eq!(ctx.words(1).len(), 4);
+}
@@ -0,0 +1,61 @@
+use std::any::type_name;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    fn line_count(&self, source: &str) -> usize {
+        source.lines().count()
+    }
+
+    #[cached_query]
+    fn size_of<T>(&self) -> usize {
+        size_of::<T>()
+    }
+}
+
+#[cached_query(db_expr = ctx, key = source)]
+fn is_empty(ctx: &Context, source: &str) -> bool {
+    ctx.line_count(source) == 0
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    let results = |name: &str| ctx.db.read_txn(|view| view.query(name).map(Query::len));
+
+    assert_eq!(ctx.line_count("a\nb"), 2);
+    assert_eq!(ctx.line_count("a\nb\nc"), 3);
+    assert!(!is_empty(&ctx, "a"));
+
+    // Methods are named after the type of their receiver, and free functions
+    // after their module.
+    let context = type_name::<Context>();
+
+    assert_eq!(results(&format!("{context}::line_count")), Some(3));
+    assert_eq!(results(&format!("{}::is_empty", module_path!())), Some(1));
+
+    // Every instantiation of a generic method is a separate query.
+    assert_eq!(ctx.size_of::<u32>(), 4);
+    assert_eq!(ctx.size_of::<u64>(), 8);
+
+    assert_eq!(results(&format!("{context}::size_of<u32>")), Some(1));
+    assert_eq!(results(&format!("{context}::size_of<u64>")), Some(1));
+
+    // Names which are built from parts are only hashed once per instance.
+    let names = QueryNameCache::new();
+    let parts = [context, "::line_count"];
+
+    let name = names.get(&parts);
+    assert_eq!(name.query_id(), QueryId::from_name(&format!("{context}::line_count")));
+    assert_eq!(names.get(&parts).query_id(), name.query_id());
+}
@@ -0,0 +1,19 @@
+use lume_architect::*;
+
+// The function is passed to the query itself, so it doesn't need to capture
+// any state to execute the query recursively.
+fn fib(db: &Database, n: &u64) -> u64 {
+    if *n < 2 {
+        return *n;
+    }
+
+    db.execute_recursive("fib", &(n - 1), fib) + db.execute_recursive("fib", &(n - 2), fib)
+}
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("fib", QueryFlags::empty);
+
+    assert_eq!(db.execute_recursive("fib", &50, fib), 12_586_269_025);
+    assert_eq!(db.query("fib").len(), 51);
+}
@@ -0,0 +1,43 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    pub fn tokens(&self, file: usize) -> Vec<usize> {
+        (0..file).collect()
+    }
+
+    #[cached_query]
+    pub fn parse(&self, file: usize) -> usize {
+        self.tokens(file).len()
+    }
+
+    #[cached_query]
+    pub fn typecheck(&self, file: usize) -> bool {
+        self.parse(file) == self.tokens(file).len()
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db().enable_reentrancy_audit();
+
+    for file in 0..4 {
+        assert!(ctx.typecheck(file));
+    }
+
+    for call in ctx.db().reentrancy_report() {
+        println!("{} -> {} ({} calls)", call.caller, call.callee, call.count);
+    }
+
+    assert_eq!(ctx.db().reentrancy_report().len(), 3);
+}
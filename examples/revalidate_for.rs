@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn line_count(&self, file: &str) -> usize {
+        self.db
+            .execute_query("line_count", &file, || self.source(file).lines().count())
+    }
+
+    fn total_lines(&self) -> usize {
+        self.db.execute_query("total_lines", &(), || {
+            self.line_count("main.lm") + self.line_count("lib.lm")
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("line_count", QueryFlags::empty);
+    ctx.db.ensure_query_exists("total_lines", QueryFlags::empty);
+
+    // Saving a file without changing it doesn't invalidate its dependents.
+    ctx.db.enable_checksums::<String>("source");
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}\nfn b() {}"));
+    assert_eq!(ctx.total_lines(), 3);
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}"));
+
+    // Without any budget, no work is done.
+    let progress = ctx.db.revalidate_for(Duration::ZERO);
+    assert_eq!(progress.remaining, 3);
+
+    // Between keystrokes, the editor cleans up the dirty results.
+    let progress = ctx.db.revalidate_for(Duration::from_secs(1));
+    assert_eq!(progress, Revalidation {
+        verified: 1,
+        discarded: 2,
+        remaining: 0,
+    });
+
+    assert!(ctx.db.contains("line_count", &"main.lm"));
+    assert!(!ctx.db.contains("line_count", &"lib.lm"));
+    assert!(!ctx.db.contains("total_lines", &()));
+
+    assert_eq!(ctx.total_lines(), 2);
+}
@@ -0,0 +1,41 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn line_count(&self, file: &str) -> usize {
+        self.db.execute_query("line_count", &file, || {
+            println!("counting lines of {file}");
+
+            self.source(file).lines().count()
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("line_count", QueryFlags::empty);
+
+    ctx.db.insert("source", &"main.lm", String::from("fn main() {}"));
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}\nfn b() {}"));
+
+    assert_eq!(ctx.line_count("main.lm"), 1);
+    assert_eq!(ctx.line_count("lib.lm"), 2);
+
+    // Changing an input marks all results which depend on it as dirty.
+    ctx.db.insert("source", &"lib.lm", String::from("fn a() {}"));
+
+    assert!(ctx.db.is_dirty("line_count", &"lib.lm"));
+    assert!(!ctx.db.is_dirty("line_count", &"main.lm"));
+
+    // Dirty results are only recomputed once they are requested again.
+    assert_eq!(ctx.line_count("lib.lm"), 1);
+    assert!(!ctx.db.is_dirty("line_count", &"lib.lm"));
+}
@@ -0,0 +1,45 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    let initial = db.current_revision();
+
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("len", QueryFlags::empty);
+
+    // Adding queries mutates the database.
+    let added = db.current_revision();
+    assert!(added > initial);
+
+    // Changing the settings of a query doesn't affect any result.
+    db.enable_checksums::<String>("source");
+    db.query_mut("len").set_max_dependencies(8);
+    assert_eq!(db.current_revision(), added);
+
+    db.insert("source", &"main.lm", String::from("fn main() {}"));
+
+    let inserted = db.current_revision();
+    assert_eq!(inserted, added.next());
+
+    // Computing a result inserts it, while cache hits don't.
+    let len = || {
+        db.execute_query("len", &"main.lm", || {
+            db.execute_query("source", &"main.lm", String::new).len()
+        })
+    };
+
+    assert_eq!(len(), 12);
+    let computed = db.current_revision();
+    assert!(computed > inserted);
+
+    assert_eq!(len(), 12);
+    assert_eq!(db.current_revision(), computed);
+
+    // Results inserted through a mutable query are stamped with the next
+    // revision.
+    db.query_mut("source").insert(&"lib.lm", String::from("fn lib() {}"));
+    assert_eq!(db.current_revision(), computed.next());
+
+    db.clear("source");
+    assert!(db.current_revision() > computed.next());
+}
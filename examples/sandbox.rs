@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use lume_architect::*;
+
+struct Host {
+    db: Database,
+}
+
+impl Host {
+    /// Query registered by a plugin, which recurses without a base case.
+    fn countdown(&self, n: u64) -> Result<u64, QueryError> {
+        self.db
+            .try_execute_query_result("plugin::countdown", &n, || Ok(self.countdown(n.wrapping_sub(1))? + 1))
+    }
+
+    /// Query registered by a plugin, which takes far too long.
+    fn slow(&self, n: u64) -> Result<u64, QueryError> {
+        self.db.try_execute_query_result("plugin::slow", &n, || {
+            std::thread::sleep(Duration::from_millis(20));
+
+            Ok(n)
+        })
+    }
+
+    /// Query registered by a plugin, which only takes too long for some
+    /// inputs.
+    fn scaled(&self, n: u64) -> Result<u64, QueryError> {
+        self.db.try_execute_query_result("plugin::scaled", &n, || {
+            let factor = self.db.execute_query("plugin::factor", &(), || 1_u64);
+
+            if factor > 1 {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+
+            Ok(n * factor)
+        })
+    }
+
+    /// Query registered by a plugin, which caches a result for every input.
+    fn square(&self, n: u64) -> Result<u64, QueryError> {
+        self.db.try_execute_query_result("plugin::square", &n, || Ok(n * n))
+    }
+}
+
+fn main() {
+    let host = Host { db: Database::new() };
+
+    for name in [
+        "plugin::countdown",
+        "plugin::slow",
+        "plugin::scaled",
+        "plugin::factor",
+        "plugin::square",
+    ] {
+        host.db.ensure_query_exists(name, QueryFlags::empty);
+    }
+
+    host.db.set_sandbox("plugin::countdown", Sandbox {
+        max_depth: Some(64),
+        ..Sandbox::default()
+    });
+
+    host.db.set_sandbox("plugin::slow", Sandbox {
+        max_duration: Some(Duration::from_millis(5)),
+        ..Sandbox::default()
+    });
+
+    host.db.set_sandbox("plugin::scaled", Sandbox {
+        max_duration: Some(Duration::from_millis(5)),
+        ..Sandbox::default()
+    });
+
+    host.db.set_sandbox("plugin::square", Sandbox {
+        max_entries: Some(2),
+        ..Sandbox::default()
+    });
+
+    // Violations are reported as errors, instead of overflowing the stack of
+    // the host.
+    match host.countdown(10) {
+        Err(QueryError::LimitExceeded { query, limit, .. }) => {
+            assert_eq!(query, "plugin::countdown");
+            assert_eq!(limit, Limit::Depth(64));
+        }
+        result => panic!("expected depth limit, found {result:?}"),
+    }
+
+    // Results which took too long are discarded.
+    assert!(matches!(
+        host.slow(1),
+        Err(QueryError::LimitExceeded {
+            limit: Limit::Duration(_),
+            ..
+        })
+    ));
+    assert!(!host.db.contains("plugin::slow", &1_u64));
+
+    // Outdated results are discarded as well, when recomputing them took too
+    // long, instead of being reused.
+    assert_eq!(host.scaled(3), Ok(3));
+
+    host.db.insert("plugin::factor", &(), 2_u64);
+
+    assert!(host.scaled(3).is_err());
+    assert!(!host.db.contains("plugin::scaled", &3_u64));
+    assert!(host.scaled(3).is_err());
+
+    // Cached results may be reused, but no more than two are cached.
+    assert_eq!(host.square(2), Ok(4));
+    assert_eq!(host.square(3), Ok(9));
+    assert_eq!(host.square(2), Ok(4));
+    assert_eq!(
+        host.square(4).unwrap_err().to_string(),
+        "query `plugin::square` exceeded its entry limit of 2"
+    );
+}
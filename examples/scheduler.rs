@@ -0,0 +1,49 @@
+use std::sync::Mutex;
+
+use lume_architect::*;
+
+/// Runs a batch and a read transaction on two threads, interleaved by a
+/// scheduler with the given seed, and returns the order in which both threads
+/// made progress.
+fn run(seed: u64) -> Vec<&'static str> {
+    let db = Database::new();
+    let order = Mutex::new(Vec::new());
+
+    db.ensure_query_exists("version", QueryFlags::empty);
+    db.ensure_query_exists("checksum", QueryFlags::empty);
+
+    Scheduler::new(seed).run(vec![
+        Box::new(|| {
+            db.batch(|db| {
+                db.insert("version", &(), 1u64);
+                order.lock().unwrap().push("version");
+
+                db.insert("checksum", &(), 31u64);
+                order.lock().unwrap().push("checksum");
+            });
+        }),
+        Box::new(|| {
+            db.read_txn(|view| {
+                let version = view.get_cached::<_, u64>("version", &());
+                let checksum = view.get_cached::<_, u64>("checksum", &());
+
+                // Transactions never observe part of a batch.
+                assert_eq!(version.map(|version| version * 31), checksum);
+                order.lock().unwrap().push("read");
+            });
+        }),
+    ]);
+
+    order.into_inner().unwrap()
+}
+
+fn main() {
+    // Different seeds explore different interleavings.
+    let interleavings = (0..32).map(run).collect::<std::collections::HashSet<_>>();
+    assert!(interleavings.len() > 1);
+
+    // The same seed always reproduces the same interleaving.
+    for seed in 0..32 {
+        assert_eq!(run(seed), run(seed));
+    }
+}
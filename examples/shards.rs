@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use lume_architect::*;
+
+fn source(db: &Database, file: &str) -> String {
+    db.execute_query("source", &file, || format!(" {file} "))
+}
+
+fn parse(db: &Database, file: &str) -> String {
+    db.execute_query("parse", &file, || source(db, file).to_uppercase())
+}
+
+fn main() {
+    let db = Database::new();
+    let inserted = Arc::new(Mutex::new(Vec::new()));
+
+    db.ensure_query_exists("source", QueryFlags::empty);
+    db.ensure_query_exists("parse", QueryFlags::empty);
+
+    // Settings of the queries are copied into every shard.
+    db.query_mut("parse")
+        .on_store(|value: &mut String| *value = value.trim().to_string());
+
+    {
+        let inserted = Arc::clone(&inserted);
+        db.add_observer(move |changes: &ChangeSet| inserted.lock().unwrap().extend(changes.inserted.clone()));
+    }
+
+    let files = [["a.lm", "b.lm"], ["c.lm", "d.lm"]];
+
+    // Every worker parses its files into its own shard, without contending on
+    // the locks of the main database.
+    let shards = std::thread::scope(|scope| {
+        let workers = files
+            .iter()
+            .map(|files| {
+                let shard = db.shard();
+
+                scope.spawn(move || {
+                    for file in files {
+                        parse(&shard, file);
+                    }
+
+                    shard
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    for shard in shards {
+        db.merge(shard);
+    }
+
+    assert_eq!(db.query("parse").len(), 4);
+    assert_eq!(db.get_cached::<_, String>("parse", &"c.lm"), Some(String::from("C.LM")));
+
+    // Observers are notified of the merged results.
+    assert_eq!(inserted.lock().unwrap().len(), 8);
+
+    // Dependencies between the merged results are merged as well, so changes
+    // to the sources within the main database propagate to the parsed files.
+    db.invalidate("source", &"c.lm");
+    assert!(!db.contains("parse", &"c.lm"));
+    assert!(db.contains("parse", &"d.lm"));
+}
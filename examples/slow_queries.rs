@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl Context {
+    fn source(&self, file: &str) -> String {
+        self.db.execute_query("source", &file, || String::from("fn main() {}"))
+    }
+
+    fn typecheck(&self, file: &str) -> bool {
+        self.db.label_key("typecheck", &file, || file.to_string());
+
+        self.db.execute_query("typecheck", &file, || {
+            std::thread::sleep(Duration::from_millis(20));
+
+            !self.source(file).is_empty()
+        })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    ctx.db.ensure_query_exists("source", QueryFlags::empty);
+    ctx.db.ensure_query_exists("typecheck", QueryFlags::empty);
+
+    ctx.db.set_slow_query_threshold(Duration::from_millis(10));
+    ctx.db.enable_key_labels();
+
+    assert!(ctx.typecheck("main.lm"));
+
+    let log = ctx.db.slow_query_log();
+    assert_eq!(log.len(), 1);
+
+    assert_eq!(log[0].query, "typecheck");
+    assert_eq!(log[0].label.as_deref(), Some("main.lm"));
+    assert_eq!(log[0].dependencies, 1);
+    assert!(log[0].duration >= Duration::from_millis(10));
+}
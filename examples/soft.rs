@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+
+    // Parse trees are cheap to recompute and may be discarded under memory
+    // pressure, while symbol tables must be kept around.
+    db.ensure_query_exists("parse", || QueryFlags::SOFT);
+    db.ensure_query_exists("symbols", QueryFlags::empty);
+
+    let pressure = Arc::new(AtomicBool::new(false));
+    let monitor = pressure.clone();
+    db.set_memory_monitor(move || monitor.load(Ordering::Relaxed));
+
+    db.execute_query("parse", &"main.lm", || String::from("(tree)"));
+    db.execute_query("symbols", &"main.lm", || vec![String::from("main")]);
+
+    // The next computation notices the pressure and discards soft results.
+    pressure.store(true, Ordering::Relaxed);
+    db.execute_query("parse", &"lib.lm", || String::from("(tree)"));
+
+    assert!(!db.contains("parse", &"main.lm"));
+    assert!(db.contains("symbols", &"main.lm"));
+}
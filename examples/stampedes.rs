@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", QueryFlags::empty);
+
+    // Set by mistake, so every lookup misses the cache.
+    db.ensure_query_exists("resolve", || QueryFlags::ALWAYS);
+
+    assert!(db.stampede_report(10).is_empty());
+    db.enable_stampede_detection(Duration::from_secs(60), 3);
+
+    for _ in 0..5 {
+        db.execute_query("parse", &"main.lm", || 1);
+        db.execute_query("resolve", &"main.lm", || 2);
+    }
+
+    let report = db.stampede_report(10);
+
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].query, "resolve");
+    assert_eq!(report[0].key, ResultKey::from_hashable(&"main.lm"));
+    assert_eq!(report[0].misses, 5);
+
+    db.disable_stampede_detection();
+    assert!(db.stampede_report(10).is_empty());
+}
@@ -0,0 +1,30 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", QueryFlags::empty);
+    db.ensure_query_exists("lex", || QueryFlags::SOFT);
+
+    db.execute_query("lex", &"main.lm", || vec!["fn", "main"]);
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+
+    db.release_soft_entries();
+
+    let stats = db.stats();
+    println!("{stats:#?}");
+
+    assert_eq!(stats.queries, 2);
+    assert_eq!(stats.entries, 1);
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+    assert_eq!(stats.evictions, 1);
+
+    assert_eq!(stats.per_query[0].name, "lex");
+    assert_eq!(stats.per_query[1].hits, 1);
+
+    // Durations are tracked per query, to find queries with slow outliers.
+    let parse = &stats.per_query[1].durations;
+
+    assert_eq!(parse.count, 1);
+    assert!(parse.p50 <= parse.p95 && parse.p95 <= parse.max);
+}
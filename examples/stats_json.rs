@@ -0,0 +1,18 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("parse", QueryFlags::empty);
+
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+    db.execute_query("parse", &"main.lm", || String::from("fn main() {}"));
+
+    // Statistics can be exported, such as to a dashboard.
+    let json = serde_json::to_value(db.stats()).unwrap();
+    println!("{json:#}");
+
+    assert_eq!(json["queries"], 1);
+    assert_eq!(json["per_query"][0]["name"], "parse");
+    assert_eq!(json["per_query"][0]["hits"], 1);
+    assert_eq!(json["per_query"][0]["durations"]["count"], 1);
+}
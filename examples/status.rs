@@ -0,0 +1,21 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("get_name", QueryFlags::empty);
+    db.ensure_query_exists("get_time", || QueryFlags::ALWAYS);
+
+    let (_, status) = db.execute_query_with_status("get_name", &1, || String::from("Admin"));
+    assert_eq!(status, CacheStatus::Miss);
+
+    let (_, status) = db.execute_query_with_status("get_name", &1, || String::from("Admin"));
+    assert_eq!(status, CacheStatus::Hit);
+
+    // Queries with `QueryFlags::ALWAYS` are computed on every execution.
+    let (_, status) = db.execute_query_with_status("get_time", &1, || 1_u64);
+    assert_eq!(status, CacheStatus::Miss);
+
+    let (time, status) = db.execute_query_with_status("get_time", &1, || 2_u64);
+    assert_eq!(status, CacheStatus::Recomputed);
+    assert_eq!(time, 2);
+}
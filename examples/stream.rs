@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("references", QueryFlags::empty);
+    db.ensure_query_exists("symbols", QueryFlags::empty);
+    db.ensure_query_exists("symbol_count", QueryFlags::empty);
+
+    let produced = Arc::new(AtomicUsize::new(0));
+
+    let find_references = || {
+        let produced = produced.clone();
+
+        (0..1_000_u32).map(move |line| {
+            produced.fetch_add(1, Ordering::Relaxed);
+            line * 10
+        })
+    };
+
+    // Only the first three references are computed.
+    let first = db
+        .execute_stream("references", &"main", find_references)
+        .take(3)
+        .collect::<Vec<_>>();
+
+    assert_eq!(first, vec![0, 10, 20]);
+    assert_eq!(produced.load(Ordering::Relaxed), 3);
+
+    // Cached references are replayed, before computing the remaining ones.
+    let more = db
+        .execute_stream("references", &"main", find_references)
+        .take(5)
+        .collect::<Vec<_>>();
+
+    assert_eq!(more, vec![0, 10, 20, 30, 40]);
+    assert_eq!(produced.load(Ordering::Relaxed), 5);
+
+    // Results which are executed while the iterator is created are recorded
+    // as dependencies of the stream, so it is produced again once they change.
+    db.insert("symbol_count", &"main", 2_u32);
+
+    let symbols = || {
+        let count = db.execute_query("symbol_count", &"main", || 0_u32);
+
+        0..count
+    };
+
+    let all = db.execute_stream("symbols", &"main", symbols).collect::<Vec<_>>();
+    assert_eq!(all, vec![0, 1]);
+
+    db.insert("symbol_count", &"main", 3_u32);
+
+    let all = db.execute_stream("symbols", &"main", symbols).collect::<Vec<_>>();
+    assert_eq!(all, vec![0, 1, 2]);
+}
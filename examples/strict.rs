@@ -0,0 +1,21 @@
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    db.enable_strict_registration();
+
+    let parse = db.register_query::<&str, String>("parse", QueryFlags::empty());
+    assert_eq!(
+        parse.execute(&db, &"main.lm", || String::from("fn main() {}")),
+        "fn main() {}"
+    );
+
+    // Queries which were registered ahead of time can be used like usual.
+    db.ensure_query_exists("parse", QueryFlags::empty);
+
+    // ...while typos in query names are caught.
+    let err = db.try_ensure_query_exists("prase", QueryFlags::empty).unwrap_err();
+    assert_eq!(err, QueryError::Unregistered {
+        query: String::from("prase")
+    });
+}
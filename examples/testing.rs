@@ -0,0 +1,45 @@
+use std::cell::Cell;
+
+use lume_architect::*;
+
+fn main() {
+    let db = Database::new();
+    let runs = Cell::new(0);
+
+    db.ensure_query_exists("get_name", QueryFlags::empty);
+
+    let compute = || {
+        runs.set(runs.get() + 1);
+        String::from("Admin")
+    };
+
+    db.execute_query("get_name", &1, compute);
+    db.execute_query("get_name", &1, compute);
+    assert_eq!(runs.get(), 1);
+
+    // Simulate the result being missing, to exercise the recomputation path.
+    db.force_miss("get_name", &1);
+
+    db.execute_query("get_name", &1, compute);
+    assert_eq!(runs.get(), 2);
+
+    // Simulate a cycle, to exercise the error recovery of the caller. Only
+    // the next execution fails.
+    db.force_cycle("get_name", &1);
+
+    let err = db.try_execute_query("get_name", &1, compute).unwrap_err();
+    assert!(matches!(err, QueryError::Cycle { ref query, .. } if query == "get_name"));
+    assert_eq!(db.stats().cycles, 1);
+
+    db.execute_query("get_name", &1, compute);
+    assert_eq!(runs.get(), 3);
+
+    // Simulate the result being evicted, which is counted like a real
+    // eviction.
+    assert!(db.force_evict("get_name", &1));
+    assert!(!db.force_evict("get_name", &1));
+    assert_eq!(db.stats().evictions, 1);
+
+    db.execute_query("get_name", &1, compute);
+    assert_eq!(runs.get(), 4);
+}
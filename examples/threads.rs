@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use std::thread;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    pub fn square(&self, value: u64) -> u64 {
+        value * value
+    }
+}
+
+fn main() {
+    let ctx = Arc::new(Context { db: Database::new() });
+
+    let handles = (0..4)
+        .map(|_| {
+            let ctx = Arc::clone(&ctx);
+
+            thread::spawn(move || (0..100).map(|v| ctx.square(v)).sum::<u64>())
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 328_350);
+    }
+}
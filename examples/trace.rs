@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use lume_architect::*;
+use tracing::Subscriber;
+use tracing::field::{Field, Visit};
+use tracing::span::{Id, Record};
+use tracing_subscriber::layer::{Context as LayerContext, Layer, SubscriberExt};
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query(trace)]
+    pub fn line_count(&self, file: &'static str) -> usize {
+        file.len()
+    }
+}
+
+/// Layer which collects the cache status of every `cached_query` span.
+struct Statuses(Arc<Mutex<Vec<String>>>);
+
+impl<S: Subscriber> Layer<S> for Statuses {
+    fn on_record(&self, _: &Id, values: &Record<'_>, _: LayerContext<'_, S>) {
+        values.record(&mut StatusVisitor(&self.0));
+    }
+}
+
+struct StatusVisitor<'a>(&'a Mutex<Vec<String>>);
+
+impl Visit for StatusVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "status" {
+            self.0.lock().unwrap().push(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn Debug) {}
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+
+    let subscriber = tracing_subscriber::registry().with(Statuses(Arc::clone(&statuses)));
+
+    tracing::subscriber::with_default(subscriber, || {
+        ctx.line_count("main.lm");
+        ctx.line_count("main.lm");
+        ctx.line_count("lib.lm");
+    });
+
+    // Every call gets a span, including calls which are served from the cache.
+    assert_eq!(*statuses.lock().unwrap(), ["miss", "hit", "miss"]);
+}
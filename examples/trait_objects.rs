@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use lume_architect::*;
+
+trait Artifact {
+    fn size(&self) -> usize;
+}
+
+struct ObjectFile {
+    bytes: Vec<u8>,
+}
+
+impl Artifact for ObjectFile {
+    fn size(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    // the concrete artifact type is erased before it is cached, so callers
+    // only ever see `dyn Artifact`.
+    #[cached_query]
+    pub fn artifact(&self, len: usize) -> Arc<dyn Artifact> {
+        println!("running artifact");
+
+        Arc::new(ObjectFile { bytes: vec![0; len] })
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+
+    let a1 = ctx.artifact(16);
+    let a2 = ctx.artifact(16);
+
+    assert!(Arc::ptr_eq(&a1, &a2));
+    assert_eq!(a1.size(), 16);
+}
@@ -0,0 +1,27 @@
+use lume_architect::*;
+
+fn main() {
+    let mut query = Query::new(String::from("get_name"), QueryFlags::empty());
+    query.insert(&1, String::from("Admin"));
+
+    assert_eq!(query.try_get::<_, String>(&1).unwrap(), Some(&String::from("Admin")));
+
+    // A missing result isn't an error.
+    assert_eq!(query.try_get::<_, String>(&2).unwrap(), None);
+
+    // A result of another type is reported, instead of looking missing.
+    assert_eq!(query.get::<_, u32>(&1), None);
+
+    let Err(QueryError::TypeMismatch {
+        query: name,
+        expected,
+        found,
+    }) = query.try_get::<_, u32>(&1)
+    else {
+        panic!("expected a type mismatch");
+    };
+
+    assert_eq!(name, "get_name");
+    assert_eq!(expected, "u32");
+    assert!(found.contains("String"));
+}
@@ -0,0 +1,48 @@
+use std::panic::AssertUnwindSafe;
+
+use lume_architect::*;
+
+#[derive(Debug)]
+enum LookupError {
+    NotFound,
+    Query(QueryError),
+}
+
+impl From<QueryError> for LookupError {
+    fn from(err: QueryError) -> Self {
+        LookupError::Query(err)
+    }
+}
+
+fn main() {
+    let db = Database::new();
+    db.ensure_query_exists("get_name", QueryFlags::empty);
+
+    db.execute_query("get_name", &1, || String::from("Admin"));
+
+    // Another call site disagrees on the type of the query.
+    let result = db.try_execute_query("get_name", &1, || 1_u32);
+
+    assert!(matches!(result, Err(QueryError::TypeMismatch { .. })));
+    println!("{}", result.unwrap_err());
+
+    // Fallible queries can fold type mismatches into their own error type.
+    let result = db.try_execute_query_result("get_name", &1, || Err::<u32, _>(LookupError::NotFound));
+    assert!(matches!(
+        result,
+        Err(LookupError::Query(QueryError::TypeMismatch { .. }))
+    ));
+
+    let result = db.try_execute_query_result("get_name", &2, || Err::<String, _>(LookupError::NotFound));
+    assert!(matches!(result, Err(LookupError::NotFound)));
+
+    // Infallible lookups panic with the same error, instead of replacing the
+    // result of the other call site.
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| db.execute_query("get_name", &1, || 1_u32)));
+    let _ = std::panic::take_hook();
+
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains("is of type `alloc::string::String`"));
+    assert_eq!(db.get_cached::<_, String>("get_name", &1), Some(String::from("Admin")));
+}
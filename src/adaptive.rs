@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::{Database, Query, QueryCounters, QueryFlags, QueryId};
+
+/// State of a query with [`QueryFlags::ADAPTIVE`], which tracks whether its
+/// results are cheap enough to compute that caching them isn't worthwhile.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Adaptive {
+    /// Duration below which computing a result is considered cheaper than
+    /// looking it up in the cache.
+    threshold: Duration,
+
+    /// Number of consecutive results which were computed faster than the
+    /// threshold.
+    cheap_runs: usize,
+
+    /// Whether results of the query are no longer cached.
+    bypassed: bool,
+}
+
+impl Adaptive {
+    /// Default duration below which computing a result is considered cheaper
+    /// than looking it up, which is a rough estimate of the cost of hashing
+    /// the key, locking the database and cloning the result.
+    pub const DEFAULT_THRESHOLD: Duration = Duration::from_micros(2);
+    /// Number of consecutive cheap results after which caching is bypassed.
+    const SAMPLES: usize = 16;
+
+    /// Records that a result of the query took `duration` to compute.
+    ///
+    /// Returns whether the result should be cached.
+    pub fn record(&mut self, duration: Duration) -> bool {
+        if duration < self.threshold {
+            self.cheap_runs = self.cheap_runs.saturating_add(1);
+        } else {
+            self.cheap_runs = 0;
+        }
+
+        self.bypassed = self.cheap_runs >= Self::SAMPLES;
+
+        !self.bypassed
+    }
+}
+
+impl Adaptive {
+    /// Creates a new [`Adaptive`] with the same threshold, which hasn't
+    /// recorded any results yet.
+    pub fn reset(&self) -> Self {
+        Self {
+            threshold: self.threshold,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            threshold: Self::DEFAULT_THRESHOLD,
+            cheap_runs: 0,
+            bypassed: false,
+        }
+    }
+}
+
+impl Query {
+    /// Sets the duration below which computing a result of the query is
+    /// considered cheaper than looking it up in the cache, for queries with
+    /// [`QueryFlags::ADAPTIVE`].
+    ///
+    /// Defaults to a rough estimate of the cost of a lookup, of two
+    /// microseconds.
+    pub fn set_adaptive_threshold(&mut self, threshold: Duration) {
+        self.adaptive.threshold = threshold;
+    }
+
+    /// Determines whether results of the query are currently cached.
+    ///
+    /// This is only `false` for queries with [`QueryFlags::ADAPTIVE`], whose
+    /// results have consistently been computed faster than the threshold. See
+    /// [`Query::set_adaptive_threshold`].
+    pub fn caches_results(&self) -> bool {
+        !self.adaptive.bypassed
+    }
+
+    /// Records that a result of the query took `duration` to compute, if the
+    /// query is adaptive.
+    ///
+    /// Returns whether caching was bypassed by this result.
+    fn record_adaptive(&mut self, duration: Duration) -> bool {
+        if !self.flags.contains(QueryFlags::ADAPTIVE) {
+            return false;
+        }
+
+        let bypassed = self.adaptive.bypassed;
+
+        !self.adaptive.record(duration) && !bypassed
+    }
+}
+
+impl Database {
+    /// Records that a result of the query with the given ID took `duration`
+    /// to compute, if the query is adaptive.
+    ///
+    /// Once caching is bypassed, all unpinned results of the query are
+    /// invalidated to reclaim their memory, along with all results which
+    /// depend on them.
+    pub(crate) fn record_adaptive(&self, query: QueryId, duration: Duration) {
+        let adaptive = self
+            .read()
+            .get(query)
+            .is_some_and(|query| query.flags.contains(QueryFlags::ADAPTIVE));
+
+        if !adaptive {
+            return;
+        }
+
+        // Adaptive state isn't part of any result, so the revision is left
+        // as-is.
+        let bypassed = {
+            let mut inner = self.write();
+            let id = inner.resolve(query);
+
+            inner
+                .queries
+                .get_mut(&id)
+                .is_some_and(|query| query.record_adaptive(duration))
+        };
+
+        if bypassed {
+            let removed = self.invalidate_unpinned(query);
+            let inner = self.read();
+
+            if let Some(query) = inner.get(query) {
+                QueryCounters::add(&query.counters.evictions, removed as u64);
+            }
+        }
+    }
+
+    /// Sets the duration below which computing a result of the query with the
+    /// given name is considered cheaper than looking it up in the cache. See
+    /// [`Query::set_adaptive_threshold`].
+    pub fn set_adaptive_threshold(&self, name: &str, threshold: Duration) {
+        self.query_mut(name).set_adaptive_threshold(threshold);
+    }
+}
@@ -0,0 +1,56 @@
+use std::ops::Deref;
+
+use parking_lot::MappedRwLockReadGuard;
+
+/// A reference to a cached result within a [`Database`], as returned by
+/// [`Database::get_ref`].
+///
+/// The database is read-locked for as long as the reference is alive, much
+/// like [`std::cell::Ref`] borrows a [`std::cell::RefCell`]. This gives
+/// access to the result without cloning it, but the reference must be dropped
+/// before any query is executed or any result is inserted into the database.
+/// See [`Database::get_ref`] for what happens otherwise.
+///
+/// [`Database`]: crate::Database
+/// [`Database::get_ref`]: crate::Database::get_ref
+pub struct CachedRef<'db, T: ?Sized> {
+    guard: MappedRwLockReadGuard<'db, T>,
+}
+
+impl<'db, T: ?Sized> CachedRef<'db, T> {
+    /// Creates a new [`CachedRef`] from the given guard.
+    pub(crate) fn new(guard: MappedRwLockReadGuard<'db, T>) -> Self {
+        Self { guard }
+    }
+
+    /// Makes a new [`CachedRef`] for a component of the referenced result,
+    /// keeping the database locked.
+    ///
+    /// This is an associated function, so it doesn't conflict with methods of
+    /// the referenced result.
+    pub fn map<U: ?Sized>(this: Self, f: impl FnOnce(&T) -> &U) -> CachedRef<'db, U> {
+        CachedRef {
+            guard: MappedRwLockReadGuard::map(this.guard, f),
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for CachedRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for CachedRef<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Display> std::fmt::Display for CachedRef<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -0,0 +1,101 @@
+use std::hash::Hash;
+
+use parking_lot::RwLockReadGuard;
+
+use crate::observer::CommitLock;
+use crate::{Database, DatabaseInner, Query, QueryName, QueryValue, Revision};
+
+/// Read-only view of the cached results of a [`Database`] at a single
+/// revision, as given to the closure of [`Database::read_txn`].
+///
+/// The view is a lock: the database is read-locked for as long as the view is
+/// alive, so no other thread can commit changes in between two lookups, and
+/// no batch can be open on another thread. All lookups therefore observe the
+/// same [`Revision`], and either all or none of the mutations made within a
+/// [`Database::batch`] on another thread.
+///
+/// Lookups only see results which are already cached. Nothing is computed,
+/// and no dependencies are recorded.
+pub struct CachedView<'db> {
+    inner: RwLockReadGuard<'db, DatabaseInner>,
+}
+
+impl CachedView<'_> {
+    /// Gets the revision which all lookups within the view observe.
+    #[inline]
+    pub fn revision(&self) -> Revision {
+        self.inner.current_revision()
+    }
+
+    /// Gets the query with the given name, if it exists.
+    pub fn query(&self, name: &(impl QueryName + ?Sized)) -> Option<&Query> {
+        self.inner.get(name.query_id())
+    }
+
+    /// Determines whether a result with the given key is cached within the
+    /// query with the given name. See [`Database::contains`].
+    pub fn contains<K: Hash + 'static>(&self, name: &(impl QueryName + ?Sized), key: &K) -> bool {
+        self.query(name).is_some_and(|query| query.contains(key))
+    }
+
+    /// Gets a reference to the cached result with the given key, within the
+    /// query with the given name. See [`Database::get_ref`].
+    ///
+    /// # Returns
+    ///
+    /// If the query does not exist, no value could be found, or the value found
+    /// is not of type [`T`], this method returns [`None`].
+    pub fn get_ref<K: Hash + 'static, T: QueryValue>(&self, name: &(impl QueryName + ?Sized), key: &K) -> Option<&T> {
+        let query = self.query(name)?;
+
+        query.lookup(query.hash_key(key))?.downcast_ref::<T>()
+    }
+
+    /// Gets a clone of the cached result with the given key, within the query
+    /// with the given name. See [`Database::get_cached`].
+    ///
+    /// # Returns
+    ///
+    /// If the query does not exist, no value could be found, or the value found
+    /// is not of type [`T`], this method returns [`None`].
+    pub fn get_cached<K: Hash + 'static, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+    ) -> Option<T> {
+        self.get_ref::<K, T>(name, key).cloned()
+    }
+}
+
+impl Database {
+    /// Invokes `f` within a read transaction, with a read-only view of the
+    /// cached results of the database, in which all lookups observe one
+    /// consistent revision, even if another thread commits changes
+    /// concurrently.
+    ///
+    /// The view is a lock on the database, not a snapshot: lookups through
+    /// the view never compute results and don't record dependencies, like
+    /// [`Database::peek`]. Writers and batches on other threads wait until
+    /// `f` returns, and transactions never observe part of a
+    /// [`Database::batch`] on another thread, so results which are inserted
+    /// together within a batch are seen together. Transactions on other
+    /// threads share the lock, so they don't wait for each other.
+    ///
+    /// Within `f`, the database itself may be used for lookups which don't
+    /// mutate it: cached results are returned as usual, while lookups which
+    /// would compute or revalidate a result fail with
+    /// [`QueryError::ReadOnly`], or panic for methods which don't return
+    /// errors. Any other mutation of the database within `f` panics, as does
+    /// opening a batch, instead of waiting for the transaction to finish.
+    /// Transactions may be nested, and a transaction which is opened within
+    /// a batch on the same thread observes the mutations made by the batch so
+    /// far.
+    ///
+    /// [`QueryError::ReadOnly`]: crate::QueryError::ReadOnly
+    pub fn read_txn<R>(&self, f: impl FnOnce(&CachedView<'_>) -> R) -> R {
+        let _commits = self.lock_commits(CommitLock::Txn);
+        let view = CachedView { inner: self.read() };
+
+        f(&view)
+    }
+}
@@ -0,0 +1,203 @@
+use std::any::Any;
+
+use crate::{Database, QueryId, ResultKey};
+
+/// Lightweight callback, which is invoked with the key of a result whenever
+/// it hits or misses the cache of a query.
+///
+/// Registered using [`Query::on_hit`] and [`Query::on_miss`]. Callbacks are
+/// invoked while the database is locked, so they must not access the
+/// database themselves.
+///
+/// When the `sync` feature is enabled, callbacks must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::on_hit`]: crate::Query::on_hit
+/// [`Query::on_miss`]: crate::Query::on_miss
+#[cfg(not(feature = "sync"))]
+pub trait KeyCallback: Fn(ResultKey) {}
+
+#[cfg(not(feature = "sync"))]
+impl<F: Fn(ResultKey)> KeyCallback for F {}
+
+/// Lightweight callback, which is invoked with the key of a result whenever
+/// it hits or misses the cache of a query.
+///
+/// Registered using [`Query::on_hit`] and [`Query::on_miss`]. Callbacks are
+/// invoked while the database is locked, so they must not access the
+/// database themselves.
+///
+/// When the `sync` feature is enabled, callbacks must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::on_hit`]: crate::Query::on_hit
+/// [`Query::on_miss`]: crate::Query::on_miss
+#[cfg(feature = "sync")]
+pub trait KeyCallback: Fn(ResultKey) + Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<F: Fn(ResultKey) + Send + Sync> KeyCallback for F {}
+
+/// Callback which reports whether the process is under memory pressure, as
+/// registered using [`Database::set_memory_monitor`].
+///
+/// When the `sync` feature is enabled, monitors must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Database::set_memory_monitor`]: crate::Database::set_memory_monitor
+#[cfg(not(feature = "sync"))]
+pub trait MemoryMonitor: Fn() -> bool {}
+
+#[cfg(not(feature = "sync"))]
+impl<F: Fn() -> bool> MemoryMonitor for F {}
+
+/// Callback which reports whether the process is under memory pressure, as
+/// registered using [`Database::set_memory_monitor`].
+///
+/// When the `sync` feature is enabled, monitors must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Database::set_memory_monitor`]: crate::Database::set_memory_monitor
+#[cfg(feature = "sync")]
+pub trait MemoryMonitor: Fn() -> bool + Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<F: Fn() -> bool + Send + Sync> MemoryMonitor for F {}
+
+/// Transform which is applied to every result of type `T` before it is
+/// stored within a query, as registered using [`Query::on_store`].
+///
+/// When the `sync` feature is enabled, transforms must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::on_store`]: crate::Query::on_store
+#[cfg(not(feature = "sync"))]
+pub trait StoreHook<T>: Fn(&mut T) + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<T, F: Fn(&mut T) + 'static> StoreHook<T> for F {}
+
+/// Transform which is applied to every result of type `T` before it is
+/// stored within a query, as registered using [`Query::on_store`].
+///
+/// When the `sync` feature is enabled, transforms must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::on_store`]: crate::Query::on_store
+#[cfg(feature = "sync")]
+pub trait StoreHook<T>: Fn(&mut T) + Send + Sync + 'static {}
+
+#[cfg(feature = "sync")]
+impl<T, F: Fn(&mut T) + Send + Sync + 'static> StoreHook<T> for F {}
+
+/// Shared [`KeyCallback`], so it can be copied into shards of the database.
+#[cfg(not(feature = "sync"))]
+pub(crate) type SharedKeyCallback = std::rc::Rc<dyn KeyCallback>;
+
+/// Shared [`KeyCallback`], so it can be copied into shards of the database.
+#[cfg(feature = "sync")]
+pub(crate) type SharedKeyCallback = std::sync::Arc<dyn KeyCallback>;
+
+/// Type-erased [`StoreHook`], which ignores results of other types than the
+/// one expected by the transform.
+///
+/// Shared, so it can be copied into shards of the database.
+#[cfg(not(feature = "sync"))]
+pub(crate) type ErasedStoreHook = std::rc::Rc<dyn Fn(&mut dyn Any)>;
+
+/// Type-erased [`StoreHook`], which ignores results of other types than the
+/// one expected by the transform.
+///
+/// Shared, so it can be copied into shards of the database.
+#[cfg(feature = "sync")]
+pub(crate) type ErasedStoreHook = std::sync::Arc<dyn Fn(&mut dyn Any) + Send + Sync>;
+
+/// Function which recomputes a result of a query from its original key, as
+/// registered using [`Query::set_recompute`].
+///
+/// When the `sync` feature is enabled, functions must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::set_recompute`]: crate::Query::set_recompute
+#[cfg(not(feature = "sync"))]
+pub trait Recompute<K, T>: Fn(&Database, &K) -> T + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<K, T, F: Fn(&Database, &K) -> T + 'static> Recompute<K, T> for F {}
+
+/// Function which recomputes a result of a query from its original key, as
+/// registered using [`Query::set_recompute`].
+///
+/// When the `sync` feature is enabled, functions must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Query::set_recompute`]: crate::Query::set_recompute
+#[cfg(feature = "sync")]
+pub trait Recompute<K, T>: Fn(&Database, &K) -> T + Send + Sync + 'static {}
+
+#[cfg(feature = "sync")]
+impl<K, T, F: Fn(&Database, &K) -> T + Send + Sync + 'static> Recompute<K, T> for F {}
+
+/// Type-erased [`Recompute`], which recomputes and stores the result with the
+/// given ID and key, from its original key. Returns whether the result was
+/// recomputed, which is not the case if the original key is of another type,
+/// or the computation failed.
+///
+/// Shared, so it can be invoked without keeping the database locked.
+#[cfg(not(feature = "sync"))]
+pub(crate) type ErasedRecompute = std::rc::Rc<dyn Fn(&Database, QueryId, ResultKey, &dyn Any) -> bool>;
+
+/// Type-erased [`Recompute`], which recomputes and stores the result with the
+/// given ID and key, from its original key. Returns whether the result was
+/// recomputed, which is not the case if the original key is of another type,
+/// or the computation failed.
+///
+/// Shared, so it can be invoked without keeping the database locked.
+#[cfg(feature = "sync")]
+pub(crate) type ErasedRecompute = std::sync::Arc<dyn Fn(&Database, QueryId, ResultKey, &dyn Any) -> bool + Send + Sync>;
+
+/// Callbacks attached to a single query.
+#[derive(Default, Clone)]
+pub(crate) struct Callbacks {
+    pub(crate) on_hit: Option<SharedKeyCallback>,
+    pub(crate) on_miss: Option<SharedKeyCallback>,
+    pub(crate) on_store: Option<ErasedStoreHook>,
+    pub(crate) recompute: Option<ErasedRecompute>,
+}
+
+impl Callbacks {
+    /// Invokes the hit callback, if any, with the given key.
+    #[inline]
+    pub(crate) fn hit(&self, key: ResultKey) {
+        if let Some(on_hit) = &self.on_hit {
+            on_hit(key);
+        }
+    }
+
+    /// Invokes the miss callback, if any, with the given key.
+    #[inline]
+    pub(crate) fn miss(&self, key: ResultKey) {
+        if let Some(on_miss) = &self.on_miss {
+            on_miss(key);
+        }
+    }
+
+    /// Invokes the store transform, if any, with the given result.
+    #[inline]
+    pub(crate) fn store(&self, value: &mut dyn Any) {
+        if let Some(on_store) = &self.on_store {
+            on_store(value);
+        }
+    }
+}
+
+impl std::fmt::Debug for Callbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Callbacks")
+            .field("on_hit", &self.on_hit.is_some())
+            .field("on_miss", &self.on_miss.is_some())
+            .field("on_store", &self.on_store.is_some())
+            .field("recompute", &self.recompute.is_some())
+            .finish()
+    }
+}
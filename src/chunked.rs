@@ -0,0 +1,53 @@
+use std::hash::Hash;
+
+use crate::{Database, QueryName, QueryValue};
+
+impl Database {
+    /// Looks up the collection with the given key within the query instance
+    /// with the given name, which is stored in `chunks` separate chunks.
+    ///
+    /// Every chunk is cached as a separate result, keyed by the given key and
+    /// the index of the chunk. Chunks which could not be found are computed
+    /// by invoking `f` with their index. The items of all chunks are returned
+    /// in order.
+    ///
+    /// Since chunks are cached separately, a single chunk can be invalidated
+    /// using [`Database::invalidate_chunk`], without recomputing the entire
+    /// collection.
+    pub fn execute_chunked<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        chunks: usize,
+        f: impl Fn(usize) -> Vec<T>,
+    ) -> Vec<T> {
+        let mut items = Vec::new();
+
+        for index in 0..chunks {
+            items.extend(self.execute_query(name, &(key, index), || f(index)));
+        }
+
+        items
+    }
+
+    /// Invalidates the chunk with the given index, of the collection with the
+    /// given key, within the query with the given name, along with all results
+    /// which depend on it. See [`Database::execute_chunked`] and
+    /// [`Database::invalidate`].
+    ///
+    /// Chunks are keyed by a borrowed key, so invalidation rules are not
+    /// applied to them.
+    ///
+    /// Returns whether the chunk was cached.
+    pub fn invalidate_chunk<K: Hash>(&self, name: &(impl QueryName + ?Sized), key: &K, index: usize) -> bool {
+        let id = name.query_id();
+
+        // Chunks can't be computed for queries with a key normalizer, so there
+        // is nothing to invalidate.
+        let Ok(key) = self.hash_unnormalized(id, &(key, index)) else {
+            return false;
+        };
+
+        self.invalidate_key(id, key, None)
+    }
+}
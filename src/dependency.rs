@@ -0,0 +1,870 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::{ChangeSet, Database, DatabaseInner, QueryFlags, QueryId, QueryName, QueryValue, ResultKey, Slot};
+
+/// Result within the dependency graph, identified by its query and key.
+pub(crate) type Node = (QueryId, ResultKey);
+
+/// Estimate of the results which would be affected by changing a single
+/// result, as reported by [`Database::estimate_impact`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Impact {
+    /// Number of results which transitively depend on the result, whether
+    /// they are currently cached or not.
+    pub dependents: usize,
+
+    /// Number of dependent results which are currently cached, and would be
+    /// invalidated or revalidated.
+    pub cached: usize,
+
+    /// Total time it took to compute the cached dependent results, for those
+    /// whose computation time is known.
+    pub recompute_time: Duration,
+}
+
+/// Progress of revalidating dirty results within a time budget, as returned
+/// by [`Database::revalidate_for`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Revalidation {
+    /// Number of dirty results which were found to be up-to-date, and were
+    /// marked as clean.
+    pub verified: usize,
+
+    /// Number of dirty results which were found to be outdated, and were
+    /// discarded.
+    pub discarded: usize,
+
+    /// Number of results which are still marked as dirty, since the budget
+    /// ran out.
+    pub remaining: usize,
+}
+
+/// Progress of recomputing dirty results within a time budget, as returned
+/// by [`Database::pump`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Pump {
+    /// Number of dirty results which were found to be up-to-date, and were
+    /// marked as clean.
+    pub verified: usize,
+
+    /// Number of dirty results which were found to be outdated, and were
+    /// recomputed.
+    pub recomputed: usize,
+
+    /// Number of results which are still marked as dirty, since the budget
+    /// ran out, or since they can't be recomputed.
+    pub remaining: usize,
+
+    /// Whether pumping was stopped early, since an interactive query was
+    /// executed. See [`Database::execute_with_priority`].
+    pub preempted: bool,
+}
+
+/// Priority with which a query is executed, relative to dirty results being
+/// pumped. See [`Database::execute_with_priority`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// The query is executed alongside pumping, like any other query.
+    #[default]
+    Background,
+
+    /// The query is being waited on, such as by the user of an editor, so
+    /// pumping yields to it.
+    Interactive,
+}
+
+/// Marks an interactive query as executing, until it is dropped.
+struct InteractiveGuard<'db>(&'db Database);
+
+impl Drop for InteractiveGuard<'_> {
+    fn drop(&mut self) {
+        self.0.interactive.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Shape of the dependency graph around the results of a single query, as
+/// reported by [`Database::dependency_shapes`].
+///
+/// Queries which are too coarse show up with many dependents, since every
+/// change to one of their results invalidates all of them. Queries which are
+/// too fine show up with a high fan-out and depth in the queries which use
+/// them, since every result must be tracked separately.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DependencyShape {
+    /// Name of the query.
+    pub query: String,
+
+    /// Number of results of the query which are currently cached.
+    pub results: usize,
+
+    /// Length of the longest chain of dependencies below any cached result
+    /// of the query, where a result without any dependencies has a depth of
+    /// zero.
+    pub max_depth: usize,
+
+    /// Number of results which the cached results of the query directly
+    /// depend on, summed across all of them.
+    pub dependencies: usize,
+
+    /// Number of distinct results which directly depend on any result of the
+    /// query.
+    pub dependents: usize,
+}
+
+impl DependencyShape {
+    /// Gets the average number of results which each cached result of the
+    /// query directly depends on.
+    #[allow(clippy::cast_precision_loss, reason = "an estimate is sufficient for metrics")]
+    pub fn average_fan_out(&self) -> f64 {
+        if self.results == 0 {
+            return 0.0;
+        }
+
+        self.dependencies as f64 / self.results as f64
+    }
+}
+
+/// Graph of dependencies between results, in both directions.
+#[derive(Default)]
+pub(crate) struct DependencyGraph {
+    /// Results which each result depends on.
+    dependencies: HashMap<Node, HashSet<Node>>,
+
+    /// Results which depend on each result.
+    dependents: HashMap<Node, HashSet<Node>>,
+
+    /// Results which may be outdated, since one of their transitive
+    /// dependencies has changed.
+    dirty: HashSet<Node>,
+
+    /// Results which had more dependencies than allowed by their query, so
+    /// their dependencies are no longer tracked. See
+    /// [`Query::set_max_dependencies`].
+    ///
+    /// [`Query::set_max_dependencies`]: crate::Query::set_max_dependencies
+    overflowed: HashSet<Node>,
+
+    /// Results which may be outdated without being marked as dirty, since
+    /// they, or any of their transitive dependencies, have overflowed.
+    untracked: HashSet<Node>,
+}
+
+impl DependencyGraph {
+    /// Adds an edge, which declares that `dependent` depends on `dependency`.
+    pub fn add(&mut self, dependent: Node, dependency: Node) {
+        self.dependencies.entry(dependent).or_default().insert(dependency);
+        self.dependents.entry(dependency).or_default().insert(dependent);
+    }
+
+    /// Gets the number of results which the given result directly depends on.
+    pub fn dependency_count(&self, node: Node) -> usize {
+        self.dependencies.get(&node).map_or(0, HashSet::len)
+    }
+
+    /// Gets all results which directly depend on the given result.
+    pub fn dependents(&self, node: Node) -> impl Iterator<Item = Node> + '_ {
+        self.dependents.get(&node).into_iter().flatten().copied()
+    }
+
+    /// Gets all results which depend on any result within the given query.
+    pub fn dependents_of_query(&self, query: QueryId) -> impl Iterator<Item = Node> + '_ {
+        self.dependents
+            .iter()
+            .filter(move |(node, _)| node.0 == query)
+            .flat_map(|(_, dependents)| dependents.iter().copied())
+    }
+
+    /// Gets all results which the given result directly depends on.
+    pub fn dependencies(&self, node: Node) -> impl Iterator<Item = Node> + '_ {
+        self.dependencies.get(&node).into_iter().flatten().copied()
+    }
+
+    /// Removes all edges from the given result to its dependencies, so they
+    /// can be recorded again.
+    pub fn remove_dependencies(&mut self, node: Node) {
+        for dependency in self.dependencies.remove(&node).unwrap_or_default() {
+            if let Some(dependents) = self.dependents.get_mut(&dependency) {
+                dependents.remove(&node);
+            }
+        }
+    }
+
+    /// Gets the length of the longest chain of dependencies below every result
+    /// which has any dependencies.
+    ///
+    /// Edges which would close a cycle are ignored, so every chain is finite.
+    pub fn depths(&self) -> HashMap<Node, usize> {
+        let mut depths = HashMap::new();
+        let mut visiting = HashSet::new();
+
+        for start in self.dependencies.keys() {
+            let mut pending = vec![(*start, false)];
+
+            while let Some((node, expanded)) = pending.pop() {
+                if expanded {
+                    let depth = self
+                        .dependencies(node)
+                        .map(|dependency| depths.get(&dependency).copied().unwrap_or_default() + 1)
+                        .max()
+                        .unwrap_or_default();
+
+                    depths.insert(node, depth);
+                    visiting.remove(&node);
+
+                    continue;
+                }
+
+                if depths.contains_key(&node) || !visiting.insert(node) {
+                    continue;
+                }
+
+                pending.push((node, true));
+
+                for dependency in self.dependencies(node) {
+                    if !depths.contains_key(&dependency) && !visiting.contains(&dependency) {
+                        pending.push((dependency, false));
+                    }
+                }
+            }
+        }
+
+        depths
+    }
+
+    /// Gets all results which transitively depend on the given result.
+    pub fn transitive_dependents(&self, node: Node) -> HashSet<Node> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![node];
+
+        while let Some(node) = pending.pop() {
+            for dependent in self.dependents.get(&node).into_iter().flatten() {
+                if visited.insert(*dependent) {
+                    pending.push(*dependent);
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Marks all results which transitively depend on the given result as
+    /// dirty.
+    pub fn mark_dirty(&mut self, node: Node) {
+        let mut pending = vec![node];
+
+        while let Some(node) = pending.pop() {
+            for dependent in self.dependents.get(&node).into_iter().flatten() {
+                if self.dirty.insert(*dependent) {
+                    pending.push(*dependent);
+                }
+            }
+        }
+    }
+
+    /// Marks the given result as dirty, along with all results which
+    /// transitively depend on it.
+    pub fn mark_outdated(&mut self, node: Node) {
+        self.dirty.insert(node);
+        self.mark_dirty(node);
+    }
+
+    /// Determines whether the given result is marked as dirty.
+    pub fn is_dirty(&self, node: Node) -> bool {
+        self.dirty.contains(&node)
+    }
+
+    /// Marks the given result as being up-to-date.
+    pub fn mark_clean(&mut self, node: Node) {
+        self.dirty.remove(&node);
+    }
+
+    /// Stops tracking the dependencies of the given result, since it has more
+    /// dependencies than allowed, and removes the existing ones.
+    ///
+    /// Since changes to its dependencies are no longer propagated, the result
+    /// and all results which transitively depend on it are marked as
+    /// untracked.
+    pub fn mark_overflowed(&mut self, node: Node) {
+        self.remove_dependencies(node);
+        self.overflowed.insert(node);
+        self.mark_untracked(node);
+    }
+
+    /// Marks the given result and all results which transitively depend on it
+    /// as untracked.
+    pub fn mark_untracked(&mut self, node: Node) {
+        let dependents = self.transitive_dependents(node);
+
+        self.untracked.insert(node);
+        self.untracked.extend(dependents);
+    }
+
+    /// Determines whether the given result may be outdated without being
+    /// marked as dirty.
+    pub fn is_untracked(&self, node: Node) -> bool {
+        self.untracked.contains(&node)
+    }
+
+    /// Determines whether the dependencies of the given result are no longer
+    /// tracked, since it has more dependencies than allowed.
+    pub fn is_overflowed(&self, node: Node) -> bool {
+        self.overflowed.contains(&node)
+    }
+
+    /// Creates a copy of the graph, which only contains edges and dirty
+    /// markers between results of the given queries.
+    pub fn subset(&self, queries: &HashSet<QueryId>) -> DependencyGraph {
+        let contains = |node: &Node| queries.contains(&node.0);
+        let mut subset = DependencyGraph::default();
+
+        for (dependent, dependencies) in self.dependencies.iter().filter(|(node, _)| contains(node)) {
+            for dependency in dependencies.iter().filter(|node| contains(node)) {
+                subset.add(*dependent, *dependency);
+            }
+        }
+
+        subset.dirty = self.dirty.iter().copied().filter(contains).collect();
+        subset.overflowed = self.overflowed.iter().copied().filter(contains).collect();
+        subset.untracked = self.untracked.iter().copied().filter(contains).collect();
+        subset
+    }
+
+    /// Records that the given dependent result depends on the given result,
+    /// which it accessed while it was executing.
+    pub(crate) fn record(&mut self, inner: &DatabaseInner, dependent: Node, dependency: Node) {
+        if dependent == dependency || self.is_overflowed(dependent) {
+            return;
+        }
+
+        // Results of queries with `ALWAYS` are recomputed on every access, so
+        // their dependencies never need to be revalidated.
+        let always = |node: Node| {
+            inner
+                .get(node.0)
+                .is_some_and(|query| query.flags.contains(QueryFlags::ALWAYS))
+        };
+
+        if always(dependent) {
+            return;
+        }
+
+        // Results with an enormous number of dependencies are recomputed on
+        // every access, instead of tracking all of their dependencies.
+        let limit = inner.get(dependent.0).and_then(|query| query.max_dependencies);
+
+        if limit.is_some_and(|limit| self.dependency_count(dependent) >= limit) {
+            self.mark_overflowed(dependent);
+            return;
+        }
+
+        self.add(dependent, dependency);
+
+        // Results which depend on results of queries with `ALWAYS` may be
+        // outdated on every access, just like the results they depend on.
+        if self.is_untracked(dependency) || always(dependency) {
+            self.mark_untracked(dependent);
+        }
+    }
+
+    /// Adds the edges and markers of the given results from `other`, such as
+    /// when the results are merged from a shard.
+    pub fn merge(&mut self, other: &DependencyGraph, nodes: &HashSet<Node>) {
+        for (dependent, dependencies) in other.dependencies.iter().filter(|(node, _)| nodes.contains(node)) {
+            for dependency in dependencies {
+                self.add(*dependent, *dependency);
+            }
+        }
+
+        self.dirty.extend(other.dirty.intersection(nodes));
+        self.overflowed.extend(other.overflowed.intersection(nodes));
+
+        for node in other.untracked.intersection(nodes) {
+            self.mark_untracked(*node);
+        }
+    }
+
+    /// Removes all edges and dirty markers from the graph.
+    pub fn clear(&mut self) {
+        self.dependencies.clear();
+        self.dependents.clear();
+        self.dirty.clear();
+        self.overflowed.clear();
+        self.untracked.clear();
+    }
+}
+
+impl Database {
+    /// Declares that the result of the `dependent` query with the given key
+    /// depends on the result of the `dependency` query with the given key.
+    ///
+    /// Whenever the dependency is invalidated, using [`Database::invalidate`]
+    /// or [`Database::clear`], the dependent result is invalidated as well.
+    /// Whenever the dependency is changed using [`Database::insert`], the
+    /// dependent result is marked as dirty. This allows results which are
+    /// computed outside of [`Database::execute_query`] to be kept up-to-date.
+    ///
+    /// Dependencies between queries executed within each other are recorded
+    /// automatically, so they don't need to be declared.
+    ///
+    /// Both queries are given as a tuple of the query name and the key of the
+    /// result within the query.
+    pub fn declare_dependency<K1: Hash + 'static, K2: Hash + 'static>(
+        &self,
+        dependent: (&str, &K1),
+        dependency: (&str, &K2),
+    ) {
+        let inner = self.read();
+        let mut graph = self.dependencies.lock();
+
+        let dependent = inner.node(QueryId::from_name(dependent.0), dependent.1);
+        let dependency = inner.node(QueryId::from_name(dependency.0), dependency.1);
+
+        graph.add(dependent, dependency);
+    }
+
+    /// Records that the query which is currently executing on this thread
+    /// depends on the results of the query with the given ID, with the given
+    /// keys, which were accessed on other threads.
+    #[cfg(all(feature = "rayon", feature = "sync"))]
+    pub(crate) fn record_dependencies(&self, query: QueryId, keys: &[ResultKey]) {
+        let Some(caller) = self.current_query() else {
+            return;
+        };
+
+        let inner = self.read();
+        let mut graph = self.dependencies.lock();
+        let dependent = (inner.resolve(caller.query), caller.key);
+
+        for key in keys {
+            graph.record(&inner, dependent, (inner.resolve(query), *key));
+        }
+    }
+
+    /// Gets the names and keys of all results which the result of the query
+    /// with the given key directly depends on.
+    pub fn dependencies_of<K: Hash + 'static>(&self, name: &str, key: &K) -> Vec<(String, ResultKey)> {
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+        let node = inner.node(QueryId::from_name(name), key);
+
+        graph
+            .dependencies(node)
+            .filter_map(|(query, key)| Some((inner.get(query)?.name.clone(), key)))
+            .collect()
+    }
+
+    /// Removes all dependencies from the database, including those recorded
+    /// during execution.
+    pub fn clear_dependencies(&self) {
+        self.dependencies.lock().clear();
+    }
+
+    /// Determines whether the result of the query with the given key is marked
+    /// as dirty, since one of its transitive dependencies has been changed
+    /// using [`Database::insert`].
+    ///
+    /// Dirty results are not discarded right away. Instead, they are
+    /// revalidated when they are requested again: if none of their
+    /// dependencies have changed since the result was computed, the result is
+    /// reused. Otherwise, the result is recomputed. Dirty dependencies are
+    /// verified first, and recomputed if possible, so results are reused when
+    /// their dependencies are recomputed without changing. See
+    /// [`Query::set_recompute`].
+    ///
+    /// [`Query::set_recompute`]: crate::Query::set_recompute
+    pub fn is_dirty<K: Hash + 'static>(&self, name: &str, key: &K) -> bool {
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+
+        graph.is_dirty(inner.node(QueryId::from_name(name), key))
+    }
+
+    /// Estimates how many results would be affected by changing the result of
+    /// the query with the given key, by walking all results which
+    /// transitively depend on it, without changing anything.
+    ///
+    /// This can be used to warn about expensive edits, or to decide how to
+    /// schedule the work following a change. Results affected by invalidation
+    /// rules are not included, since they are not part of the dependency
+    /// graph. See [`Database::add_invalidation_rule`].
+    pub fn estimate_impact<K: Hash + 'static>(&self, name: &str, key: &K) -> Impact {
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+
+        let node = inner.node(QueryId::from_name(name), key);
+        let dependents = graph.transitive_dependents(node);
+
+        let mut impact = Impact {
+            dependents: dependents.len(),
+            ..Impact::default()
+        };
+
+        for (query, key) in dependents {
+            let Some(slot) = inner.get(query).and_then(|query| query.results.get(&key)) else {
+                continue;
+            };
+
+            impact.cached += 1;
+            impact.recompute_time += slot.duration.unwrap_or_default();
+        }
+
+        impact
+    }
+
+    /// Gets the shape of the dependency graph around the results of every
+    /// query, sorted by the name of the query. See [`DependencyShape`].
+    pub fn dependency_shapes(&self) -> Vec<DependencyShape> {
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+
+        let depths = graph.depths();
+
+        let mut shapes = inner
+            .queries
+            .iter()
+            .map(|(id, query)| {
+                let nodes = query.results.keys().map(|key| (*id, *key));
+
+                DependencyShape {
+                    query: query.name.clone(),
+                    results: query.results.len(),
+                    max_depth: nodes
+                        .clone()
+                        .filter_map(|node| depths.get(&node).copied())
+                        .max()
+                        .unwrap_or_default(),
+                    dependencies: nodes.map(|node| graph.dependency_count(node)).sum(),
+                    dependents: graph.dependents_of_query(*id).collect::<HashSet<_>>().len(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        shapes.sort_by(|a, b| a.query.cmp(&b.query));
+
+        shapes
+    }
+
+    /// Revalidates results which are marked as dirty, until the given time
+    /// budget runs out, so that less work remains when they are requested.
+    ///
+    /// Results are revalidated after their dependencies, and otherwise in
+    /// the order of when they were last computed or reused, latest first, so
+    /// the results which are most likely to be requested again are
+    /// revalidated before the budget runs out.
+    /// Results which are up-to-date are marked as clean, while results which
+    /// are outdated are discarded, as if they were invalidated. Since results
+    /// are not recomputed, results which depend on discarded results are
+    /// discarded as well, even if recomputing the discarded result would have
+    /// produced the same value. See [`Database::pump`] to recompute them
+    /// instead.
+    ///
+    /// This allows an editor to clean up between keystrokes. The budget is
+    /// checked between results, so it may be exceeded slightly.
+    pub fn revalidate_for(&self, budget: Duration) -> Revalidation {
+        fn slot(inner: &DatabaseInner, (query, key): Node) -> Option<&Slot> {
+            inner.get(query).and_then(|query| query.results.get(&key))
+        }
+
+        let start = Instant::now();
+        let mut progress = Revalidation::default();
+        let mut changes = ChangeSet::default();
+
+        {
+            let mut inner = self.write();
+            let mut graph = self.dependencies.lock();
+
+            let accessed_at = |inner: &DatabaseInner, node: Node| slot(inner, node).map(Slot::accessed_at);
+
+            // Number of dirty dependencies of each dirty result, which must be
+            // revalidated before the result itself.
+            let mut blocked = graph
+                .dirty
+                .iter()
+                .map(|node| {
+                    (
+                        *node,
+                        graph.dependencies(*node).filter(|dep| graph.is_dirty(*dep)).count(),
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+
+            let mut ready = blocked
+                .iter()
+                .filter(|(_, count)| **count == 0)
+                .map(|(node, _)| (accessed_at(&inner, *node), *node))
+                .collect::<BinaryHeap<_>>();
+
+            while start.elapsed() < budget {
+                // Dependencies between dirty results may form a cycle, in which
+                // case any remaining result is revalidated next.
+                let node = match ready.pop() {
+                    Some((_, node)) => node,
+                    None => match blocked.keys().next() {
+                        Some(node) => *node,
+                        None => break,
+                    },
+                };
+
+                if blocked.remove(&node).is_none() {
+                    continue;
+                }
+
+                let valid = match slot(&inner, node).map(|slot| slot.inserted_tick) {
+                    Some(_) if graph.is_untracked(node) => false,
+                    Some(inserted_at) => graph.dependencies(node).all(|dependency| {
+                        !graph.is_dirty(dependency)
+                            && slot(&inner, dependency).is_some_and(|slot| slot.modified_tick <= inserted_at)
+                    }),
+                    None => true,
+                };
+
+                if valid {
+                    progress.verified += 1;
+                } else {
+                    let query = inner.query_mut_by_id(node.0);
+                    query.remove(node.1);
+
+                    changes.invalidated.push((query.name.clone(), node.1));
+                    progress.discarded += 1;
+                }
+
+                graph.mark_clean(node);
+
+                for dependent in graph.dependents(node).collect::<Vec<_>>() {
+                    if let Some(count) = blocked.get_mut(&dependent) {
+                        *count = count.saturating_sub(1);
+
+                        if *count == 0 {
+                            ready.push((accessed_at(&inner, dependent), dependent));
+                        }
+                    }
+                }
+            }
+
+            progress.remaining = graph.dirty.len();
+        }
+
+        self.publish(changes);
+
+        progress
+    }
+
+    /// Recomputes results which are marked as dirty, until the given time
+    /// budget runs out, so that they are up-to-date when they are requested.
+    ///
+    /// Unlike [`Database::revalidate_for`], outdated results are recomputed
+    /// instead of discarded, using the recompute function of their query.
+    /// See [`Query::set_recompute`]. Outdated results which can't be
+    /// recomputed are left dirty, to be recomputed once they are requested.
+    ///
+    /// Results are pumped after their dependencies, so the dirty wave is
+    /// stopped as early as possible by dependencies which are recomputed with
+    /// an unchanged value, and otherwise in the order of when they were last
+    /// computed or reused, latest first, like [`Database::revalidate_for`]. The
+    /// budget is checked between results, so it may be exceeded by the time
+    /// it takes to recompute a single result.
+    ///
+    /// Recomputed results are only computed on the calling thread. With the
+    /// `sync` feature, the database can be pumped from a background thread,
+    /// while other threads request results. Pumping stops early whenever an
+    /// interactive query is executed, so it doesn't contend with the query
+    /// for the database. See [`Database::execute_with_priority`].
+    ///
+    /// [`Query::set_recompute`]: crate::Query::set_recompute
+    pub fn pump(&self, budget: Duration) -> Pump {
+        let start = Instant::now();
+        let mut progress = Pump::default();
+
+        let pending = {
+            let inner = self.read();
+            let graph = self.dependencies.lock();
+            let depths = graph.depths();
+
+            let mut pending = graph
+                .dirty
+                .iter()
+                .map(|node| {
+                    let accessed_at = inner
+                        .get(node.0)
+                        .and_then(|query| query.results.get(&node.1))
+                        .map(Slot::accessed_at);
+
+                    (
+                        depths.get(node).copied().unwrap_or_default(),
+                        Reverse(accessed_at),
+                        *node,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            pending.sort_unstable();
+            pending
+        };
+
+        for (_, _, node) in pending {
+            if self.interactive.load(Ordering::Acquire) > 0 {
+                progress.preempted = true;
+                break;
+            }
+
+            if start.elapsed() >= budget {
+                break;
+            }
+
+            // Results may have been verified or recomputed along with one of
+            // their dependents in the meantime.
+            if !self.dependencies.lock().is_dirty(node) {
+                continue;
+            }
+
+            if self.deep_verify(node, &mut HashSet::new()) {
+                progress.verified += 1;
+            } else if self.recompute(node) {
+                progress.recomputed += 1;
+            }
+        }
+
+        progress.remaining = self.dependencies.lock().dirty.len();
+
+        progress
+    }
+
+    /// Looks up the given key within the query instance with the given name,
+    /// with the given priority.
+    ///
+    /// Behaves like [`Database::execute_query`]. While interactive queries
+    /// are executing, [`Database::pump`] stops between results, so work
+    /// which is being waited on is never queued behind background work.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_with_priority<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        priority: Priority,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let _guard = (priority == Priority::Interactive).then(|| {
+            self.interactive.fetch_add(1, Ordering::AcqRel);
+
+            InteractiveGuard(self)
+        });
+
+        self.execute_query(name, key, f)
+    }
+
+    /// Prepares the result with the given key within the query with the given
+    /// ID to be recomputed, by removing its recorded dependencies and marking
+    /// it as clean.
+    pub(crate) fn begin_recompute(&self, query: QueryId, key: ResultKey) {
+        let node = (self.read().resolve(query), key);
+        let mut graph = self.dependencies.lock();
+
+        graph.remove_dependencies(node);
+        graph.mark_clean(node);
+        graph.overflowed.remove(&node);
+        graph.untracked.remove(&node);
+    }
+
+    /// Determines whether the cached result with the given key within the
+    /// query with the given ID may be trusted.
+    ///
+    /// Results whose dependencies are no longer tracked, or which depend on
+    /// such results, are never valid.
+    /// Results which aren't dirty are always valid. Dirty results are valid if
+    /// none of their dependencies have changed since the result was inserted,
+    /// in which case the result is marked as clean again.
+    ///
+    /// Dirty dependencies are verified first, recursively. Dependencies which
+    /// are outdated are recomputed, if their query can recompute them. See
+    /// [`Query::set_recompute`]. Dependencies which were recomputed with an
+    /// unchanged checksum are not considered to be changed, which stops the
+    /// propagation of changes through results which are expensive to compare.
+    ///
+    /// [`Query::set_recompute`]: crate::Query::set_recompute
+    pub(crate) fn revalidate(&self, query: QueryId, key: ResultKey) -> bool {
+        let node = (self.read().resolve(query), key);
+
+        self.deep_verify(node, &mut HashSet::new())
+    }
+
+    /// Determines whether the given result may be trusted, after verifying
+    /// all of its dirty dependencies. See [`Database::revalidate`].
+    ///
+    /// Results which are already being verified are part of a cycle, so they
+    /// are treated as outdated.
+    fn deep_verify(&self, node: Node, verifying: &mut HashSet<Node>) -> bool {
+        let (inserted_at, dependencies) = {
+            let inner = self.read();
+            let graph = self.dependencies.lock();
+
+            if graph.is_untracked(node) {
+                return false;
+            }
+
+            if !graph.is_dirty(node) {
+                return true;
+            }
+
+            let Some(slot) = inner.get(node.0).and_then(|query| query.results.get(&node.1)) else {
+                return true;
+            };
+
+            (slot.inserted_tick, graph.dependencies(node).collect::<Vec<_>>())
+        };
+
+        if !verifying.insert(node) {
+            return false;
+        }
+
+        for dependency in dependencies {
+            let outdated = {
+                let graph = self.dependencies.lock();
+
+                graph.is_dirty(dependency) || graph.is_untracked(dependency)
+            };
+
+            if outdated && !self.deep_verify(dependency, verifying) && !self.recompute(dependency) {
+                return false;
+            }
+
+            let inner = self.read();
+
+            let unchanged = inner
+                .get(dependency.0)
+                .and_then(|query| query.results.get(&dependency.1))
+                .is_some_and(|slot| slot.modified_tick <= inserted_at);
+
+            if !unchanged {
+                return false;
+            }
+        }
+
+        self.dependencies.lock().mark_clean(node);
+
+        true
+    }
+
+    /// Recomputes the given result using the recompute function of its query,
+    /// if it has one and the original key of the result was retained.
+    ///
+    /// Returns whether the result was recomputed.
+    fn recompute(&self, (query, key): Node) -> bool {
+        let Some((recompute, original)) = self.read().get(query).and_then(|found| {
+            let recompute = found.callbacks.recompute.clone()?;
+            let (original, clone) = found.results.get(&key)?.key.as_ref()?;
+
+            Some((recompute, clone(&**original)))
+        }) else {
+            return false;
+        };
+
+        recompute(self, query, key, &*original)
+    }
+}
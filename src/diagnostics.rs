@@ -0,0 +1,230 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::{QueryId, ResultKey};
+
+/// A result key which repeatedly missed the cache within a short window,
+/// as reported by [`Database::stampede_report`].
+///
+/// [`Database::stampede_report`]: crate::Database::stampede_report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stampede {
+    /// Name of the query which holds the result.
+    pub query: String,
+
+    /// Key of the result which missed the cache.
+    pub key: ResultKey,
+
+    /// Highest number of misses observed within a single window.
+    pub misses: usize,
+}
+
+/// A result key for which a query produced different results across
+/// identical invocations, as reported by [`Database::nondeterminism_report`].
+///
+/// [`Database::nondeterminism_report`]: crate::Database::nondeterminism_report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nondeterminism {
+    /// Name of the query which produced the results.
+    pub query: String,
+
+    /// Key of the result which differed between invocations.
+    pub key: ResultKey,
+}
+
+/// A query which accessed another query during its execution, as reported by
+/// [`Database::reentrancy_report`].
+///
+/// [`Database::reentrancy_report`]: crate::Database::reentrancy_report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReentrantCall {
+    /// Name of the query which was executing.
+    pub caller: String,
+
+    /// Name of the query which was accessed during the execution.
+    pub callee: String,
+
+    /// Number of times the callee was accessed by the caller.
+    pub count: usize,
+}
+
+/// A result which took longer to compute than the configured threshold, as
+/// reported by [`Database::slow_query_log`].
+///
+/// [`Database::slow_query_log`]: crate::Database::slow_query_log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowQuery {
+    /// Name of the query which computed the result.
+    pub query: String,
+
+    /// Key of the result which was computed.
+    pub key: ResultKey,
+
+    /// Label of the key, if one was captured when the result was computed.
+    /// See [`Database::enable_key_labels`].
+    ///
+    /// [`Database::enable_key_labels`]: crate::Database::enable_key_labels
+    pub label: Option<String>,
+
+    /// Time it took to compute the result.
+    pub duration: Duration,
+
+    /// Number of results which the result depends on.
+    pub dependencies: usize,
+}
+
+/// History of cache misses for a single result key.
+struct MissHistory {
+    query: String,
+    recent: VecDeque<Instant>,
+    peak: usize,
+}
+
+/// Diagnostic which records cache misses, to find keys which are recomputed
+/// over and over again.
+pub(crate) struct StampedeDetector {
+    window: Duration,
+    threshold: usize,
+    keys: HashMap<(QueryId, ResultKey), MissHistory>,
+}
+
+impl StampedeDetector {
+    /// Creates a new [`StampedeDetector`], which flags keys that miss the cache
+    /// at least `threshold` times within `window`.
+    pub fn new(window: Duration, threshold: usize) -> Self {
+        Self {
+            window,
+            threshold,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Records a cache miss for the given key within the given query.
+    pub fn record_miss(&mut self, query: &str, key: ResultKey) {
+        let now = Instant::now();
+
+        let history = self
+            .keys
+            .entry((QueryId::from_name(query), key))
+            .or_insert_with(|| MissHistory {
+                query: query.to_string(),
+                recent: VecDeque::new(),
+                peak: 0,
+            });
+
+        while history
+            .recent
+            .front()
+            .is_some_and(|first| now.duration_since(*first) > self.window)
+        {
+            history.recent.pop_front();
+        }
+
+        history.recent.push_back(now);
+        history.peak = history.peak.max(history.recent.len());
+    }
+
+    /// Gets the keys which have missed the cache at least as many times as
+    /// the threshold within a single window, ordered by the number of misses.
+    pub fn report(&self, limit: usize) -> Vec<Stampede> {
+        let mut offenders = self
+            .keys
+            .iter()
+            .filter(|(_, history)| history.peak >= self.threshold)
+            .map(|((_, key), history)| Stampede {
+                query: history.query.clone(),
+                key: *key,
+                misses: history.peak,
+            })
+            .collect::<Vec<_>>();
+
+        offenders.sort_by_key(|offender| std::cmp::Reverse(offender.misses));
+        offenders.truncate(limit);
+
+        offenders
+    }
+}
+
+/// Diagnostic which records queries accessing other queries during their
+/// execution, to build a call-graph of the queries.
+#[derive(Default)]
+pub(crate) struct ReentrancyAudit {
+    calls: HashMap<(String, String), usize>,
+}
+
+impl ReentrancyAudit {
+    /// Records that the `caller` query accessed the `callee` query.
+    pub fn record(&mut self, caller: &str, callee: &str) {
+        *self.calls.entry((caller.to_string(), callee.to_string())).or_default() += 1;
+    }
+
+    /// Gets all recorded calls, ordered by the number of calls.
+    pub fn report(&self) -> Vec<ReentrantCall> {
+        let mut calls = self
+            .calls
+            .iter()
+            .map(|((caller, callee), count)| ReentrantCall {
+                caller: caller.clone(),
+                callee: callee.clone(),
+                count: *count,
+            })
+            .collect::<Vec<_>>();
+
+        calls.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.caller.cmp(&b.caller))
+                .then_with(|| a.callee.cmp(&b.callee))
+        });
+
+        calls
+    }
+}
+
+/// Diagnostic which records results that took longer to compute than a
+/// threshold, keeping only the most recent ones.
+pub(crate) struct SlowQueryLog {
+    threshold: Duration,
+    entries: VecDeque<SlowQuery>,
+}
+
+impl SlowQueryLog {
+    /// Maximum number of entries kept in the log, after which the oldest
+    /// entries are discarded.
+    const CAPACITY: usize = 256;
+
+    /// Creates a new [`SlowQueryLog`], which records results that took at
+    /// least `threshold` to compute.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Sets the threshold above which results are recorded.
+    pub fn set_threshold(&mut self, threshold: Duration) {
+        self.threshold = threshold;
+    }
+
+    /// Determines whether a result which took the given duration to compute
+    /// should be recorded.
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        duration >= self.threshold
+    }
+
+    /// Records the given slow result, discarding the oldest entry if the log
+    /// is full.
+    pub fn record(&mut self, entry: SlowQuery) {
+        if self.entries.len() == Self::CAPACITY {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+
+    /// Gets all recorded entries, from oldest to newest.
+    pub fn report(&self) -> Vec<SlowQuery> {
+        self.entries.iter().cloned().collect()
+    }
+}
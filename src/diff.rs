@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+
+use crate::{Database, Query, QueryId, QueryName, QueryValue, ResultKey, Slot};
+
+/// Items which were added to and removed from a collection, compared to a
+/// previous version of the collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff<T> {
+    /// Items which are only present in the current collection.
+    pub added: Vec<T>,
+
+    /// Items which are only present in the previous collection.
+    pub removed: Vec<T>,
+}
+
+impl<T> Diff<T> {
+    /// Determines whether the collections are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Collection which can be compared against a previous version of itself.
+///
+/// For maps, items are key-value pairs. A value which changed is reported as
+/// the removal of the old pair and the addition of the new pair.
+pub trait Diffable {
+    /// Type of the items within the collection.
+    type Item;
+
+    /// Gets all items which were added to or removed from the collection,
+    /// compared to `previous`.
+    fn diff(&self, previous: &Self) -> Diff<Self::Item>;
+
+    /// Gets all items of the collection, as if they were all added.
+    fn all_added(&self) -> Diff<Self::Item>;
+}
+
+impl<T: Eq + Hash + Clone, S: BuildHasher> Diffable for HashSet<T, S> {
+    type Item = T;
+
+    fn diff(&self, previous: &Self) -> Diff<T> {
+        Diff {
+            added: self.iter().filter(|item| !previous.contains(*item)).cloned().collect(),
+            removed: previous.iter().filter(|item| !self.contains(*item)).cloned().collect(),
+        }
+    }
+
+    fn all_added(&self) -> Diff<T> {
+        Diff {
+            added: self.iter().cloned().collect(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> Diffable for BTreeSet<T> {
+    type Item = T;
+
+    fn diff(&self, previous: &Self) -> Diff<T> {
+        Diff {
+            added: self.difference(previous).cloned().collect(),
+            removed: previous.difference(self).cloned().collect(),
+        }
+    }
+
+    fn all_added(&self) -> Diff<T> {
+        Diff {
+            added: self.iter().cloned().collect(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: PartialEq + Clone, S: BuildHasher> Diffable for HashMap<K, V, S> {
+    type Item = (K, V);
+
+    fn diff(&self, previous: &Self) -> Diff<(K, V)> {
+        Diff {
+            added: self
+                .iter()
+                .filter(|(key, value)| previous.get(*key) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            removed: previous
+                .iter()
+                .filter(|(key, value)| self.get(*key) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    fn all_added(&self) -> Diff<(K, V)> {
+        Diff {
+            added: self.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: PartialEq + Clone> Diffable for BTreeMap<K, V> {
+    type Item = (K, V);
+
+    fn diff(&self, previous: &Self) -> Diff<(K, V)> {
+        Diff {
+            added: self
+                .iter()
+                .filter(|(key, value)| previous.get(*key) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            removed: previous
+                .iter()
+                .filter(|(key, value)| self.get(*key) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    fn all_added(&self) -> Diff<(K, V)> {
+        Diff {
+            added: self.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+/// Result which was replaced by a recomputed result, and is kept until the
+/// change is diffed. See [`Query::enable_diffs`].
+#[derive(Debug, Clone)]
+pub(crate) enum Replaced {
+    /// No result was cached before, so all items of the result were added.
+    Nothing,
+
+    /// Result which was cached before, which the result is compared against.
+    Slot(Box<Slot>),
+}
+
+impl Replaced {
+    /// Gets what the result which replaces `previous` has replaced since it
+    /// was last diffed.
+    ///
+    /// If `previous` itself replaced a result which was not diffed yet, that
+    /// result is kept instead, so the next diff covers all replacements since
+    /// the last diff.
+    pub(crate) fn of(previous: Option<Slot>) -> Self {
+        match previous {
+            Some(mut previous) => previous
+                .replaced
+                .take()
+                .unwrap_or_else(|| Self::Slot(Box::new(previous))),
+            None => Self::Nothing,
+        }
+    }
+
+    /// Computes the difference of `current` against the replaced result.
+    ///
+    /// If the replaced result is of a different type, there is nothing to
+    /// compare against, so all items are reported as added.
+    fn diff<C: QueryValue + Diffable>(replaced: Option<&Self>, current: &C) -> Diff<C::Item> {
+        match replaced {
+            None => Diff {
+                added: Vec::new(),
+                removed: Vec::new(),
+            },
+            Some(Self::Slot(slot)) if let Some(previous) = slot.downcast_ref::<C>() => current.diff(previous),
+            Some(_) => current.all_added(),
+        }
+    }
+}
+
+impl Query {
+    /// Keeps the result which is replaced whenever a result of the query is
+    /// recomputed, until the change is diffed by [`Database::take_diff`] or
+    /// [`Database::execute_query_diff`].
+    ///
+    /// Since the replaced result is kept until it is diffed, a result which
+    /// is recomputed several times in between is compared against the result
+    /// which was last diffed, and the change is never lost to a lookup which
+    /// doesn't diff. Results which are cached already, or which are inserted
+    /// after their previous result was removed, are reported as entirely
+    /// added by their next diff.
+    pub fn enable_diffs(&mut self) {
+        if self.diffs {
+            return;
+        }
+
+        self.diffs = true;
+
+        for slot in self.results.values_mut() {
+            slot.replaced = Some(Replaced::Nothing);
+        }
+    }
+}
+
+impl Database {
+    /// Enables diffs for the query with the given name. See
+    /// [`Query::enable_diffs`].
+    pub fn enable_diffs(&self, name: &str) {
+        self.query_mut(name).enable_diffs();
+    }
+
+    /// Computes the difference of the result which is cached for the given
+    /// key within the query instance with the given name, against the result
+    /// it replaced since the last diff, and marks the change as diffed.
+    ///
+    /// Enables diffs for the query, if they are not enabled yet, in which
+    /// case all items are reported as added. See [`Query::enable_diffs`].
+    ///
+    /// # Returns
+    ///
+    /// If no result of type `C` is cached, this method returns [`None`].
+    pub fn take_diff<K: Hash + 'static, C: QueryValue + Clone + Diffable>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+    ) -> Option<Diff<C::Item>> {
+        let id = name.query_id();
+        let (current, replaced) = self.take_replaced(id, self.hash_key(id, key), C::clone)?;
+
+        Some(Replaced::diff(replaced.as_ref(), &current))
+    }
+
+    /// Looks up the given key within the query instance with the given name,
+    /// like [`Database::execute_query`], and computes the difference against
+    /// the result which was last diffed, like [`Database::take_diff`].
+    ///
+    /// Results which depend on a changed input are kept until they are
+    /// recomputed, so the returned [`Diff`] holds the changes caused by the
+    /// input. This lets consumers, such as index updaters, apply the diff
+    /// instead of reprocessing the entire collection. Results which are
+    /// recomputed by other lookups, such as [`Database::execute_query`], keep
+    /// the result they replaced, so their changes are still reported by the
+    /// next diff. If no result was cached, such as after the result was
+    /// invalidated or the query was cleared, all items are reported as added.
+    pub fn execute_query_diff<K: Hash, C: QueryValue + Clone + Diffable>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> C,
+    ) -> (C, Diff<C::Item>) {
+        let id = name.query_id();
+        let key = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        // Diffs are enabled before the query is executed, so the result which
+        // is replaced by the execution is kept.
+        if self.read().get(id).is_some_and(|query| !query.diffs) {
+            self.query_mut_by_id(id).enable_diffs();
+        }
+
+        let value = self.execute_query_by_id(id, key, f);
+
+        let diff = match self.take_replaced(id, key, |_: &C| ()) {
+            Some(((), replaced)) => Replaced::diff(replaced.as_ref(), &value),
+            None => value.all_added(),
+        };
+
+        (value, diff)
+    }
+
+    /// Takes the result which the cached result with the given key replaced
+    /// since it was last diffed, if any, and maps the cached result using
+    /// `f`. Enables diffs for the query, if they are not enabled yet.
+    ///
+    /// # Returns
+    ///
+    /// If no result of type `C` is cached, this method returns [`None`].
+    fn take_replaced<C: QueryValue, R>(
+        &self,
+        id: QueryId,
+        key: ResultKey,
+        f: impl FnOnce(&C) -> R,
+    ) -> Option<(R, Option<Replaced>)> {
+        let mut inner = self.write();
+        inner.get(id)?;
+
+        let query = inner.query_mut_by_id(id);
+        query.enable_diffs();
+
+        let slot = query.results.get_mut(&key)?;
+        let mapped = f(slot.downcast_ref::<C>()?);
+
+        Some((mapped, slot.replaced.take()))
+    }
+}
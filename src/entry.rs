@@ -0,0 +1,149 @@
+use std::marker::PhantomData;
+
+use crate::{Query, QueryError, QueryValue, ResultKey, Slot};
+
+/// A view into a single result within a [`Query`], which may either be
+/// occupied or vacant.
+///
+/// Created by [`Query::entry`].
+pub enum Entry<'q, T> {
+    Occupied(OccupiedEntry<'q, T>),
+    Vacant(VacantEntry<'q, T>),
+}
+
+impl<'q, T: QueryValue + Clone> Entry<'q, T> {
+    /// Creates a new [`Entry`] for the given key within the query.
+    pub(crate) fn new(query: &'q mut Query, key: ResultKey) -> Result<Self, QueryError> {
+        let Some(slot) = query.results.get(&key) else {
+            return Ok(Entry::Vacant(VacantEntry {
+                query,
+                key,
+                _marker: PhantomData,
+            }));
+        };
+
+        if slot.downcast_ref::<T>().is_none() {
+            return Err(slot.type_mismatch::<T>(&query.name));
+        }
+
+        Ok(Entry::Occupied(OccupiedEntry {
+            query,
+            key,
+            _marker: PhantomData,
+        }))
+    }
+
+    /// Gets the key of the entry.
+    pub fn key(&self) -> ResultKey {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => entry.key,
+        }
+    }
+
+    /// Inserts the given value if the entry is vacant, and returns a reference
+    /// to the value within the entry.
+    pub fn or_insert(self, value: T) -> &'q T {
+        self.or_insert_with(|| value)
+    }
+
+    /// Inserts the result of `f` if the entry is vacant, and returns a
+    /// reference to the value within the entry.
+    pub fn or_insert_with(self, f: impl FnOnce() -> T) -> &'q T {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Invokes `f` with a mutable reference to the value, if the entry is
+    /// occupied. See [`OccupiedEntry::modify`].
+    pub fn and_modify(mut self, f: impl FnOnce(&mut T)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            entry.modify(f);
+        }
+
+        self
+    }
+}
+
+/// A view into an occupied result within a [`Query`].
+pub struct OccupiedEntry<'q, T> {
+    query: &'q mut Query,
+    key: ResultKey,
+    _marker: PhantomData<T>,
+}
+
+impl<'q, T: QueryValue + Clone> OccupiedEntry<'q, T> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> ResultKey {
+        self.key
+    }
+
+    /// Gets a reference to the value within the entry.
+    pub fn get(&self) -> &T {
+        self.slot().downcast_ref::<T>().unwrap()
+    }
+
+    /// Converts the entry into a reference to the value, which lives as long
+    /// as the query.
+    pub fn into_ref(self) -> &'q T {
+        self.query.results.get(&self.key).unwrap().downcast_ref::<T>().unwrap()
+    }
+
+    /// Invokes `f` with a mutable reference to the value, after which the
+    /// value is marked as changed within the query.
+    pub fn modify(&mut self, f: impl FnOnce(&mut T)) {
+        let slot = self.query.results.get_mut(&self.key).unwrap();
+        f(slot.downcast_mut::<T>().unwrap());
+
+        self.query.restamp(self.key);
+    }
+
+    /// Replaces the value within the entry, returning the old value.
+    pub fn insert(&mut self, mut value: T) -> T {
+        self.query.callbacks.store(&mut value);
+
+        let slot = self.query.results.get_mut(&self.key).unwrap();
+        let old = std::mem::replace(slot.downcast_mut::<T>().unwrap(), value);
+
+        slot.duration = None;
+        self.query.restamp(self.key);
+
+        old
+    }
+
+    /// Removes the entry from the query, returning its value.
+    pub fn remove(self) -> T {
+        let slot = self.query.remove(self.key).unwrap();
+
+        slot.downcast::<T>().unwrap()
+    }
+
+    fn slot(&self) -> &Slot {
+        self.query.results.get(&self.key).unwrap()
+    }
+}
+
+/// A view into a vacant result within a [`Query`].
+pub struct VacantEntry<'q, T> {
+    query: &'q mut Query,
+    key: ResultKey,
+    _marker: PhantomData<T>,
+}
+
+impl<'q, T: QueryValue + Clone> VacantEntry<'q, T> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> ResultKey {
+        self.key
+    }
+
+    /// Inserts the given value into the entry, and returns a reference to it.
+    pub fn insert(self, mut value: T) -> &'q T {
+        self.query.callbacks.store(&mut value);
+        self.query.results.insert(self.key, Slot::new(value));
+        self.query.restamp(self.key);
+
+        self.query.results.get(&self.key).unwrap().downcast_ref::<T>().unwrap()
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::Display;
+
+use crate::{Limit, ResultKey};
+
+/// Error returned when a query could not be executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// The cached result of the query is not of the type requested by the
+    /// caller, which indicates that two call sites disagree on the type of
+    /// the query.
+    TypeMismatch {
+        /// Name of the query which holds the result.
+        query: String,
+
+        /// Name of the type which was requested.
+        expected: &'static str,
+
+        /// Name of the type which is actually stored.
+        found: &'static str,
+    },
+
+    /// The query was executed without being registered first, while strict
+    /// registration is enabled.
+    Unregistered {
+        /// Name of the query which was executed.
+        query: String,
+    },
+
+    /// The query was executed with a key, while it was already being executed
+    /// with the same key on the same thread, either directly or through other
+    /// queries.
+    Cycle {
+        /// Name of the query which was executed again.
+        query: String,
+
+        /// Key of the result which was executed again.
+        key: ResultKey,
+
+        /// Label of the key, if one was captured. See
+        /// [`Database::enable_key_labels`].
+        ///
+        /// [`Database::enable_key_labels`]: crate::Database::enable_key_labels
+        label: Option<String>,
+
+        /// Names and keys of all queries which form the cycle, starting with
+        /// the first execution of the query and ending with the query which
+        /// executed it again.
+        path: Vec<(String, ResultKey)>,
+    },
+
+    /// The query exceeded one of the limits of its sandbox. See
+    /// [`Query::set_sandbox`].
+    ///
+    /// [`Query::set_sandbox`]: crate::Query::set_sandbox
+    LimitExceeded {
+        /// Name of the query which exceeded the limit.
+        query: String,
+
+        /// Key of the result which was being computed.
+        key: ResultKey,
+
+        /// Limit which was exceeded.
+        limit: Limit,
+    },
+
+    /// A middleware cancelled the computation of the query, without running
+    /// it. See [`Database::add_middleware`].
+    ///
+    /// [`Database::add_middleware`]: crate::Database::add_middleware
+    Cancelled {
+        /// Name of the query which was cancelled.
+        query: String,
+    },
+
+    /// The query has a key normalizer, but was given a key which can't be
+    /// normalized, since its type isn't `'static`. See
+    /// [`Query::set_key_normalizer`].
+    ///
+    /// [`Query::set_key_normalizer`]: crate::Query::set_key_normalizer
+    Unnormalized {
+        /// Name of the query which was given the key.
+        query: String,
+    },
+
+    /// The result of the query had to be computed within a read transaction,
+    /// which can't store results. See [`Database::read_txn`].
+    ///
+    /// [`Database::read_txn`]: crate::Database::read_txn
+    ReadOnly {
+        /// Name of the query which had to be computed.
+        query: String,
+    },
+
+    /// A query with the same name already exists, so the query could not be
+    /// added.
+    DuplicateQuery {
+        /// Name of the query which was added.
+        query: String,
+    },
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::TypeMismatch { query, expected, found } => {
+                write!(
+                    f,
+                    "result in query `{query}` is of type `{found}`, expected `{expected}`"
+                )
+            }
+            QueryError::Unregistered { query } => {
+                write!(f, "query `{query}` was executed without being registered")
+            }
+            QueryError::Cycle {
+                query,
+                key,
+                label,
+                path,
+            } => {
+                match label {
+                    Some(label) => write!(f, "cycle detected when executing query `{query}` with key `{label}`: ")?,
+                    None => write!(
+                        f,
+                        "cycle detected when executing query `{query}` with key `{}`: ",
+                        key.0
+                    )?,
+                }
+
+                for (name, _) in path {
+                    write!(f, "`{name}` -> ")?;
+                }
+
+                write!(f, "`{query}`")
+            }
+            QueryError::LimitExceeded { query, limit, .. } => {
+                write!(f, "query `{query}` exceeded its {limit}")
+            }
+            QueryError::Cancelled { query } => write!(f, "computation of query `{query}` was cancelled"),
+            QueryError::Unnormalized { query } => {
+                write!(
+                    f,
+                    "query `{query}` has a key normalizer, but was given a key which can't be normalized"
+                )
+            }
+            QueryError::ReadOnly { query } => {
+                write!(f, "query `{query}` had to be computed within a read transaction")
+            }
+            QueryError::DuplicateQuery { query } => write!(f, "duplicate query name: {query}"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Result of executing a query, which may fail with a [`QueryError`].
+pub type QueryResult<T> = Result<T, QueryError>;
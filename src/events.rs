@@ -0,0 +1,414 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::dependency::Node;
+use crate::{ChangeSet, Database, QueryId, ResultKey, Revision};
+
+/// Kind of an [`Event`] within the event log of a [`Database`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// A result was computed, since it could not be found in the cache.
+    ///
+    /// This event is recorded once the result has been computed, so it
+    /// follows the events of any results computed along the way.
+    Executed {
+        /// Time it took to compute the result.
+        duration: Duration,
+    },
+
+    /// A result was inserted using [`Database::insert`].
+    Inserted,
+
+    /// A result was removed by an invalidation, or because it depends on an
+    /// invalidated result.
+    Invalidated,
+
+    /// A result was discarded to release memory. See
+    /// [`Database::release_soft_entries`].
+    Evicted,
+
+    /// All results of a query were cleared.
+    Cleared,
+
+    /// A cycle was detected while executing a query.
+    Cycle,
+}
+
+/// Why a result was computed or invalidated, as recorded within an
+/// [`Event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// The result was computed, since it was not cached.
+    Missing,
+
+    /// The result was recomputed, since the given result changed after the
+    /// result was computed, or was no longer cached. The given result is
+    /// either a dependency of the result, or a result which one of its dirty
+    /// dependencies depends on. See [`Database::explain`].
+    Changed(Culprit),
+
+    /// The result was recomputed, since its dependencies are not tracked.
+    /// See [`Query::set_max_dependencies`].
+    ///
+    /// [`Query::set_max_dependencies`]: crate::Query::set_max_dependencies
+    Untracked,
+
+    /// The result was recomputed, since its cached result could not be
+    /// reused, even though none of its dependencies changed, such as while
+    /// caching is disabled.
+    Bypassed,
+
+    /// The result was invalidated itself, such as using
+    /// [`Database::invalidate`].
+    Invalidated,
+
+    /// The result was invalidated, since the given result was invalidated,
+    /// which it depends on, or which is mapped onto it by an invalidation
+    /// rule. See [`Database::add_invalidation_rule`].
+    Cascaded(Culprit),
+
+    /// The result was invalidated, since it depends on a result of the query
+    /// with the given name, which was cleared entirely.
+    Cleared(String),
+}
+
+/// Result which caused another result to be computed or invalidated, as
+/// recorded within a [`Reason`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Culprit {
+    /// Name of the query which holds the result.
+    pub query: String,
+
+    /// Key of the result.
+    pub key: ResultKey,
+
+    /// Label of the key, if one was captured when the event was recorded. See
+    /// [`Database::enable_key_labels`].
+    pub label: Option<String>,
+}
+
+impl std::fmt::Display for Culprit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "`{}` with key `{label}`", self.query),
+            None => write!(f, "`{}` with key `{}`", self.query, self.key.0),
+        }
+    }
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::Missing => write!(f, "it was not cached"),
+            Reason::Changed(culprit) => write!(f, "{culprit} changed"),
+            Reason::Untracked => write!(f, "its dependencies are not tracked"),
+            Reason::Bypassed => write!(f, "its cached result could not be reused"),
+            Reason::Invalidated => write!(f, "it was invalidated"),
+            Reason::Cascaded(culprit) => write!(f, "{culprit} was invalidated"),
+            Reason::Cleared(query) => write!(f, "`{query}` was cleared"),
+        }
+    }
+}
+
+/// Creates a [`Culprit`] for the given result, without a label.
+fn culprit(query: String, key: ResultKey) -> Culprit {
+    Culprit {
+        query,
+        key,
+        label: None,
+    }
+}
+
+/// [`Reason`] as recorded while the database is locked, which refers to
+/// results by their node, until the event is logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RawReason {
+    Missing,
+    Changed(Node),
+    Untracked,
+    Bypassed,
+    Invalidated,
+    Cascaded(Node),
+    Cleared(QueryId),
+}
+
+/// Single entry within the event log of a [`Database`], as returned by
+/// [`Database::event_log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Revision of the database when the event was recorded.
+    pub revision: Revision,
+
+    /// Kind of the event.
+    pub kind: EventKind,
+
+    /// Name of the query which the event applies to.
+    pub query: String,
+
+    /// Key of the result which the event applies to, unless the event applies
+    /// to the query as a whole.
+    pub key: Option<ResultKey>,
+
+    /// Label of the key, if one was captured when the event was recorded. See
+    /// [`Database::enable_key_labels`].
+    pub label: Option<String>,
+
+    /// Why the result was computed or invalidated, for events of kind
+    /// [`EventKind::Executed`] and [`EventKind::Invalidated`].
+    pub reason: Option<Reason>,
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            EventKind::Executed { .. } => "executed",
+            EventKind::Inserted => "inserted",
+            EventKind::Invalidated => "invalidated",
+            EventKind::Evicted => "evicted",
+            EventKind::Cleared => "cleared",
+            EventKind::Cycle => "cycle in",
+        };
+
+        write!(f, "[r{}] {kind} `{}`", self.revision.as_u64(), self.query)?;
+
+        match (&self.label, self.key) {
+            (Some(label), _) => write!(f, " with key `{label}`")?,
+            (None, Some(key)) => write!(f, " with key `{}`", key.0)?,
+            (None, None) => {}
+        }
+
+        if let EventKind::Executed { duration } = self.kind {
+            write!(f, " in {duration:?}")?;
+        }
+
+        if let Some(reason) = &self.reason {
+            write!(f, ", since {reason}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Log of the most recent events within a database.
+pub(crate) struct EventLog {
+    capacity: usize,
+    events: VecDeque<Event>,
+
+    /// Reasons of invalidations which were made, but not logged yet, since
+    /// they are logged once the changes are published.
+    pending: HashMap<Node, RawReason>,
+}
+
+impl EventLog {
+    /// Creates a new, empty [`EventLog`], which keeps at most `capacity`
+    /// events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Records the given event, discarding the oldest events if the log is
+    /// full.
+    pub fn record(&mut self, event: Event) {
+        while self.events.len() >= self.capacity.max(1) {
+            self.events.pop_front();
+        }
+
+        self.events.push_back(event);
+    }
+}
+
+impl Database {
+    /// Enables the event log, which records every result which is executed,
+    /// inserted, invalidated or evicted, along with every cycle, in the order
+    /// they occurred.
+    ///
+    /// Only the most recent `capacity` events are kept. The log is meant to be
+    /// dumped when something goes wrong, such as from a panic hook, so that
+    /// reports about wrong incremental results come with the history which
+    /// led up to them. See [`Database::dump_event_log`].
+    ///
+    /// If the log is already enabled, its capacity is changed, while keeping
+    /// the recorded events.
+    pub fn enable_event_log(&self, capacity: usize) {
+        let mut log = self.events.lock();
+
+        match log.as_mut() {
+            Some(log) => log.capacity = capacity,
+            None => *log = Some(EventLog::new(capacity)),
+        }
+    }
+
+    /// Disables the event log and discards all recorded events.
+    pub fn disable_event_log(&self) {
+        *self.events.lock() = None;
+    }
+
+    /// Gets all events within the event log, from oldest to newest.
+    ///
+    /// If the event log is not enabled, this method returns an empty list.
+    pub fn event_log(&self) -> Vec<Event> {
+        self.events
+            .lock()
+            .as_ref()
+            .map(|log| log.events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Writes all events within the event log to the given writer, one event
+    /// per line, from oldest to newest.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error which occurred while writing to `out`.
+    pub fn dump_event_log(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        for event in self.event_log() {
+            writeln!(out, "{event}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Installs a panic hook, which writes all events within the event log to
+    /// `out` whenever a thread panics, before invoking the previously
+    /// installed hook. See [`Database::dump_event_log`].
+    ///
+    /// The hook doesn't keep the database alive. Once the database is
+    /// dropped, the hook only invokes the previous hook. The hook stays
+    /// installed until it is replaced using [`std::panic::set_hook`].
+    ///
+    /// Reports about wrong incremental results, which often end in a failed
+    /// assertion, thereby come with the history which led up to them, such as
+    /// when `out` is [`std::io::stderr`].
+    pub fn install_panic_dump(&self, out: impl std::io::Write + Send + 'static) {
+        let events = Arc::downgrade(&self.events);
+        let out = parking_lot::Mutex::new(out);
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            // The panic may have occurred while the log was locked, in which
+            // case it is skipped, instead of deadlocking.
+            if let Some(events) = events.upgrade()
+                && let Some(log) = events.try_lock()
+                && let Some(log) = log.as_ref()
+            {
+                let mut out = out.lock();
+
+                for event in &log.events {
+                    let _ = writeln!(out, "{event}");
+                }
+            }
+
+            previous(info);
+        }));
+    }
+
+    /// Records an event of the given kind for the query with the given ID, if
+    /// the event log is enabled.
+    ///
+    /// The database must not be locked when this method is invoked.
+    pub(crate) fn log_event(&self, query: QueryId, key: Option<ResultKey>, kind: EventKind) {
+        self.log_event_with_reason(query, key, kind, None);
+    }
+
+    /// Records an event of the given kind for the query with the given ID,
+    /// along with why it occurred, if the event log is enabled.
+    ///
+    /// The database must not be locked when this method is invoked.
+    pub(crate) fn log_event_with_reason(
+        &self,
+        query: QueryId,
+        key: Option<ResultKey>,
+        kind: EventKind,
+        reason: Option<RawReason>,
+    ) {
+        if self.events.lock().is_none() {
+            return;
+        }
+
+        let (id, name, revision, reason) = {
+            let inner = self.read();
+            let id = inner.resolve(query);
+
+            let Some(found) = inner.get(id) else {
+                return;
+            };
+
+            let name_of = |query: QueryId| inner.get(query).map(|query| query.name.clone()).unwrap_or_default();
+
+            let reason = reason.map(|reason| match reason {
+                RawReason::Missing => (Reason::Missing, None),
+                RawReason::Changed(node) => (Reason::Changed(culprit(name_of(node.0), node.1)), Some(node)),
+                RawReason::Untracked => (Reason::Untracked, None),
+                RawReason::Bypassed => (Reason::Bypassed, None),
+                RawReason::Invalidated => (Reason::Invalidated, None),
+                RawReason::Cascaded(node) => (Reason::Cascaded(culprit(name_of(node.0), node.1)), Some(node)),
+                RawReason::Cleared(query) => (Reason::Cleared(name_of(query)), None),
+            });
+
+            (id, found.name.clone(), inner.current_revision(), reason)
+        };
+
+        let label = key.and_then(|key| self.key_label_by_id(id, key));
+
+        // Labels are kept apart from the database, so they're captured once
+        // the database is unlocked.
+        let reason = reason.map(|(mut reason, node)| {
+            if let (Reason::Changed(culprit) | Reason::Cascaded(culprit), Some((query, key))) = (&mut reason, node) {
+                culprit.label = self.key_label_by_id(query, key);
+            }
+
+            reason
+        });
+
+        if let Some(log) = self.events.lock().as_mut() {
+            log.record(Event {
+                revision,
+                kind,
+                query: name,
+                key,
+                label,
+                reason,
+            });
+        }
+    }
+
+    /// Keeps the reasons of the given invalidations, until their events are
+    /// logged when the changes are published, if the event log is enabled.
+    pub(crate) fn note_reasons(&self, reasons: Vec<(Node, RawReason)>) {
+        if let Some(log) = self.events.lock().as_mut() {
+            log.pending.extend(reasons);
+        }
+    }
+
+    /// Records events for all changes within the given set, if the event log
+    /// is enabled.
+    pub(crate) fn log_changes(&self, changes: &ChangeSet) {
+        if self.events.lock().is_none() {
+            return;
+        }
+
+        for (name, key) in &changes.inserted {
+            self.log_event(QueryId::from_name(name), Some(*key), EventKind::Inserted);
+        }
+
+        for (name, key) in &changes.invalidated {
+            let id = self.read().resolve(QueryId::from_name(name));
+            let reason = self
+                .events
+                .lock()
+                .as_mut()
+                .and_then(|log| log.pending.remove(&(id, *key)));
+
+            self.log_event_with_reason(id, Some(*key), EventKind::Invalidated, reason);
+        }
+
+        for name in &changes.cleared {
+            self.log_event(QueryId::from_name(name), None, EventKind::Cleared);
+        }
+    }
+}
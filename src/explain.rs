@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::dependency::{DependencyGraph, Node};
+use crate::events::RawReason;
+use crate::{Database, DatabaseInner, Event, QueryId, QueryName, ResultKey};
+
+/// What happens to a result when it is requested next, as reported by
+/// [`Database::explain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The result is not cached, so it is computed.
+    Computed,
+
+    /// The result is cached and up-to-date, so it is reused.
+    Reused,
+
+    /// The result is marked as dirty, so its dependencies are verified first.
+    /// It is reused if none of them turn out to have changed, and recomputed
+    /// otherwise.
+    Verified,
+
+    /// The dependencies of the result are not tracked, so it is recomputed on
+    /// every access. See [`Query::set_max_dependencies`].
+    ///
+    /// [`Query::set_max_dependencies`]: crate::Query::set_max_dependencies
+    Untracked,
+}
+
+/// Kind of a [`Cause`] within an [`Explanation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CauseKind {
+    /// The result changed after the result which depends on it was computed,
+    /// such as an input which was inserted using [`Database::insert`].
+    Changed,
+
+    /// The result is no longer cached, so it is computed again.
+    Missing,
+
+    /// The result is marked as dirty, so it is verified first, along with its
+    /// own causes, which follow it.
+    Verified,
+
+    /// The dependencies of the result are not tracked, so it is recomputed.
+    Untracked,
+}
+
+/// Result which causes the explained result to be verified or recomputed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cause {
+    /// Name of the query which holds the result.
+    pub query: String,
+
+    /// Key of the result.
+    pub key: ResultKey,
+
+    /// Label of the key, if one was captured. See
+    /// [`Database::enable_key_labels`].
+    pub label: Option<String>,
+
+    /// Why the result affects the explained result.
+    pub kind: CauseKind,
+
+    /// Number of dependency edges between the explained result and this
+    /// result, minus one, so direct dependencies have a depth of zero.
+    pub depth: usize,
+}
+
+/// Explanation of why a result is recomputed, as returned by
+/// [`Database::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// What happens to the result when it is requested next.
+    pub verdict: Verdict,
+
+    /// Dependencies which cause the result to be verified or recomputed, in
+    /// depth-first order. Each dependency which is verified is followed by
+    /// its own causes, so every chain of invalidations leads from a direct
+    /// dependency of the result to a result which changed or is missing.
+    pub causes: Vec<Cause>,
+
+    /// Events of the result within the event log, from oldest to newest,
+    /// which record why the result was computed or invalidated before. Empty
+    /// unless the event log is enabled. See [`Database::enable_event_log`].
+    pub history: Vec<Event>,
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = match self.verdict {
+            Verdict::Computed => "computed, since it is not cached",
+            Verdict::Reused => "reused, since it is up-to-date",
+            Verdict::Verified => "verified, since it is dirty",
+            Verdict::Untracked => "recomputed, since its dependencies are not tracked",
+        };
+
+        write!(f, "result is {verdict}")?;
+
+        for cause in &self.causes {
+            let kind = match cause.kind {
+                CauseKind::Changed => "changed",
+                CauseKind::Missing => "missing",
+                CauseKind::Verified => "verified",
+                CauseKind::Untracked => "untracked",
+            };
+
+            write!(
+                f,
+                "\n{:indent$}{kind} `{}`",
+                "",
+                cause.query,
+                indent = (cause.depth + 1) * 2
+            )?;
+
+            match &cause.label {
+                Some(label) => write!(f, " with key `{label}`")?,
+                None => write!(f, " with key `{}`", cause.key.0)?,
+            }
+        }
+
+        if !self.history.is_empty() {
+            write!(f, "\nhistory:")?;
+
+            for event in &self.history {
+                write!(f, "\n  {event}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Explains what happens to the result of the query with the given key
+    /// when it is requested next, and why, by walking the recorded
+    /// dependencies of the result without changing anything.
+    ///
+    /// Dirty results are explained by the chains of dependencies which lead
+    /// to the results which changed since they were computed, passing through
+    /// every intermediate result which is verified along the way. Whether a
+    /// verified result is recomputed with the same value as before is only
+    /// known once it is verified, so its dependents may still be reused. See
+    /// [`Database::is_dirty`].
+    ///
+    /// If the event log is enabled, the explanation also reports why the
+    /// result was computed or invalidated before, as recorded by the log.
+    pub fn explain<K: Hash + 'static>(&self, name: &(impl QueryName + ?Sized), key: &K) -> Explanation {
+        let id = name.query_id();
+        let key = self.hash_key(id, key);
+
+        let (verdict, causes, query) = {
+            let inner = self.read();
+            let graph = self.dependencies.lock();
+
+            let node = (inner.resolve(id), key);
+            let mut causes = Vec::new();
+
+            let verdict = match inserted_tick(&inner, node) {
+                None => Verdict::Computed,
+                Some(_) if graph.is_untracked(node) => Verdict::Untracked,
+                Some(_) if !graph.is_dirty(node) => Verdict::Reused,
+                Some(inserted_at) => {
+                    explain_dependencies(&graph, &inner, node, inserted_at, 0, &mut HashSet::new(), &mut causes);
+
+                    Verdict::Verified
+                }
+            };
+
+            let causes = causes
+                .into_iter()
+                .filter_map(|((query, key), kind, depth)| {
+                    Some((query, inner.get(query)?.name.clone(), key, kind, depth))
+                })
+                .collect::<Vec<_>>();
+
+            let query = inner.get(node.0).map(|query| query.name.clone());
+
+            (verdict, causes, query)
+        };
+
+        let causes = causes
+            .into_iter()
+            .map(|(id, query, key, kind, depth)| Cause {
+                query,
+                key,
+                label: self.key_label_by_id(id, key),
+                kind,
+                depth,
+            })
+            .collect();
+
+        let history = self
+            .event_log()
+            .into_iter()
+            .filter(|event| Some(&event.query) == query.as_ref() && event.key == Some(key))
+            .collect();
+
+        Explanation {
+            verdict,
+            causes,
+            history,
+        }
+    }
+}
+
+impl Database {
+    /// Determines why the result with the given key, within the query with
+    /// the given ID, is about to be computed, if the event log is enabled.
+    ///
+    /// The dependencies of the result are walked like [`Database::explain`]
+    /// does, so the first result which changed since the result was inserted,
+    /// or which is no longer cached, is reported as its cause.
+    pub(crate) fn recompute_reason(&self, query: QueryId, key: ResultKey) -> Option<RawReason> {
+        if self.events.lock().is_none() {
+            return None;
+        }
+
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+
+        let node = (inner.resolve(query), key);
+
+        let Some(inserted_at) = inserted_tick(&inner, node) else {
+            return Some(RawReason::Missing);
+        };
+
+        if graph.is_untracked(node) {
+            return Some(RawReason::Untracked);
+        }
+
+        let mut causes = Vec::new();
+        explain_dependencies(&graph, &inner, node, inserted_at, 0, &mut HashSet::new(), &mut causes);
+
+        // Causes lead through verified results to the results which changed,
+        // so the first of those is reported.
+        let changed = causes
+            .into_iter()
+            .find(|(_, kind, _)| *kind != CauseKind::Verified)
+            .map(|(dependency, _, _)| dependency);
+
+        Some(changed.map_or(RawReason::Bypassed, RawReason::Changed))
+    }
+}
+
+/// Gets the tick in which the given result was last inserted, if it is
+/// cached.
+fn inserted_tick(inner: &DatabaseInner, (query, key): Node) -> Option<u64> {
+    inner
+        .get(query)
+        .and_then(|query| query.results.get(&key))
+        .map(|slot| slot.inserted_tick)
+}
+
+/// Collects the dependencies of the given result which cause it to be
+/// verified or recomputed, mirroring how dirty results are verified.
+///
+/// Dependencies are visited in a fixed order, so the explanation is the same
+/// across runs. Results which are already being explained are part of a
+/// cycle, so they are skipped.
+fn explain_dependencies(
+    graph: &DependencyGraph,
+    inner: &DatabaseInner,
+    node: Node,
+    inserted_at: u64,
+    depth: usize,
+    visiting: &mut HashSet<Node>,
+    causes: &mut Vec<(Node, CauseKind, usize)>,
+) {
+    if !visiting.insert(node) {
+        return;
+    }
+
+    let mut dependencies = graph.dependencies(node).collect::<Vec<_>>();
+    dependencies.sort_unstable();
+
+    for dependency in dependencies {
+        let Some(slot) = inner
+            .get(dependency.0)
+            .and_then(|query| query.results.get(&dependency.1))
+        else {
+            causes.push((dependency, CauseKind::Missing, depth));
+            continue;
+        };
+
+        if graph.is_untracked(dependency) {
+            causes.push((dependency, CauseKind::Untracked, depth));
+            continue;
+        }
+
+        if graph.is_dirty(dependency) {
+            let start = causes.len();
+            causes.push((dependency, CauseKind::Verified, depth));
+
+            explain_dependencies(
+                graph,
+                inner,
+                dependency,
+                slot.inserted_tick,
+                depth + 1,
+                visiting,
+                causes,
+            );
+
+            // Dirty dependencies without any cause of their own are reused,
+            // so they are only reported if they changed themselves.
+            if causes.len() > start + 1 {
+                continue;
+            }
+
+            causes.truncate(start);
+        }
+
+        if slot.modified_tick > inserted_at {
+            causes.push((dependency, CauseKind::Changed, depth));
+        }
+    }
+
+    visiting.remove(&node);
+}
@@ -0,0 +1,87 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::{CachedRef, Database, QueryId, QueryValue};
+
+/// A typed handle to a [`Query`](crate::Query) within a [`Database`].
+///
+/// The handle refers to the query by its [`QueryId`], so the query name is
+/// only hashed once, when the handle is created using
+/// [`Database::register_query`]. The key type `K` and the result type `V`
+/// are part of the handle, so mismatching types are caught at compile-time,
+/// instead of silently missing the cache.
+///
+/// Keys given to the handle are normalized before they are hashed, if the
+/// query has a key normalizer. See [`Query::set_key_normalizer`].
+///
+/// The key type must be `'static`, since normalizers are matched against
+/// the type of the key. Keys which borrow data, such as `&str`, must be given
+/// in their owned form, such as [`String`], or with a `'static` lifetime.
+///
+/// [`Query::set_key_normalizer`]: crate::Query::set_key_normalizer
+///
+/// Since queries are never removed from a database, a handle stays valid for
+/// as long as the database it was created from.
+pub struct QueryHandle<K, V> {
+    id: QueryId,
+    _marker: PhantomData<fn(&K) -> V>,
+}
+
+impl<K: Hash + 'static, V: QueryValue + Clone> QueryHandle<K, V> {
+    /// Creates a new [`QueryHandle`] to the query with the given ID.
+    pub(crate) fn new(id: QueryId) -> Self {
+        Self {
+            id,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gets the ID of the query which this handle refers to.
+    #[inline]
+    pub fn id(&self) -> QueryId {
+        self.id
+    }
+
+    /// Looks up the given key within the query. See
+    /// [`Database::execute_query`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the handle was created from another database.
+    #[inline]
+    pub fn execute(&self, db: &Database, key: &K, f: impl FnOnce() -> V) -> V {
+        db.execute_query_by_id(self.id, db.hash_key(self.id, key), f)
+    }
+
+    /// Gets a clone of the cached result with the given key, without
+    /// computing it. See [`Database::get_cached`].
+    #[inline]
+    pub fn get_cached(&self, db: &Database, key: &K) -> Option<V> {
+        db.peek_by_id(self.id, db.hash_key(self.id, key), V::clone)
+    }
+
+    /// Gets a reference to the cached result with the given key, without
+    /// computing or cloning it. See [`Database::get_ref`].
+    #[inline]
+    pub fn get_ref<'db>(&self, db: &'db Database, key: &K) -> Option<CachedRef<'db, V>> {
+        db.get_ref_by_id(self.id, db.hash_key(self.id, key))
+    }
+}
+
+impl<K, V> Clone for QueryHandle<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for QueryHandle<K, V> {}
+
+impl<K, V> std::fmt::Debug for QueryHandle<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryHandle")
+            .field("id", &self.id)
+            .field("key", &std::any::type_name::<K>())
+            .field("value", &std::any::type_name::<V>())
+            .finish()
+    }
+}
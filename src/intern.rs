@@ -0,0 +1,172 @@
+use std::any::{Any, TypeId};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use fxhash::FxHashMap;
+
+use crate::{Database, QueryName, QueryValue};
+
+/// Small, copyable ID of a key of type [`K`], which was interned within a
+/// [`Database`] using [`Database::intern`].
+///
+/// Hashing an ID is much cheaper than hashing the key itself, so large keys,
+/// such as whole signatures, can be interned once and passed around by ID.
+/// IDs are only meaningful within the database which interned them.
+pub struct Interned<K> {
+    index: u32,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<K> Interned<K> {
+    /// Gets the ID as a plain integer.
+    #[inline]
+    pub fn as_u32(self) -> u32 {
+        self.index
+    }
+}
+
+impl<K> Clone for Interned<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Interned<K> {}
+
+impl<K> PartialEq for Interned<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<K> Eq for Interned<K> {}
+
+impl<K> Hash for Interned<K> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<K> std::fmt::Debug for Interned<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Interned").field(&self.index).finish()
+    }
+}
+
+/// Interned keys of a single type, in both directions.
+///
+/// Keys are hashed using the same hasher as result keys, since interning is
+/// meant to avoid hashing large keys more than once.
+pub(crate) struct Interner<K> {
+    ids: FxHashMap<K, u32>,
+    keys: Vec<K>,
+}
+
+impl<K: Hash + Eq + Clone> Interner<K> {
+    /// Gets the ID of the given key, interning it if it wasn't yet.
+    fn intern(&mut self, key: K) -> u32 {
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+
+        let id = u32::try_from(self.keys.len()).expect("too many interned keys");
+
+        self.keys.push(key.clone());
+        self.ids.insert(key, id);
+
+        id
+    }
+}
+
+impl Database {
+    /// Interns the given key, returning a small ID which can be used as the key
+    /// of queries instead, and resolved back using
+    /// [`Database::lookup_interned`].
+    ///
+    /// Interning the same key again returns the same ID. Interned keys are
+    /// kept for the lifetime of the database, even when all results are
+    /// cleared, so IDs remain valid.
+    pub fn intern<K: QueryValue + Hash + Eq + Clone>(&self, key: K) -> Interned<K> {
+        let type_id = TypeId::of::<K>();
+
+        let existing = self
+            .interners
+            .read()
+            .get(&type_id)
+            .and_then(|interner| (&**interner as &dyn Any).downcast_ref::<Interner<K>>())
+            .and_then(|interner| interner.ids.get(&key).copied());
+
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                let mut interners = self.interners.write();
+
+                let interner = interners.entry(type_id).or_insert_with(|| {
+                    Box::new(Interner::<K> {
+                        ids: FxHashMap::default(),
+                        keys: Vec::new(),
+                    })
+                });
+
+                (&mut **interner as &mut dyn Any)
+                    .downcast_mut::<Interner<K>>()
+                    .expect("interner of mismatched type")
+                    .intern(key)
+            }
+        };
+
+        Interned {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Gets a clone of the key which was interned as the given ID.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the ID was not interned within this database.
+    pub fn lookup_interned<K: QueryValue + Clone>(&self, id: Interned<K>) -> K {
+        self.interners
+            .read()
+            .get(&TypeId::of::<K>())
+            .and_then(|interner| (&**interner as &dyn Any).downcast_ref::<Interner<K>>())
+            .and_then(|interner| interner.keys.get(id.index as usize))
+            .cloned()
+            .expect("ID was not interned within this database")
+    }
+
+    /// Interns the given key and executes the query with the given name,
+    /// using the interned ID as the key of the result. See
+    /// [`Database::intern`].
+    ///
+    /// The ID is given to `f`, so the query can pass it on to other queries,
+    /// which only hash the ID, instead of the whole key. The key itself can be
+    /// resolved using [`Database::lookup_interned`].
+    ///
+    /// Keys which were already interned can be given by their ID, using
+    /// [`Database::query_interned`].
+    pub fn intern_and_query<K: QueryValue + Hash + Eq + Clone, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: K,
+        f: impl FnOnce(Interned<K>) -> T,
+    ) -> T {
+        self.query_interned(name, self.intern(key), f)
+    }
+
+    /// Executes the query with the given name, using the given interned ID as
+    /// the key of the result. See [`Database::intern_and_query`].
+    ///
+    /// Unlike [`Database::intern_and_query`], the key isn't interned again, so
+    /// only the ID is hashed, such as when a query which was given an ID
+    /// passes it on to another query.
+    pub fn query_interned<K, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        id: Interned<K>,
+        f: impl FnOnce(Interned<K>) -> T,
+    ) -> T {
+        self.execute_query(name, &id, || f(id))
+    }
+}
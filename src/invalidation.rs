@@ -0,0 +1,392 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::dependency::{DependencyGraph, Node};
+use crate::events::RawReason;
+use crate::{ChangeSet, Database, DatabaseInner, KeyPrefix, QueryId, QueryName, QueryValue, ResultKey};
+
+/// Function which maps a key onto another key, such as the key of an
+/// invalidated result onto the key of a result within another query, as
+/// registered using [`Database::add_invalidation_rule`], or a key onto its
+/// canonical form, as registered using [`Database::set_key_normalizer`].
+///
+/// When the `sync` feature is enabled, functions must also be [`Send`] and
+/// [`Sync`].
+#[cfg(not(feature = "sync"))]
+pub trait KeyMap<K, R>: Fn(&K) -> R + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<K, R, F: Fn(&K) -> R + 'static> KeyMap<K, R> for F {}
+
+/// Function which maps a key onto another key, such as the key of an
+/// invalidated result onto the key of a result within another query, as
+/// registered using [`Database::add_invalidation_rule`], or a key onto its
+/// canonical form, as registered using [`Database::set_key_normalizer`].
+///
+/// When the `sync` feature is enabled, functions must also be [`Send`] and
+/// [`Sync`].
+#[cfg(feature = "sync")]
+pub trait KeyMap<K, R>: Fn(&K) -> R + Send + Sync + 'static {}
+
+#[cfg(feature = "sync")]
+impl<K, R, F: Fn(&K) -> R + Send + Sync + 'static> KeyMap<K, R> for F {}
+
+/// Type-erased [`KeyMap`], which returns [`None`] if the given key is not of
+/// the type expected by the rule.
+#[cfg(not(feature = "sync"))]
+type ErasedKeyMap = Box<dyn Fn(&dyn Any) -> Option<(ResultKey, Box<dyn Any>)>>;
+
+/// Type-erased [`KeyMap`], which returns [`None`] if the given key is not of
+/// the type expected by the rule.
+#[cfg(feature = "sync")]
+type ErasedKeyMap = Box<dyn Fn(&dyn Any) -> Option<(ResultKey, Box<dyn Any>)> + Send + Sync>;
+
+/// Rule which invalidates results of the `target` query, whenever results of
+/// the `source` query are invalidated.
+pub(crate) struct InvalidationRule {
+    source: QueryId,
+    target: QueryId,
+    map: ErasedKeyMap,
+}
+
+impl Database {
+    /// Adds a rule, which invalidates the result of the `target` query with
+    /// the key returned by `map`, whenever the result of the `source` query
+    /// with key `K` is invalidated using [`Database::invalidate`].
+    ///
+    /// Whenever the `source` query is cleared entirely, the `target` query is
+    /// cleared entirely as well. Rules are applied transitively.
+    ///
+    /// This covers relationships between queries which are not visible to
+    /// the database, such as results derived outside of the query system.
+    /// The key returned by `map` must be the key which the result of the
+    /// `target` query was executed with.
+    pub fn add_invalidation_rule<K: 'static, R: Hash + 'static>(
+        &self,
+        source: &str,
+        target: &str,
+        map: impl KeyMap<K, R>,
+    ) {
+        self.invalidation_rules.write().push(InvalidationRule {
+            source: QueryId::from_name(source),
+            target: QueryId::from_name(target),
+            map: Box::new(move |key: &dyn Any| {
+                let mapped = map(key.downcast_ref::<K>()?);
+
+                Some((ResultKey::from_hashable(&mapped), Box::new(mapped) as Box<dyn Any>))
+            }),
+        });
+    }
+
+    /// Removes all invalidation rules from the database.
+    pub fn clear_invalidation_rules(&self) {
+        self.invalidation_rules.write().clear();
+    }
+
+    /// Invalidates the result with the given key within the query with the
+    /// given name, so it is recomputed when it is requested again, along with
+    /// all results affected by invalidation rules or declared dependencies.
+    ///
+    /// Returns whether a result was cached for the key.
+    pub fn invalidate<K: Hash + 'static>(&self, name: &str, key: &K) -> bool {
+        let id = QueryId::from_name(name);
+
+        self.invalidate_key(id, self.hash_key(id, key), Some(key))
+    }
+
+    /// Invalidates the result with the given hashed key within the query with
+    /// the given ID, as described by [`Database::invalidate`].
+    ///
+    /// If `original` is given, which is the key before it was hashed,
+    /// invalidation rules of the query are applied to it as well.
+    pub(crate) fn invalidate_key(&self, id: QueryId, key: ResultKey, original: Option<&dyn Any>) -> bool {
+        let (removed, changes) = {
+            let rules = self.invalidation_rules.read();
+            let mut inner = self.write();
+            let graph = self.dependencies.lock();
+
+            let mut invalidation = Invalidation {
+                inner: &mut inner,
+                rules: &rules,
+                graph: &graph,
+                visited: HashSet::new(),
+                changes: ChangeSet::default(),
+                reasons: Vec::new(),
+            };
+
+            let removed = invalidation.invalidate(id, key, original, RawReason::Invalidated);
+
+            (removed, invalidation.finish(self))
+        };
+
+        self.publish(changes);
+
+        removed
+    }
+
+    /// Invalidates all results within the query with the given name, whose
+    /// original key is of type [`K`] and starts with the given prefix, along
+    /// with all results affected by invalidation rules or declared
+    /// dependencies. See [`Database::invalidate`].
+    ///
+    /// Only results whose key was retained on insertion, such as through
+    /// [`Database::execute_query_keyed`], can be matched against the prefix.
+    /// This maps naturally onto invalidating everything derived from a single
+    /// file, when results are keyed by `(file_id, ...)`, using
+    /// `db.clear_prefix::<(FileId, String)>("typecheck", &(file_id,))`.
+    ///
+    /// Returns the number of results within the query which were cleared.
+    pub fn clear_prefix<K: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        prefix: &impl KeyPrefix<K>,
+    ) -> usize {
+        let (removed, changes) = {
+            let rules = self.invalidation_rules.read();
+            let mut inner = self.write();
+            let graph = self.dependencies.lock();
+
+            let id = name.query_id();
+            let matches = inner
+                .get(id)
+                .map(|query| query.prefixed_results(prefix))
+                .unwrap_or_default();
+
+            let mut invalidation = Invalidation {
+                inner: &mut inner,
+                rules: &rules,
+                graph: &graph,
+                visited: HashSet::new(),
+                changes: ChangeSet::default(),
+                reasons: Vec::new(),
+            };
+
+            let removed = matches
+                .iter()
+                .filter(|(key, original)| invalidation.invalidate(id, *key, Some(original), RawReason::Invalidated))
+                .count();
+
+            (removed, invalidation.finish(self))
+        };
+
+        self.publish(changes);
+
+        removed
+    }
+
+    /// Invalidates all unpinned results within the query with the given ID,
+    /// along with all results which depend on them, and shrinks the query to
+    /// reclaim their memory.
+    ///
+    /// Results are not invalidated because their key changed, so invalidation
+    /// rules are not applied.
+    ///
+    /// Returns the number of results within the query which were removed.
+    pub(crate) fn invalidate_unpinned(&self, id: QueryId) -> usize {
+        let (removed, changes) = {
+            let rules = self.invalidation_rules.read();
+            let mut inner = self.write();
+            let graph = self.dependencies.lock();
+
+            let id = inner.resolve(id);
+            let unpinned = inner
+                .get(id)
+                .map(|query| {
+                    query
+                        .results
+                        .keys()
+                        .filter(|key| !query.is_pinned_by_key(**key))
+                        .copied()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            let mut invalidation = Invalidation {
+                inner: &mut inner,
+                rules: &rules,
+                graph: &graph,
+                visited: HashSet::new(),
+                changes: ChangeSet::default(),
+                reasons: Vec::new(),
+            };
+
+            let removed = unpinned
+                .into_iter()
+                .filter(|key| invalidation.invalidate(id, *key, None, RawReason::Invalidated))
+                .count();
+
+            let changes = invalidation.finish(self);
+
+            if let Some(query) = inner.queries.get_mut(&id) {
+                query.results.shrink_to_fit();
+            }
+
+            (removed, changes)
+        };
+
+        self.publish(changes);
+
+        removed
+    }
+
+    /// Clears all results from the query with the given ID, along with all
+    /// queries affected by invalidation rules and all results which depend on
+    /// any of the cleared results.
+    pub(crate) fn clear_cascading(&self, id: QueryId) {
+        let changes = self.clear_cascading_locked(id);
+
+        self.publish(changes);
+    }
+
+    /// Clears the query with the given ID, as described by
+    /// [`Database::clear_cascading`], and returns the changes which were made.
+    fn clear_cascading_locked(&self, id: QueryId) -> ChangeSet {
+        let rules = self.invalidation_rules.read();
+        let mut inner = self.write();
+        let graph = self.dependencies.lock();
+
+        let id = inner.resolve(id);
+        inner.clear_by_id(id);
+
+        let mut pending = vec![id];
+        let mut cleared = HashSet::from([id]);
+
+        while let Some(id) = pending.pop() {
+            let targets = rules
+                .iter()
+                .filter(|rule| inner.resolve(rule.source) == id)
+                .map(|rule| inner.resolve(rule.target))
+                .collect::<Vec<_>>();
+
+            for target in targets {
+                if !cleared.insert(target) {
+                    continue;
+                }
+
+                // Targets of rules may not have been registered yet, in which
+                // case they don't hold any results.
+                if inner.get(target).is_some() {
+                    inner.clear_by_id(target);
+                }
+
+                pending.push(target);
+            }
+        }
+
+        let mut changes = ChangeSet {
+            cleared: cleared
+                .iter()
+                .filter_map(|&id| inner.get(id))
+                .map(|query| query.name.clone())
+                .collect(),
+            ..ChangeSet::default()
+        };
+
+        let mut invalidation = Invalidation {
+            inner: &mut inner,
+            rules: &rules,
+            graph: &graph,
+            visited: HashSet::new(),
+            changes: ChangeSet::default(),
+            reasons: Vec::new(),
+        };
+
+        for query in cleared {
+            for (dependent, key) in graph.dependents_of_query(query) {
+                invalidation.invalidate(dependent, key, None, RawReason::Cleared(query));
+            }
+        }
+
+        changes.invalidated = invalidation.finish(self).invalidated;
+        changes
+    }
+}
+
+/// State of a single invalidation, which may cascade through any number of
+/// results.
+struct Invalidation<'a> {
+    inner: &'a mut DatabaseInner,
+    rules: &'a [InvalidationRule],
+    graph: &'a DependencyGraph,
+
+    /// Results which have already been invalidated.
+    visited: HashSet<Node>,
+
+    /// Results which were removed by the invalidation.
+    changes: ChangeSet,
+
+    /// Why each of the removed results was removed.
+    reasons: Vec<(Node, RawReason)>,
+}
+
+impl Invalidation<'_> {
+    /// Removes the result with the given key from the query with the given
+    /// ID, along with all results which depend on it.
+    ///
+    /// If `original` is given, which is the key before it was hashed, all
+    /// invalidation rules of the query are applied to it as well. Results
+    /// which are reached through declared dependencies don't have an original
+    /// key, so no rules are applied to them.
+    ///
+    /// The given `reason` is recorded for the result, if it was removed.
+    fn invalidate(&mut self, id: QueryId, key: ResultKey, original: Option<&dyn Any>, reason: RawReason) -> bool {
+        let id = self.inner.resolve(id);
+
+        if !self.visited.insert((id, key)) {
+            return false;
+        }
+
+        let removed = if self.inner.get(id).is_some() {
+            let query = self.inner.query_mut_by_id(id);
+            query.errors.remove(&key);
+
+            let removed = query.remove(key).is_some();
+
+            if removed {
+                self.changes.invalidated.push((query.name.clone(), key));
+                self.reasons.push(((id, key), reason));
+            }
+
+            removed
+        } else {
+            false
+        };
+
+        if let Some(original) = original {
+            for rule in self.rules {
+                if self.inner.resolve(rule.source) != id {
+                    continue;
+                }
+
+                if let Some((target_key, mapped)) = (rule.map)(original) {
+                    // Keys are mapped before the target query is known, so
+                    // they're normalized by the target query here.
+                    let target_key = self
+                        .inner
+                        .get(rule.target)
+                        .and_then(|target| target.normalizer.as_ref()?.hash(&*mapped))
+                        .unwrap_or(target_key);
+
+                    self.invalidate(rule.target, target_key, Some(&*mapped), RawReason::Cascaded((id, key)));
+                }
+            }
+        }
+
+        let graph = self.graph;
+
+        for (dependent, dependent_key) in graph.dependents((id, key)) {
+            self.invalidate(dependent, dependent_key, None, RawReason::Cascaded((id, key)));
+        }
+
+        removed
+    }
+
+    /// Finishes the invalidation, keeping the reasons of the removed results
+    /// until they're logged, and returns the changes which were made.
+    fn finish(self, db: &Database) -> ChangeSet {
+        db.note_reasons(self.reasons);
+
+        self.changes
+    }
+}
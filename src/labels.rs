@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{Database, QueryId, QueryName, ResultKey};
+
+/// Human-readable representations of result keys, per query and key.
+pub(crate) type KeyLabels = HashMap<(QueryId, ResultKey), String>;
+
+impl Database {
+    /// Enables capturing human-readable labels of result keys, as given to
+    /// [`Database::label_key`].
+    ///
+    /// Results are indexed by the hash of their key, so without labels,
+    /// diagnostics such as [`QueryError::Cycle`] can only show bare hashes.
+    /// Labels are retained until they are disabled, so they are meant for
+    /// debugging, rather than for production use.
+    ///
+    /// [`QueryError::Cycle`]: crate::QueryError::Cycle
+    pub fn enable_key_labels(&self) {
+        let mut labels = self.key_labels.lock();
+
+        if labels.is_none() {
+            *labels = Some(KeyLabels::default());
+        }
+    }
+
+    /// Disables capturing labels of result keys, and discards all captured
+    /// labels. See [`Database::enable_key_labels`].
+    pub fn disable_key_labels(&self) {
+        *self.key_labels.lock() = None;
+    }
+
+    /// Captures a human-readable label of the given key, within the query with
+    /// the given name, such as its [`Debug`] representation.
+    ///
+    /// The label is only computed when key labels are enabled and the key
+    /// doesn't have a label yet, so this is cheap to call on every execution.
+    /// See [`Database::enable_key_labels`].
+    pub fn label_key<K: Hash>(&self, name: &(impl QueryName + ?Sized), key: &K, label: impl FnOnce() -> String) {
+        if self.key_labels.lock().is_none() {
+            return;
+        }
+
+        // Keys which can't be normalized are skipped, since the query can't be
+        // executed with them either.
+        let id = name.query_id();
+        let Ok(key) = self.hash_unnormalized(id, key) else {
+            return;
+        };
+
+        self.label_by_id(id, key, label);
+    }
+
+    /// Captures a human-readable label of the given, already hashed, key
+    /// within the query with the given name. See [`Database::label_key`] and
+    /// [`Database::execute_query_by_key`].
+    pub fn label_key_by_key(&self, name: &(impl QueryName + ?Sized), key: ResultKey, label: impl FnOnce() -> String) {
+        if self.key_labels.lock().is_none() {
+            return;
+        }
+
+        let id = name.query_id();
+
+        if self.check_unnormalized(id).is_ok() {
+            self.label_by_id(id, key, label);
+        }
+    }
+
+    /// Captures the label of the given key within the query with the given ID,
+    /// unless the key already has a label.
+    fn label_by_id(&self, id: QueryId, key: ResultKey, label: impl FnOnce() -> String) {
+        let id = self.read().resolve(id);
+
+        if let Some(labels) = self.key_labels.lock().as_mut() {
+            labels.entry((id, key)).or_insert_with(label);
+        }
+    }
+
+    /// Gets the label which was captured for the given, already hashed, key
+    /// within the query with the given name, if any.
+    pub fn key_label(&self, name: &str, key: ResultKey) -> Option<String> {
+        let id = self.read().resolve(QueryId::from_name(name));
+
+        self.key_label_by_id(id, key)
+    }
+
+    /// Gets the label which was captured for the given key within the query
+    /// with the given, resolved, ID, if any.
+    pub(crate) fn key_label_by_id(&self, id: QueryId, key: ResultKey) -> Option<String> {
+        self.key_labels.lock().as_ref()?.get(&(id, key)).cloned()
+    }
+}
@@ -1,11 +1,90 @@
-use std::any::Any;
-use std::collections::HashMap;
+mod adaptive;
+mod cached_ref;
+mod cached_view;
+mod callback;
+mod chunked;
+mod dependency;
+mod diagnostics;
+mod diff;
+mod entry;
+mod error;
+mod events;
+mod explain;
+mod handle;
+mod intern;
+mod invalidation;
+mod labels;
+mod map_reduce;
+mod middleware;
+mod names;
+mod normalize;
+mod observer;
+mod pin;
+mod prefix;
+mod sandbox;
+mod shard;
+mod sites;
+mod stats;
+mod stream;
+mod sync;
+#[cfg(feature = "testing")]
+mod testing;
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 use bitflags::bitflags;
+use indexmap::IndexMap;
 #[cfg(feature = "derive")]
-pub use lume_architect_derive::cached_query;
-use parking_lot::RwLock;
+pub use lume_architect_derive::{cached_queries, cached_query, query_module};
+#[cfg(all(feature = "rayon", feature = "sync"))]
+use rayon::prelude::*;
+
+use crate::adaptive::Adaptive;
+pub use crate::cached_ref::CachedRef;
+pub use crate::cached_view::CachedView;
+use crate::callback::{Callbacks, ErasedRecompute, ErasedStoreHook, SharedKeyCallback};
+pub use crate::callback::{KeyCallback, MemoryMonitor, Recompute, StoreHook};
+use crate::dependency::DependencyGraph;
+pub use crate::dependency::{DependencyShape, Impact, Priority, Pump, Revalidation};
+pub use crate::diagnostics::{Nondeterminism, ReentrantCall, SlowQuery, Stampede};
+use crate::diagnostics::{ReentrancyAudit, SlowQueryLog, StampedeDetector};
+use crate::diff::Replaced;
+pub use crate::diff::{Diff, Diffable};
+pub use crate::entry::{Entry, OccupiedEntry, VacantEntry};
+pub use crate::error::{QueryError, QueryResult};
+pub use crate::events::{Culprit, Event, EventKind, Reason};
+use crate::events::{EventLog, RawReason};
+pub use crate::explain::{Cause, CauseKind, Explanation, Verdict};
+pub use crate::handle::QueryHandle;
+pub use crate::intern::Interned;
+use crate::invalidation::InvalidationRule;
+pub use crate::invalidation::KeyMap;
+use crate::labels::KeyLabels;
+pub use crate::middleware::{Computed, Middleware, Next, QueryCall};
+pub use crate::names::{PrehashedName, QueryNameCache};
+use crate::normalize::KeyNormalizer;
+use crate::observer::Batch;
+pub use crate::observer::{ChangeObserver, ChangeSet};
+pub use crate::prefix::KeyPrefix;
+use crate::sandbox::{Deadline, SandboxState};
+pub use crate::sandbox::{Limit, Sandbox};
+pub use crate::sites::DuplicateDefinition;
+#[doc(hidden)]
+pub use crate::sites::{DefinitionSite, QueryDefinition};
+use crate::stats::QueryCounters;
+pub use crate::stats::{DatabaseStats, DurationStats, QueryStats};
+pub use crate::stream::{QueryStream, StreamSource};
+use crate::sync::{AtomicBool, AtomicU64, AtomicUsize, Mutex, RwLock, thread_local};
+#[cfg(all(feature = "testing", feature = "sync"))]
+pub use crate::testing::Scheduler;
+#[cfg(feature = "testing")]
+pub use crate::testing::{CoherenceError, Operation};
 
 /// Represents a unique index, referencing a [`Query`] within a [`Database`].
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -13,16 +92,106 @@ pub struct QueryId(usize);
 
 impl QueryId {
     /// Creates a new [`QueryId`] from the given string.
-    pub fn from_name(str: &str) -> Self {
-        let hash = fxhash::hash(str);
+    ///
+    /// Since this is a `const fn`, the IDs of queries with static names can be
+    /// computed at compile-time and stored in constants, instead of hashing
+    /// the name on every call.
+    pub const fn from_name(str: &str) -> Self {
+        Self::from_parts(&[str])
+    }
+
+    /// Creates a new [`QueryId`] from the concatenation of the given strings,
+    /// without allocating the concatenated string.
+    ///
+    /// The returned ID is identical to the ID of the concatenated name.
+    pub const fn from_parts(parts: &[&str]) -> Self {
+        // Every byte of every part is mixed into the hash in turn, using the
+        // rotate, xor and multiply step of FxHash. Unlike `fxhash::hash`,
+        // which mixes in whole words and a terminator after each string, the
+        // parts are treated as a single stream of bytes, so the ID only
+        // depends on the concatenated name, and not on how it was split up.
+        const SEED: usize = 0x51_7c_c1_b7_27_22_0a_95_u64 as usize;
+
+        let mut hash: usize = 0;
+        let mut i = 0;
+
+        while i < parts.len() {
+            let bytes = parts[i].as_bytes();
+            let mut j = 0;
+
+            while j < bytes.len() {
+                hash = (hash.rotate_left(5) ^ bytes[j] as usize).wrapping_mul(SEED);
+                j += 1;
+            }
+
+            i += 1;
+        }
 
         Self(hash)
     }
 }
 
+/// Name of a [`Query`], which can be hashed into a [`QueryId`] without
+/// building the full name.
+///
+/// Besides plain strings, this is implemented for arrays of name parts, such
+/// as `[type_name, "::", method]`, whose full name is only allocated when the
+/// query is first added to a [`Database`].
+pub trait QueryName {
+    /// Gets the [`QueryId`] of the query with this name.
+    fn query_id(&self) -> QueryId;
+
+    /// Gets the full name of the query.
+    fn to_query_name(&self) -> String;
+}
+
+impl QueryName for str {
+    #[inline]
+    fn query_id(&self) -> QueryId {
+        QueryId::from_name(self)
+    }
+
+    fn to_query_name(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl QueryName for String {
+    #[inline]
+    fn query_id(&self) -> QueryId {
+        QueryId::from_name(self)
+    }
+
+    fn to_query_name(&self) -> String {
+        self.clone()
+    }
+}
+
+impl QueryName for [&str] {
+    #[inline]
+    fn query_id(&self) -> QueryId {
+        QueryId::from_parts(self)
+    }
+
+    fn to_query_name(&self) -> String {
+        self.concat()
+    }
+}
+
+impl<const N: usize> QueryName for [&str; N] {
+    #[inline]
+    fn query_id(&self) -> QueryId {
+        QueryId::from_parts(self)
+    }
+
+    fn to_query_name(&self) -> String {
+        self.concat()
+    }
+}
+
 /// Represents a unique index, referencing a result within a [`Query`].
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ResultKey(usize);
+pub struct ResultKey(pub(crate) usize);
 
 impl ResultKey {
     /// Creates a new [`ResultKey`] from a value, implementing [`Hash`].
@@ -33,20 +202,442 @@ impl ResultKey {
     }
 }
 
+/// Represents a revision of a [`Database`], which is bumped every time the
+/// content of the database is mutated.
+#[derive(Debug, Default, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Revision(u64);
+
+impl Revision {
+    /// Gets the revision which follows the current one.
+    #[inline]
+    pub fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+
+    /// Gets the revision as a plain integer.
+    #[inline]
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct QueryFlags: u32 {
         /// Always re-compute the result of the query, even if a matching entry
         /// already exists within the result set.
+        ///
+        /// Within the dependency graph, results of the query act as leaves
+        /// which are always dirty: their own dependencies are not recorded,
+        /// since they are recomputed regardless, while results which depend
+        /// on them are recorded as usual, and are recomputed on every access
+        /// as well, along with all results which transitively depend on them.
         const ALWAYS = 1;
+
+        /// Results of the query may be discarded by the database when the
+        /// process is under memory pressure, and are transparently recomputed
+        /// when requested again. See [`Database::set_memory_monitor`].
+        const SOFT = 2;
+
+        /// Re-entering the query with a key which is currently being computed
+        /// on the same thread returns the result which is stored for the key
+        /// so far, instead of failing with [`QueryError::Cycle`].
+        ///
+        /// Combined with [`QueryFlags::ALWAYS`], this allows for hand-written
+        /// fixpoint loops, where the query is executed until its result no
+        /// longer changes.
+        ///
+        /// Keys are not seeded automatically: if no result is stored for the
+        /// key yet, re-entering the query still fails with
+        /// [`QueryError::Cycle`], so that [`Database::execute_query`] panics.
+        /// Seed every key which may be re-entered with a provisional result
+        /// first, such as using [`Database::insert`].
+        const RECURSIVE = 4;
+
+        /// The database measures how long results of the query take to
+        /// compute, and stops caching them once they are consistently
+        /// computed faster than a cache lookup would take, discarding the
+        /// results which were cached so far. Caching resumes as soon as a
+        /// result takes longer again. See [`Query::set_adaptive_threshold`].
+        ///
+        /// Results which depend on uncached results are recomputed whenever
+        /// they are marked as dirty, since there is no result to revalidate
+        /// them against.
+        const ADAPTIVE = 8;
+
+        /// Results of the query are never discarded by any eviction policy,
+        /// as if every key of the query was pinned, such as the interned core
+        /// types which everything else depends on. See [`Query::pin`].
+        ///
+        /// Like pinned results, the results can still be invalidated or
+        /// cleared explicitly.
+        const PINNED = 16;
+    }
+}
+
+/// Policy which determines whether errors returned from a query should be
+/// cached, instead of re-executing the query on every lookup.
+///
+/// Used by [`Database::execute_query_result_cached`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorPolicy {
+    /// Number of times the query is re-executed after it has failed for a
+    /// given key. Once the query has failed more times than this, the last
+    /// error is returned from the cache instead.
+    ///
+    /// A value of `0` caches the first error until it expires.
+    pub max_retries: u32,
+
+    /// Duration after which a cached error expires, causing the query to be
+    /// re-executed on the next lookup. If [`None`], cached errors never
+    /// expire.
+    pub retry_after: Option<Duration>,
+}
+
+/// Marker trait for values which can be stored as results within a [`Query`].
+///
+/// By default, any `'static` type can be stored. When the `sync` feature is
+/// enabled, values must also be [`Send`] and [`Sync`], which in turn makes
+/// [`Database`] safe to share between threads.
+#[cfg(not(feature = "sync"))]
+pub trait QueryValue: Any {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: Any> QueryValue for T {}
+
+/// Marker trait for values which can be stored as results within a [`Query`].
+///
+/// By default, any `'static` type can be stored. When the `sync` feature is
+/// enabled, values must also be [`Send`] and [`Sync`], which in turn makes
+/// [`Database`] safe to share between threads.
+#[cfg(feature = "sync")]
+pub trait QueryValue: Any + Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: Any + Send + Sync> QueryValue for T {}
+
+/// Function which clones a stored value into a new box.
+type CloneFn = fn(&dyn Any) -> Box<dyn QueryValue>;
+
+/// Clones the given value, which must be of type `T`.
+fn clone_of<T: QueryValue + Clone>(value: &dyn Any) -> Box<dyn QueryValue> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+/// Clock which orders the accesses of results, as recorded by
+/// [`Slot::touch`]. The clock only orders accesses, instead of synchronizing
+/// anything, so it is shared by all databases.
+static ACCESS_CLOCK: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// A single result stored within a [`Query`].
+struct Slot {
+    value: Box<dyn QueryValue>,
+
+    /// Function used to clone `value`, such as when cloning a database.
+    clone: CloneFn,
+
+    /// Name of the concrete type stored in `value`, used for diagnostics.
+    type_name: &'static str,
+
+    /// Generation of the result, which is bumped every time the result is
+    /// recomputed.
+    generation: u64,
+
+    /// Revision of the database in which the result was last inserted.
+    changed_at: Revision,
+
+    /// Revision of the database in which the value of the result last
+    /// changed. Unlike `changed_at`, this is not bumped when a result is
+    /// recomputed with the same checksum as before.
+    modified_at: Revision,
+
+    /// Tick of the mutable access which last inserted the result. Unlike
+    /// `changed_at`, this orders results which were inserted within the same
+    /// revision, such as within a [`Database::batch`].
+    inserted_tick: u64,
+
+    /// Tick of the mutable access in which the value of the result last
+    /// changed, which is kept when the result is backdated, like
+    /// `modified_at`.
+    modified_tick: u64,
+
+    /// Time on the [`ACCESS_CLOCK`] at which the result was last inserted or
+    /// reused. It is updated while the database is only read-locked, so it's
+    /// kept in an atomic.
+    accessed_at: std::sync::atomic::AtomicU64,
+
+    /// Time it took to compute the result, if it was computed by executing
+    /// the query.
+    duration: Option<Duration>,
+
+    /// Hash of the value at the time it was inserted, if checksums are
+    /// enabled for the query.
+    checksum: Option<u64>,
+
+    /// Original key of the result, if it was retained on insertion, along
+    /// with the function used to clone it.
+    key: Option<(Box<dyn QueryValue>, CloneFn)>,
+
+    /// Result which this result replaced, if diffs are enabled for the query
+    /// and the replacement was not diffed yet. See [`Query::enable_diffs`].
+    replaced: Option<Replaced>,
+}
+
+impl Slot {
+    /// Creates a new [`Slot`] from the given value.
+    fn new<T: QueryValue + Clone>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            clone: clone_of::<T>,
+            type_name: std::any::type_name::<T>(),
+            generation: 0,
+            changed_at: Revision::default(),
+            modified_at: Revision::default(),
+            inserted_tick: 0,
+            modified_tick: 0,
+            accessed_at: std::sync::atomic::AtomicU64::new(next_access()),
+            duration: None,
+            checksum: None,
+            key: None,
+            replaced: None,
+        }
+    }
+
+    /// Records that the result was accessed just now.
+    #[inline]
+    fn touch(&self) {
+        self.accessed_at.store(next_access(), Ordering::Relaxed);
+    }
+
+    /// Gets the time on the [`ACCESS_CLOCK`] at which the result was last
+    /// accessed.
+    fn accessed_at(&self) -> u64 {
+        self.accessed_at.load(Ordering::Relaxed)
+    }
+
+    /// Gets the stored value as a reference to [`Any`].
+    #[inline]
+    fn value(&self) -> &dyn Any {
+        &*self.value
+    }
+
+    /// Attempts to downcast the stored value into a reference of type `T`.
+    #[inline]
+    fn downcast_ref<T: QueryValue>(&self) -> Option<&T> {
+        self.value().downcast_ref::<T>()
+    }
+
+    /// Attempts to downcast the stored value into a mutable reference of type
+    /// `T`.
+    #[inline]
+    fn downcast_mut<T: QueryValue>(&mut self) -> Option<&mut T> {
+        let value: &mut dyn Any = &mut *self.value;
+
+        value.downcast_mut::<T>()
     }
+
+    /// Attempts to downcast the stored value into a value of type `T`.
+    fn downcast<T: QueryValue>(self) -> Option<T> {
+        let value: Box<dyn Any> = self.value;
+
+        value.downcast::<T>().ok().map(|value| *value)
+    }
+
+    /// Creates a [`QueryError::TypeMismatch`] error for the slot, if it were
+    /// expected to hold a value of type `T`.
+    fn type_mismatch<T>(&self, query: &str) -> QueryError {
+        QueryError::TypeMismatch {
+            query: query.to_string(),
+            expected: std::any::type_name::<T>(),
+            found: self.type_name,
+        }
+    }
+}
+
+/// Advances the [`ACCESS_CLOCK`], and returns its new time.
+#[inline]
+fn next_access() -> u64 {
+    ACCESS_CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+impl Clone for Slot {
+    fn clone(&self) -> Self {
+        Self {
+            value: (self.clone)(self.value()),
+            clone: self.clone,
+            type_name: self.type_name,
+            generation: self.generation,
+            changed_at: self.changed_at,
+            modified_at: self.modified_at,
+            inserted_tick: self.inserted_tick,
+            modified_tick: self.modified_tick,
+            accessed_at: std::sync::atomic::AtomicU64::new(self.accessed_at()),
+            duration: self.duration,
+            checksum: self.checksum,
+            key: self.key.as_ref().map(|(key, clone)| (clone(&**key), *clone)),
+            replaced: self.replaced.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Slot")
+            .field("type_name", &self.type_name)
+            .field("generation", &self.generation)
+            .field("changed_at", &self.changed_at)
+            .field("modified_at", &self.modified_at)
+            .field("duration", &self.duration)
+            .field("checksum", &self.checksum)
+            .field("retains_key", &self.key.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Describes how the result of a query execution was obtained, as returned by
+/// [`Database::execute_query_with_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The result was found in the cache.
+    Hit,
+
+    /// No result was found in the cache, so it was computed.
+    Miss,
+
+    /// A result was found in the cache, but it was computed again, since
+    /// caching is disabled or the query has [`QueryFlags::ALWAYS`].
+    Recomputed,
+
+    /// The query was re-entered with a key which is currently being computed,
+    /// so the result stored for the key so far was returned. See
+    /// [`QueryFlags::RECURSIVE`].
+    Provisional,
+}
+
+/// Describes how a query is added when another query with the same name
+/// already exists, as used by [`Database::add_query`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Adding the query fails with [`QueryError::DuplicateQuery`].
+    #[default]
+    Error,
+
+    /// The existing query is kept if it has the same flags as the new query.
+    /// Otherwise, adding the query fails with [`QueryError::DuplicateQuery`].
+    IgnoreIfFlagsMatch,
+
+    /// The existing query is replaced by the new query, discarding all of its
+    /// results, along with all results which depend on them. Settings of the
+    /// existing query, such as its callbacks and key normalizer, are reset as
+    /// well.
+    Replace,
+}
+
+/// Describes where a result stored within a [`Query`] originated from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryProvenance {
+    /// Name of the query which holds the result.
+    pub query: String,
+
+    /// Generation of the result, which identifies the execution which
+    /// produced it. See [`Query::generation`].
+    pub generation: u64,
+
+    /// Revision of the database in which the result was inserted.
+    pub revision: Revision,
+
+    /// Time it took to compute the result. If the result was inserted
+    /// directly, instead of being computed by executing the query, this is
+    /// [`None`].
+    pub duration: Option<Duration>,
+}
+
+/// An error stored within a [`Query`], along with how many times the query has
+/// failed for the same key.
+#[derive(Debug, Clone)]
+struct FailedSlot {
+    error: Slot,
+    attempts: u32,
+
+    /// Point in time at which the query most recently failed.
+    failed_at: Instant,
+}
+
+/// Function which computes the checksum of a stored value.
+type ChecksumFn = fn(&dyn Any) -> Option<u64>;
+
+/// Computes the checksum of the given value, if it is of type `T`.
+fn checksum_of<T: Hash + 'static>(value: &dyn Any) -> Option<u64> {
+    value.downcast_ref::<T>().map(fxhash::hash64)
+}
+
+/// Function which determines whether two stored values are equal.
+type EqualityFn = fn(&dyn Any, &dyn Any) -> bool;
+
+/// Determines whether the given values are equal, if both are of type `T`.
+fn equal_of<T: PartialEq + 'static>(a: &dyn Any, b: &dyn Any) -> bool {
+    matches!((a.downcast_ref::<T>(), b.downcast_ref::<T>()), (Some(a), Some(b)) if a == b)
 }
 
 #[derive(Debug)]
 pub struct Query {
     name: String,
     flags: QueryFlags,
-    results: HashMap<ResultKey, Box<dyn Any>>,
+
+    /// Results of the query, in the order they were first inserted. Removing
+    /// a result moves the last result into its position, so the order stays
+    /// deterministic, without shifting all following results.
+    results: IndexMap<ResultKey, Slot>,
+    errors: HashMap<ResultKey, FailedSlot>,
+
+    /// Last generation which was assigned to a result within the query.
+    generation: u64,
+
+    /// Revision which is stamped onto results inserted through the current
+    /// mutable access of the query. See [`DatabaseInner::query_mut_by_id`].
+    revision: Revision,
+
+    /// Tick which is stamped onto results inserted through the current
+    /// mutable access of the query. See [`DatabaseInner::ticks`].
+    tick: u64,
+
+    /// Revision in which results were last inserted into or removed from the
+    /// query.
+    changed_at: Revision,
+
+    /// Function used to compute checksums of inserted results, if enabled.
+    checksum: Option<ChecksumFn>,
+
+    /// Function used to compare inserted results to the results they replace,
+    /// if enabled.
+    equality: Option<EqualityFn>,
+
+    /// Function used to normalize keys before they are hashed, if any.
+    normalizer: Option<KeyNormalizer>,
+
+    /// Maximum number of dependencies which are tracked for a single result,
+    /// if limited. See [`Query::set_max_dependencies`].
+    max_dependencies: Option<usize>,
+
+    /// Keys of results which are never evicted. See [`Query::pin`].
+    pinned: HashSet<ResultKey>,
+
+    /// Limits which are enforced on every execution of the query, if any. See
+    /// [`Query::set_sandbox`].
+    sandbox: Option<Sandbox>,
+
+    /// Whether results are cached, for queries with [`QueryFlags::ADAPTIVE`].
+    adaptive: Adaptive,
+
+    /// Whether replaced results are kept until they are diffed. See
+    /// [`Query::enable_diffs`].
+    diffs: bool,
+
+    /// Callbacks which are invoked on cache hits and misses.
+    callbacks: Callbacks,
+
+    /// Counters of lookups and evictions. See [`Database::stats`].
+    counters: QueryCounters,
 }
 
 impl Query {
@@ -55,7 +646,39 @@ impl Query {
         Self {
             name,
             flags,
-            results: HashMap::new(),
+            results: IndexMap::new(),
+            errors: HashMap::new(),
+            generation: 0,
+            revision: Revision::default(),
+            tick: 0,
+            changed_at: Revision::default(),
+            checksum: None,
+            equality: None,
+            normalizer: None,
+            max_dependencies: None,
+            pinned: HashSet::new(),
+            sandbox: None,
+            adaptive: Adaptive::default(),
+            diffs: false,
+            callbacks: Callbacks::default(),
+            counters: QueryCounters::default(),
+        }
+    }
+
+    /// Creates a new, empty [`Query`] with the same name, flags and settings
+    /// as this query, including its callbacks and normalizer, but without any
+    /// results or statistics.
+    pub(crate) fn clone_config(&self) -> Self {
+        Self {
+            checksum: self.checksum,
+            equality: self.equality,
+            normalizer: self.normalizer.clone(),
+            max_dependencies: self.max_dependencies,
+            sandbox: self.sandbox,
+            adaptive: self.adaptive.reset(),
+            diffs: self.diffs,
+            callbacks: self.callbacks.clone(),
+            ..Self::new(self.name.clone(), self.flags)
         }
     }
 
@@ -71,268 +694,2456 @@ impl Query {
         self.flags
     }
 
-    /// Gets the result with the given value as the result key.
-    ///
-    /// The value used for the key must be the same as the key used when
-    /// inserting the value.
+    /// Enables checksums for all results of type [`T`] which are inserted into
+    /// the query from now on.
     ///
-    /// # Returns
+    /// When enabled, a hash of each result is stored when it is inserted. In
+    /// debug builds, the hash is re-computed whenever the result is accessed,
+    /// to catch consumers which mutate cached values through interior
+    /// mutability.
     ///
-    /// If no value could be found, or the value found is not of type [`T`],
-    /// this method returns [`None`].
-    pub fn get<K: Hash, T: Clone + 'static>(&self, key: &K) -> Option<&T> {
-        let key = ResultKey::from_hashable(key);
-
-        self.results.get(&key)?.downcast_ref::<T>()
+    /// The hash also serves as a fingerprint of the result: when a result is
+    /// recomputed with the same hash as before, results which depend on it
+    /// are not recomputed, even if they were marked as dirty. See
+    /// [`Database::is_dirty`].
+    pub fn enable_checksums<T: QueryValue + Hash>(&mut self) {
+        self.checksum = Some(checksum_of::<T>);
     }
 
-    /// Inserts the given result into the query, indexed by the given key.
+    /// Enables equality checks for all results of type [`T`] which are
+    /// inserted into the query from now on.
     ///
-    /// If the query already contains a result for the key [`key`], the old
-    /// result is overwritten.
-    pub fn insert<K: Hash, T: Clone + 'static>(&mut self, key: &K, value: T) {
-        let key = ResultKey::from_hashable(key);
-        let value = Box::new(value);
-
-        self.results.insert(key, value);
+    /// When enabled, each inserted result is compared to the result it
+    /// replaces. If both are equal, the result is treated as unchanged, like
+    /// a result with the same checksum: results which depend on it are not
+    /// recomputed, and the insertion is not reported to observers, so they
+    /// aren't notified of changes which don't change anything. See
+    /// [`Query::enable_checksums`] and [`Database::add_observer`].
+    pub fn enable_equality_checks<T: QueryValue + PartialEq>(&mut self) {
+        self.equality = Some(equal_of::<T>);
     }
 
-    /// Determines whether the query contains a result for the given key.
+    /// Limits the number of dependencies which are tracked for a single result
+    /// of the query.
     ///
-    /// The value used for the key must be the same as the key used when
-    /// inserting the value.
-    pub fn contains<K: Hash>(&self, key: &K) -> bool {
-        let key = ResultKey::from_hashable(key);
+    /// Tracking the dependencies of results with an enormous fan-in can cost
+    /// more memory than the results themselves. Once a result depends on more
+    /// than `limit` other results, its dependencies are discarded, and the
+    /// result is recomputed every time it is requested, until it is computed
+    /// with fewer dependencies.
+    ///
+    /// Since changes can no longer be propagated past an overflowed result,
+    /// every result which transitively depends on it effectively behaves as
+    /// if its query had [`QueryFlags::ALWAYS`]: it is recomputed on every
+    /// access, until the overflowed result is recomputed with fewer
+    /// dependencies. A low limit on a widely used query can therefore disable
+    /// caching for large parts of the database.
+    pub fn set_max_dependencies(&mut self, limit: usize) {
+        self.max_dependencies = Some(limit);
+    }
 
-        self.results.contains_key(&key)
+    /// Removes the limit on the number of dependencies which are tracked for a
+    /// single result of the query. See [`Query::set_max_dependencies`].
+    pub fn clear_max_dependencies(&mut self) {
+        self.max_dependencies = None;
     }
 
-    /// Looks up the given key within the query instance.
-    ///
-    /// If a value is found within the query, it is returned as a reference. If
-    /// the key could not be found within the instance, returns [`None`].
-    /// stored, the original result is returned.
-    fn value_of<K: Hash, T: Clone + 'static>(&self, key: &K) -> Option<&T> {
-        let key = ResultKey::from_hashable(key);
-        let value = self.results.get(&key)?;
+    /// Sets the callback which is invoked with the key of every result which
+    /// is found in the cache when the query is executed, replacing any
+    /// existing callback.
+    pub fn on_hit(&mut self, callback: impl KeyCallback + 'static) {
+        self.callbacks.on_hit = Some(SharedKeyCallback::from(Box::new(callback) as Box<_>));
+    }
 
-        Some(
-            value
-                .downcast_ref::<T>()
-                .unwrap_or_else(|| panic!("could not convert result `{}.!{}` to type of T", self.name, key.0)),
-        )
+    /// Sets the callback which is invoked with the key of every result which
+    /// has to be computed when the query is executed, replacing any existing
+    /// callback.
+    pub fn on_miss(&mut self, callback: impl KeyCallback + 'static) {
+        self.callbacks.on_miss = Some(SharedKeyCallback::from(Box::new(callback) as Box<_>));
     }
 
-    /// Looks up the given key within the query instance.
+    /// Sets the transform which is applied to every result of type [`T`]
+    /// before it is stored within the query, replacing any existing
+    /// transform.
     ///
-    /// If a value is found within the query, it is returned as a reference. If
-    /// the key could not be found within the instance, `f` is invoked and the
-    /// result is cloned and inserted into the instance. After the result is
-    /// stored, the original result is returned.
-    pub fn get_or_insert<K: Hash, T: Clone + 'static>(&mut self, key: &K, f: impl FnOnce() -> T) -> &T {
-        if self.flags.contains(QueryFlags::ALWAYS) || !self.contains(key) {
-            self.insert(key, f());
-        }
+    /// This can be used to bring results into a canonical form, such as
+    /// sorting a [`Vec`] for determinism or stripping spans, so that the
+    /// cached form is what every consumer sees, including the caller which
+    /// computed the result. Results of any other type are stored as-is.
+    pub fn on_store<T: QueryValue>(&mut self, transform: impl StoreHook<T>) {
+        let transform = move |value: &mut dyn Any| {
+            if let Some(value) = value.downcast_mut::<T>() {
+                transform(value);
+            }
+        };
 
-        self.value_of(key).unwrap()
+        self.callbacks.on_store = Some(ErasedStoreHook::from(Box::new(transform) as Box<_>));
     }
 
-    /// Looks up the given key within the query instance.
-    ///
-    /// If a value is found within the query, it is returned as a reference. If
-    /// the key could not be found within the instance, `f` is invoked and the
-    /// result is cloned and inserted into the instance. After the result is
-    /// stored, the original result is returned.
+    /// Sets the function which recomputes results of the query from their
+    /// original key, replacing any existing function.
     ///
-    /// # Errors
+    /// When a dirty result is requested, its dirty dependencies are verified
+    /// first. Dependencies which turn out to be outdated are recomputed using
+    /// this function, so if they are recomputed with an unchanged checksum,
+    /// or an equal value, the requested result is reused without being
+    /// recomputed itself. See [`Database::is_dirty`].
     ///
-    /// If the given closure returns `Err`, this method will propagate the error
-    /// to the caller.
-    pub fn get_or_insert_result<K: Hash, T: Clone + 'static, E>(
-        &mut self,
-        key: &K,
-        f: impl FnOnce() -> Result<T, E>,
-    ) -> Result<&T, E> {
-        if self.flags.contains(QueryFlags::ALWAYS) || !self.contains(key) {
-            self.insert(key, f()?);
-        }
+    /// Only results whose original key was retained as type [`K`] can be
+    /// recomputed, such as results of [`Database::execute_query_keyed`].
+    /// Results which can't be recomputed are treated as changed, so every
+    /// result which depends on them is recomputed.
+    pub fn set_recompute<K: QueryValue + Clone, T: QueryValue + Clone>(&mut self, f: impl Recompute<K, T>) {
+        let recompute = move |db: &Database, id, hashed, key: &dyn Any| {
+            let Some(key) = key.downcast_ref::<K>() else {
+                return false;
+            };
 
-        Ok(self.value_of(key).unwrap())
-    }
-}
+            let Ok((value, duration)) = db.compute(id, hashed, || f(db, key)) else {
+                return false;
+            };
 
-/// Inner, non-locked version of [`Database`].
-#[derive(Default)]
-pub(crate) struct DatabaseInner {
-    pub(crate) queries: HashMap<QueryId, Query>,
-}
+            let mut query = db.query_mut_by_id(id);
+            query.store(hashed, value, Some(duration));
+            query.retain_key(hashed, key);
 
-impl DatabaseInner {
-    /// Clears all results from the query with the given name.
-    #[inline]
-    pub fn clear(&mut self, query: &str) {
-        self.query_mut(query).results.clear();
-    }
+            true
+        };
 
-    /// Clears all results from all queries in the database.
-    #[inline]
-    pub fn clear_all(&mut self) {
-        self.queries.clear();
+        self.callbacks.recompute = Some(ErasedRecompute::from(Box::new(recompute) as Box<_>));
     }
 
-    /// Retrieves a shared read access to the [`Query`] which matches the given
-    /// query name.
+    /// Gets the slot with the given key, verifying its checksum in debug
+    /// builds.
     ///
     /// # Panics
     ///
-    /// This method panics if another thread write-locked the query before
-    /// this method was invoked, without releasing the lock.
-    pub fn query(&self, name: &str) -> &Query {
-        let id = QueryId::from_name(name);
+    /// In debug builds, this method panics if the stored value no longer
+    /// matches the checksum computed when it was inserted.
+    fn lookup(&self, key: ResultKey) -> Option<&Slot> {
+        let slot = self.results.get(&key)?;
+
+        if cfg!(debug_assertions)
+            && let (Some(checksum), Some(expected)) = (self.checksum, slot.checksum)
+        {
+            assert!(
+                checksum(slot.value()) == Some(expected),
+                "result `{}.!{}` was mutated after being cached",
+                self.name,
+                key.0
+            );
+        }
 
-        self.queries.get(&id).unwrap()
+        Some(slot)
     }
 
-    /// Retrieves an exclusive-write access to the [`Query`] which matches the
-    /// given query name.
+    /// Gets the result with the given value as the result key.
     ///
-    /// # Panics
+    /// The value used for the key must be the same as the key used when
+    /// inserting the value.
     ///
-    /// This method panics if another thread write-locked the query before
-    /// this method was invoked, without releasing the lock.
-    pub fn query_mut(&mut self, name: &str) -> &mut Query {
-        let id = QueryId::from_name(name);
+    /// # Returns
+    ///
+    /// If no value could be found, or the value found is not of type [`T`],
+    /// this method returns [`None`].
+    ///
+    /// Since results are matched by their concrete type, trait objects should
+    /// be stored and retrieved as `Arc<dyn Trait>` or `Rc<dyn Trait>`.
+    pub fn get<K: Hash + 'static, T: QueryValue + Clone>(&self, key: &K) -> Option<&T> {
+        self.get_by_key(self.hash_key(key))
+    }
 
-        self.queries.get_mut(&id).unwrap()
+    /// Gets the result with the given, already hashed, key. See
+    /// [`Query::get`].
+    pub(crate) fn get_by_key<T: QueryValue + Clone>(&self, key: ResultKey) -> Option<&T> {
+        self.lookup(key)?.downcast_ref::<T>()
     }
 
-    /// Adds a new [`Query`] to the database, with the given name and flags.
+    /// Gets the result with the given value as the result key.
     ///
-    /// # Panics
+    /// Unlike [`Query::get`], this method distinguishes between a missing
+    /// result and a result of a different type than [`T`].
     ///
-    /// This method will panic if a query with the given name already exists.
-    #[inline]
-    pub fn add_query(&mut self, name: &str, flags: QueryFlags) {
-        let key = QueryId::from_name(name);
-        let existing = self.queries.insert(key, Query::new(name.to_string(), flags));
+    /// # Returns
+    ///
+    /// If no value could be found, this method returns [`Ok(None)`]. If a value
+    /// was found, but it is not of type [`T`], returns
+    /// [`QueryError::TypeMismatch`] with the name of the stored type.
+    pub fn try_get<K: Hash + 'static, T: QueryValue + Clone>(&self, key: &K) -> QueryResult<Option<&T>> {
+        self.value_of(self.hash_key(key))
+    }
 
-        assert!(existing.is_none(), "duplicate query name: {name}");
+    /// Gets the number of results within the query.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.results.len()
     }
 
-    /// Determines whether a query with the given name exists within the
-    /// database.
+    /// Determines whether the query contains no results.
     #[inline]
-    pub fn query_exists(&self, name: &str) -> bool {
-        let key = QueryId::from_name(name);
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
 
-        self.queries.contains_key(&key)
+    /// Gets an iterator over all results within the query, along with their
+    /// keys.
+    ///
+    /// Results are yielded in the order they were first inserted, so the order
+    /// is the same across runs. Removing a result moves the most recently
+    /// inserted result into its position.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.results.iter(),
+        }
     }
-}
 
-pub struct Database {
-    enabled: RwLock<bool>,
-    inner: RwLock<DatabaseInner>,
+    /// Gets an iterator over all results within the query, which are of type
+    /// [`T`]. Results of any other type are skipped.
+    pub fn values<T: QueryValue + Clone>(&self) -> impl Iterator<Item = &T> {
+        self.results.values().filter_map(Slot::downcast_ref::<T>)
+    }
+
+    /// Gets a parallel iterator over all results within the query, which are of
+    /// type [`T`]. Results of any other type are skipped.
+    ///
+    /// Since results must be shared between threads, this requires the `sync`
+    /// feature as well.
+    #[cfg(all(feature = "rayon", feature = "sync"))]
+    pub fn par_values<T: QueryValue + Clone>(&self) -> impl ParallelIterator<Item = &T> {
+        self.results.par_values().filter_map(Slot::downcast_ref::<T>)
+    }
+
+    /// Gets the entry for the given key within the query, for in-place
+    /// manipulation.
+    ///
+    /// The key is only hashed once, regardless of how many operations are
+    /// performed on the entry.
+    ///
+    /// # Errors
+    ///
+    /// If the query already contains a result for the key, which is not of
+    /// type [`T`], returns [`QueryError::TypeMismatch`] with the name of the
+    /// stored type.
+    pub fn entry<K: Hash + 'static, T: QueryValue + Clone>(&mut self, key: &K) -> QueryResult<Entry<'_, T>> {
+        Entry::new(self, self.hash_key(key))
+    }
+
+    /// Inserts the given result into the query, indexed by the given key.
+    ///
+    /// If the query already contains a result for the key [`key`], the old
+    /// result is overwritten.
+    pub fn insert<K: Hash + 'static, T: QueryValue + Clone>(&mut self, key: &K, value: T) {
+        self.insert_slot(self.hash_key(key), value, None);
+    }
+
+    /// Inserts the given result into the query, indexed by the given key,
+    /// along with the time it took to compute the result.
+    ///
+    /// If the query already contains a result for the key [`key`], the old
+    /// result is overwritten.
+    pub fn insert_computed<K: Hash + 'static, T: QueryValue + Clone>(&mut self, key: &K, value: T, duration: Duration) {
+        self.insert_slot(self.hash_key(key), value, Some(duration));
+    }
+
+    /// Inserts the given result into the query, indexed by the given, already
+    /// hashed, key.
+    ///
+    /// Returns whether the result was changed by the insertion, which is only
+    /// `false` if it replaced a result with the same checksum, or an equal
+    /// result. See [`Query::enable_equality_checks`].
+    pub(crate) fn insert_slot<T: QueryValue + Clone>(
+        &mut self,
+        key: ResultKey,
+        mut value: T,
+        duration: Option<Duration>,
+    ) -> bool {
+        self.callbacks.store(&mut value);
+        self.insert_stored(key, value, duration)
+    }
+
+    /// Inserts the given result into the query, indexed by the given, already
+    /// hashed, key, and returns a clone of the stored result.
+    ///
+    /// Unlike inserting the result directly, the returned clone reflects any
+    /// transform applied by [`Query::on_store`].
+    pub(crate) fn store<T: QueryValue + Clone>(
+        &mut self,
+        key: ResultKey,
+        mut value: T,
+        duration: Option<Duration>,
+    ) -> T {
+        self.callbacks.store(&mut value);
+
+        // Results of adaptive queries which are cheap to compute are not
+        // cached, and any outdated result for the key is discarded. See
+        // `Database::record_adaptive`.
+        if duration.is_none() || self.caches_results() {
+            self.insert_stored(key, value.clone(), duration);
+        } else {
+            self.remove(key);
+        }
+
+        value
+    }
+
+    /// Inserts the given result into the query as-is, without applying the
+    /// transform of [`Query::on_store`].
+    ///
+    /// Returns whether the result was changed by the insertion.
+    fn insert_stored<T: QueryValue + Clone>(&mut self, key: ResultKey, value: T, duration: Option<Duration>) -> bool {
+        let mut slot = Slot::new(value);
+        slot.duration = duration;
+
+        let previous = self.results.insert(key, slot);
+        self.restamp(key);
+
+        let equality = self.equality;
+        let slot = self.results.get_mut(&key).unwrap();
+
+        // If the fingerprint of the result is unchanged, or the result is equal
+        // to the previous one, the result is backdated, so dependent results
+        // don't need to be recomputed.
+        let unchanged = previous.as_ref().is_some_and(|previous| {
+            (previous.checksum.is_some() && previous.checksum == slot.checksum)
+                || equality.is_some_and(|equal| equal(previous.value(), slot.value()))
+        });
+
+        if unchanged && let Some(previous) = &previous {
+            slot.modified_at = previous.modified_at;
+            slot.modified_tick = previous.modified_tick;
+        }
+
+        if self.diffs {
+            slot.replaced = Some(Replaced::of(previous));
+        }
+
+        !unchanged
+    }
+
+    /// Marks the result with the given key as changed, by assigning it a new
+    /// generation, the current revision and a fresh checksum.
+    fn restamp(&mut self, key: ResultKey) {
+        let Some(slot) = self.results.get_mut(&key) else {
+            return;
+        };
+
+        self.generation += 1;
+        self.changed_at = self.revision;
+
+        slot.generation = self.generation;
+        slot.changed_at = self.revision;
+        slot.modified_at = self.revision;
+        slot.inserted_tick = self.tick;
+        slot.modified_tick = self.tick;
+        slot.checksum = self.checksum.and_then(|checksum| checksum(slot.value()));
+    }
+
+    /// Inserts the given result into the query, indexed by the given key, and
+    /// retains a clone of the original key.
+    ///
+    /// Retained keys can be retrieved using [`Query::keys_typed`], unlike the
+    /// opaque [`ResultKey`] which results are indexed by. If the query already
+    /// contains a result for the key [`key`], the old result is overwritten.
+    pub fn insert_keyed<K: Hash + QueryValue + Clone, T: QueryValue + Clone>(&mut self, key: &K, value: T) {
+        let hashed = self.hash_key(key);
+
+        self.insert_slot(hashed, value, None);
+        self.retain_key(hashed, key);
+    }
+
+    /// Retains a clone of the original key on the result with the given key,
+    /// if one exists.
+    fn retain_key<K: QueryValue + Clone>(&mut self, hashed: ResultKey, key: &K) {
+        if let Some(slot) = self.results.get_mut(&hashed) {
+            slot.key = Some((Box::new(key.clone()), clone_of::<K>));
+        }
+    }
+
+    /// Gets an iterator over the original keys of all results within the
+    /// query, which were retained on insertion and are of type [`K`].
+    ///
+    /// Results which were inserted without retaining their key, or whose key
+    /// is of any other type, are skipped.
+    pub fn keys_typed<K: QueryValue>(&self) -> impl Iterator<Item = &K> {
+        self.results.values().filter_map(|slot| {
+            let key: &dyn Any = &*slot.key.as_ref()?.0;
+
+            key.downcast_ref::<K>()
+        })
+    }
+
+    /// Gets the provenance of the result with the given value as the result
+    /// key.
+    ///
+    /// # Returns
+    ///
+    /// If no value could be found, this method returns [`None`].
+    pub fn provenance<K: Hash + 'static>(&self, key: &K) -> Option<EntryProvenance> {
+        let key = self.hash_key(key);
+        let slot = self.results.get(&key)?;
+
+        Some(EntryProvenance {
+            query: self.name.clone(),
+            generation: slot.generation,
+            revision: slot.changed_at,
+            duration: slot.duration,
+        })
+    }
+
+    /// Gets the generation of the result with the given value as the result
+    /// key.
+    ///
+    /// The generation is bumped every time a result is inserted for the key,
+    /// so it can be used to cheaply detect whether a result has been
+    /// recomputed since it was last observed. Generations are drawn from a
+    /// counter shared by the entire query, so they keep increasing even if
+    /// the query is cleared in between.
+    ///
+    /// # Returns
+    ///
+    /// If no value could be found, this method returns [`None`].
+    pub fn generation<K: Hash + 'static>(&self, key: &K) -> Option<u64> {
+        let key = self.hash_key(key);
+
+        self.results.get(&key).map(|slot| slot.generation)
+    }
+
+    /// Determines whether the query contains a result for the given key.
+    ///
+    /// The value used for the key must be the same as the key used when
+    /// inserting the value.
+    pub fn contains<K: Hash + 'static>(&self, key: &K) -> bool {
+        let key = self.hash_key(key);
+
+        self.results.contains_key(&key)
+    }
+
+    /// Gets the keys of all results which were inserted after the given
+    /// revision.
+    pub fn changed_since(&self, revision: Revision) -> Vec<ResultKey> {
+        self.results
+            .iter()
+            .filter(|(_, slot)| slot.changed_at > revision)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// Shrinks the capacity of the maps holding the results and errors of the
+    /// query as much as possible.
+    ///
+    /// Returns an estimate of the number of bytes which were reclaimed.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let before = self.allocated_bytes();
+
+        self.results.shrink_to_fit();
+        self.errors.shrink_to_fit();
+
+        before - self.allocated_bytes()
+    }
+
+    /// Gets an estimate of the number of bytes allocated by the maps holding
+    /// the results and errors of the query, excluding the values themselves.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.results.capacity() * size_of::<(ResultKey, Slot)>()
+            + self.errors.capacity() * size_of::<(ResultKey, FailedSlot)>()
+    }
+
+    /// Clears all results and cached errors from the query.
+    pub fn clear(&mut self) {
+        if !self.results.is_empty() {
+            self.changed_at = self.revision;
+        }
+
+        self.results.clear();
+        self.errors.clear();
+    }
+
+    /// Removes the result with the given, already hashed, key from the query.
+    ///
+    /// Returns the removed result, if there was one.
+    pub(crate) fn remove(&mut self, key: ResultKey) -> Option<Slot> {
+        let slot = self.results.swap_remove(&key)?;
+        self.changed_at = self.revision;
+
+        Some(slot)
+    }
+
+    /// Gets the cached error with the given value as the result key, if the
+    /// query has failed more times than allowed by `policy`.
+    ///
+    /// # Returns
+    ///
+    /// If no error is cached, the query is still allowed to be retried, the
+    /// cached error has expired or the error is not of type [`E`], this method
+    /// returns [`None`].
+    pub fn get_error<K: Hash + 'static, E: QueryValue + Clone>(&self, key: &K, policy: ErrorPolicy) -> Option<&E> {
+        self.get_error_by_key(self.hash_key(key), policy)
+    }
+
+    /// Gets the cached error with the given, already hashed, key. See
+    /// [`Query::get_error`].
+    pub(crate) fn get_error_by_key<E: QueryValue + Clone>(&self, key: ResultKey, policy: ErrorPolicy) -> Option<&E> {
+        let failed = self.errors.get(&key)?;
+
+        if failed.attempts <= policy.max_retries {
+            return None;
+        }
+
+        if policy
+            .retry_after
+            .is_some_and(|after| failed.failed_at.elapsed() >= after)
+        {
+            return None;
+        }
+
+        failed.error.downcast_ref::<E>()
+    }
+
+    /// Inserts the given error into the query, indexed by the given key, and
+    /// increments the number of failed attempts for the key.
+    pub fn insert_error<K: Hash + 'static, E: QueryValue + Clone>(&mut self, key: &K, error: E) {
+        self.insert_error_by_key(self.hash_key(key), error);
+    }
+
+    /// Inserts the given error into the query, indexed by the given, already
+    /// hashed, key. See [`Query::insert_error`].
+    pub(crate) fn insert_error_by_key<E: QueryValue + Clone>(&mut self, key: ResultKey, error: E) {
+        let attempts = self.errors.get(&key).map_or(0, |failed| failed.attempts);
+
+        self.errors.insert(key, FailedSlot {
+            error: Slot::new(error),
+            attempts: attempts + 1,
+            failed_at: Instant::now(),
+        });
+    }
+
+    /// Removes any cached error for the given key.
+    pub fn remove_error<K: Hash + 'static>(&mut self, key: &K) {
+        self.errors.remove(&self.hash_key(key));
+    }
+
+    /// Looks up the given key within the query instance.
+    ///
+    /// If a value is found within the query, it is returned as a reference. If
+    /// the key could not be found within the instance, returns [`None`].
+    ///
+    /// # Errors
+    ///
+    /// If a value is found, but it is not of type [`T`], returns
+    /// [`QueryError::TypeMismatch`] with the name of the stored type.
+    fn value_of<T: QueryValue + Clone>(&self, key: ResultKey) -> QueryResult<Option<&T>> {
+        let Some(slot) = self.lookup(key) else {
+            return Ok(None);
+        };
+
+        match slot.downcast_ref::<T>() {
+            Some(value) => Ok(Some(value)),
+            None => Err(slot.type_mismatch::<T>(&self.name)),
+        }
+    }
+
+    /// Looks up the given key within the query instance.
+    ///
+    /// If a value is found within the query, it is returned as a reference. If
+    /// the key could not be found within the instance, `f` is invoked and the
+    /// result is cloned and inserted into the instance. After the result is
+    /// stored, the original result is returned.
+    ///
+    /// If the query contains a result for the key, which is not of type [`T`],
+    /// it is replaced, as if the key could not be found. See
+    /// [`Query::try_get_or_insert`].
+    pub fn get_or_insert<K: Hash + 'static, T: QueryValue + Clone>(&mut self, key: &K, f: impl FnOnce() -> T) -> &T {
+        let key = self.hash_key(key);
+
+        if self.must_insert::<T>(key) {
+            let start = Instant::now();
+            let value = f();
+
+            self.insert_slot(key, value, Some(start.elapsed()));
+        }
+
+        self.get_by_key(key).unwrap()
+    }
+
+    /// Looks up the given key within the query instance.
+    ///
+    /// Behaves like [`Query::get_or_insert`], except that a result of another
+    /// type than [`T`] is returned as an error, instead of being replaced.
+    ///
+    /// # Errors
+    ///
+    /// If the query contains a result for the key, which is not of type
+    /// [`T`], returns [`QueryError::TypeMismatch`].
+    pub fn try_get_or_insert<K: Hash + 'static, T: QueryValue + Clone>(
+        &mut self,
+        key: &K,
+        f: impl FnOnce() -> T,
+    ) -> QueryResult<&T> {
+        let key = self.hash_key(key);
+
+        if self.flags.contains(QueryFlags::ALWAYS) || !self.results.contains_key(&key) {
+            let start = Instant::now();
+            let value = f();
+
+            self.insert_slot(key, value, Some(start.elapsed()));
+        }
+
+        Ok(self.value_of(key)?.unwrap())
+    }
+
+    /// Looks up the given key within the query instance.
+    ///
+    /// If a value is found within the query, it is returned as a reference. If
+    /// the key could not be found within the instance, `f` is invoked and the
+    /// result is cloned and inserted into the instance. After the result is
+    /// stored, the original result is returned.
+    ///
+    /// If the query contains a result for the key, which is not of type [`T`],
+    /// it is replaced, as if the key could not be found.
+    ///
+    /// # Errors
+    ///
+    /// If the given closure returns `Err`, this method will propagate the error
+    /// to the caller.
+    pub fn get_or_insert_result<K: Hash + 'static, T: QueryValue + Clone, E>(
+        &mut self,
+        key: &K,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<&T, E> {
+        let key = self.hash_key(key);
+
+        if self.must_insert::<T>(key) {
+            let start = Instant::now();
+            let value = f()?;
+
+            self.insert_slot(key, value, Some(start.elapsed()));
+        }
+
+        Ok(self.get_by_key(key).unwrap())
+    }
+
+    /// Determines whether a result of type [`T`] must be inserted for the
+    /// given key, since the query has [`QueryFlags::ALWAYS`], or it doesn't
+    /// contain a result of type [`T`] for the key.
+    fn must_insert<T: QueryValue>(&self, key: ResultKey) -> bool {
+        self.flags.contains(QueryFlags::ALWAYS) || self.results.get(&key).and_then(Slot::downcast_ref::<T>).is_none()
+    }
 }
 
-impl Database {
-    /// Creates a new empty [`Database`].
-    pub fn new() -> Self {
-        Self::default()
+impl<'q> IntoIterator for &'q Query {
+    type IntoIter = Iter<'q>;
+    type Item = (ResultKey, &'q dyn Any);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
+}
 
-    /// Retrieves a shared read access to the [`DatabaseInner`]'s inner
-    /// instance.
+/// Iterator over all results within a [`Query`], along with their keys.
+///
+/// Created by [`Query::iter`].
+pub struct Iter<'q> {
+    inner: indexmap::map::Iter<'q, ResultKey, Slot>,
+}
+
+impl<'q> Iterator for Iter<'q> {
+    type Item = (ResultKey, &'q dyn Any);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, slot) = self.inner.next()?;
+
+        Some((*key, slot.value()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Inner, non-locked version of [`Database`].
+#[derive(Default)]
+pub(crate) struct DatabaseInner {
+    /// Queries within the database, in the order they were added.
+    pub(crate) queries: IndexMap<QueryId, Query>,
+
+    /// Alternative names of queries, mapped to the ID of the query they
+    /// refer to.
+    pub(crate) aliases: HashMap<QueryId, QueryId>,
+
+    /// Current revision of the database, excluding mutations made through
+    /// the query in `pending`. See [`DatabaseInner::current_revision`].
+    pub(crate) revision: Revision,
+
+    /// Query which was last mutably accessed, whose mutations are not yet
+    /// reflected in `revision`.
+    pending: Option<QueryId>,
+
+    /// Revision of the batch which is currently open, if any. All mutations
+    /// within the batch are stamped with it. See [`Database::batch`].
+    pub(crate) batch_revision: Option<Revision>,
+
+    /// Number of mutable accesses of queries so far. Every access is
+    /// assigned the next tick, which orders results inserted within the same
+    /// revision, so results computed within a batch are still recomputed
+    /// after a later mutation within the batch.
+    pub(crate) ticks: u64,
+}
+
+impl DatabaseInner {
+    /// Gets the current revision of the database.
+    ///
+    /// The revision is only bumped by the mutable access of a query if
+    /// results of the query were actually inserted or removed through it.
     #[inline]
-    pub(crate) fn read(&self) -> parking_lot::RwLockReadGuard<'_, DatabaseInner> {
-        self.inner.try_read().unwrap()
+    pub(crate) fn current_revision(&self) -> Revision {
+        self.pending
+            .and_then(|id| self.queries.get(&id))
+            .map_or(self.revision, |query| self.revision.max(query.changed_at))
+    }
+
+    /// Bumps the revision of the database.
+    ///
+    /// Within a batch, the revision is only bumped to the revision of the
+    /// batch, so the whole batch is committed as a single revision.
+    #[inline]
+    pub(crate) fn bump_revision(&mut self) {
+        self.revision = self.batch_revision.unwrap_or_else(|| self.current_revision().next());
+
+        self.pending = None;
+    }
+
+    /// Gets the tick of the next mutable access of a query.
+    #[inline]
+    pub(crate) fn next_tick(&mut self) -> u64 {
+        self.ticks += 1;
+        self.ticks
+    }
+
+    /// Clears all results from the query with the given ID.
+    #[inline]
+    pub fn clear_by_id(&mut self, id: QueryId) {
+        self.query_mut_by_id(id).clear();
     }
 
-    /// Retrieves an exclusive-write access to the [`DatabaseInner`]'s inner
-    /// instance.
-    #[inline]
-    pub(crate) fn write(&self) -> parking_lot::RwLockWriteGuard<'_, DatabaseInner> {
-        self.inner.try_write().unwrap()
+    /// Clears all results from all queries in the database.
+    ///
+    /// The queries themselves remain registered, so any [`QueryHandle`]
+    /// referencing them stays valid.
+    #[inline]
+    pub fn clear_all(&mut self) {
+        for query in self.queries.values_mut() {
+            query.clear();
+        }
+
+        self.bump_revision();
+    }
+
+    /// Retrieves a shared read access to the [`Query`] which matches the given
+    /// query name.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if another thread write-locked the query before
+    /// this method was invoked, without releasing the lock.
+    pub fn query(&self, name: &str) -> &Query {
+        self.try_query(name).unwrap()
+    }
+
+    /// Retrieves a shared read access to the [`Query`] which matches the given
+    /// query name, if it exists.
+    pub fn try_query(&self, name: &str) -> Option<&Query> {
+        self.get(QueryId::from_name(name))
+    }
+
+    /// Retrieves a shared read access to the [`Query`] with the given ID.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if no query with the given ID exists.
+    pub fn query_by_id(&self, id: QueryId) -> &Query {
+        self.get(id).unwrap()
+    }
+
+    /// Retrieves an exclusive-write access to the [`Query`] which matches the
+    /// given query name.
+    ///
+    /// The revision of the database is bumped if results are inserted into or
+    /// removed from the query. Changing its settings doesn't bump the
+    /// revision.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if another thread write-locked the query before
+    /// this method was invoked, without releasing the lock.
+    pub fn query_mut(&mut self, name: &str) -> &mut Query {
+        self.query_mut_by_id(QueryId::from_name(name))
+    }
+
+    /// Retrieves an exclusive-write access to the [`Query`] with the given ID.
+    ///
+    /// Results inserted through the returned query are stamped with the
+    /// revision following the current one, or the revision of the open batch,
+    /// which only becomes the current revision if any result was actually
+    /// inserted or removed. See [`DatabaseInner::query_mut`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if no query with the given ID exists.
+    pub fn query_mut_by_id(&mut self, id: QueryId) -> &mut Query {
+        self.revision = self.current_revision();
+
+        let revision = self.batch_revision.unwrap_or_else(|| self.revision.next());
+        let tick = self.next_tick();
+
+        let id = self.resolve(id);
+        let query = self.queries.get_mut(&id).unwrap();
+        query.revision = revision;
+        query.tick = tick;
+
+        self.pending = Some(id);
+
+        query
+    }
+
+    /// Adds a new [`Query`] to the database, with the given name and flags.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if a query with the given name already exists.
+    #[inline]
+    pub fn add_query(&mut self, name: &str, flags: QueryFlags) {
+        self.try_add_query(name, flags, ConflictPolicy::Error)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Adds a new [`Query`] to the database, with the given name and flags,
+    /// resolving conflicts with existing queries using the given policy.
+    ///
+    /// Results of other queries which depend on the results of a replaced
+    /// query are kept, since they aren't tracked here. Use
+    /// [`Database::add_query`] to discard them as well.
+    ///
+    /// # Errors
+    ///
+    /// If an alias with the given name exists, or a query with the given name
+    /// exists and the policy doesn't allow it, returns
+    /// [`QueryError::DuplicateQuery`].
+    pub fn try_add_query(&mut self, name: &str, flags: QueryFlags, policy: ConflictPolicy) -> QueryResult<()> {
+        let key = QueryId::from_name(name);
+        let duplicate = || QueryError::DuplicateQuery {
+            query: name.to_string(),
+        };
+
+        if self.aliases.contains_key(&key) {
+            return Err(duplicate());
+        }
+
+        if let Some(existing) = self.queries.get(&key) {
+            match policy {
+                ConflictPolicy::Error => return Err(duplicate()),
+                ConflictPolicy::IgnoreIfFlagsMatch if existing.flags == flags => return Ok(()),
+                ConflictPolicy::IgnoreIfFlagsMatch => return Err(duplicate()),
+                ConflictPolicy::Replace => {}
+            }
+        }
+
+        self.queries.insert(key, Query::new(name.to_string(), flags));
+        self.bump_revision();
+
+        Ok(())
+    }
+
+    /// Adds an alias with the given name, which refers to the query with the
+    /// name `target`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if a query with the given alias name already
+    /// exists.
+    pub fn add_alias(&mut self, alias: &str, target: &str) {
+        let key = QueryId::from_name(alias);
+        let target = self.resolve(QueryId::from_name(target));
+
+        assert!(!self.queries.contains_key(&key), "duplicate query name: {alias}");
+
+        self.aliases.insert(key, target);
+    }
+
+    /// Resolves the given ID into the ID of the query it refers to, if it is
+    /// the ID of an alias.
+    #[inline]
+    pub fn resolve(&self, id: QueryId) -> QueryId {
+        if self.aliases.is_empty() {
+            return id;
+        }
+
+        self.aliases.get(&id).copied().unwrap_or(id)
+    }
+
+    /// Retrieves a shared read access to the [`Query`] with the given ID, or
+    /// the query which the alias with the given ID refers to.
+    #[inline]
+    pub fn get(&self, id: QueryId) -> Option<&Query> {
+        self.queries.get(&self.resolve(id))
+    }
+
+    /// Determines whether a query with the given name exists within the
+    /// database.
+    #[inline]
+    pub fn query_exists(&self, name: &str) -> bool {
+        let key = QueryId::from_name(name);
+
+        self.get(key).is_some()
+    }
+}
+
+thread_local! {
+    /// Queries which are currently being executed on this thread, per
+    /// database.
+    ///
+    /// Databases are identified by their address, which can't change while
+    /// any of their queries are executing, since they are borrowed until the
+    /// execution finishes. Stacks are removed once they are empty, so they
+    /// are never mistaken for those of another database at the same address.
+    static ACTIVE: RefCell<HashMap<usize, Vec<ActiveQuery>>> = RefCell::new(HashMap::new());
+}
+
+/// A query which is currently being executed.
+#[derive(Debug, Clone, Copy)]
+struct ActiveQuery {
+    query: QueryId,
+    key: ResultKey,
+
+    /// Point in time at which the execution exceeds the time limit of its
+    /// sandbox, if any. See [`Query::set_sandbox`].
+    deadline: Option<Deadline>,
+}
+
+impl ActiveQuery {
+    /// Determines whether this is an execution of the given query with the
+    /// given key.
+    #[inline]
+    fn is(&self, query: QueryId, key: ResultKey) -> bool {
+        self.query == query && self.key == key
+    }
+}
+
+/// Reason why a query could not be marked as being executed. See
+/// [`Database::enter`].
+enum Refusal {
+    /// The query is already being executed with the same key, by the given
+    /// queries.
+    Cycle(Vec<ActiveQuery>),
+
+    /// The given query exceeded a limit of its sandbox.
+    Limit(QueryId, ResultKey, Limit),
+}
+
+/// Guard which marks a query as being executed on the current thread, until
+/// the guard is dropped.
+struct ActiveGuard<'db> {
+    db: &'db Database,
+
+    /// Deadline of the execution, if the query has a time limit.
+    deadline: Option<Deadline>,
+}
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        let id = self.db.active_id();
+
+        ACTIVE.with(|active| {
+            let mut active = active.borrow_mut();
+
+            if let Some(stack) = active.get_mut(&id) {
+                stack.pop();
+
+                if stack.is_empty() {
+                    active.remove(&id);
+                }
+            }
+        });
+    }
+}
+
+/// Database of cached query results.
+///
+/// # Threads
+///
+/// With the `sync` feature, the database is [`Send`] and [`Sync`], so it can
+/// be shared between threads within an [`Arc`], including by the tasks of an
+/// asynchronous runtime. No lock of the database is held once a method has
+/// returned, except by the guards returned from [`Database::query`] and
+/// [`Database::query_mut`]. These guards are not [`Send`], so they can't be
+/// held across an `.await` point of a task which may move between threads.
+///
+/// Methods still block the calling thread, while results are computed, and
+/// while they wait for other threads. Since [`Database::batch`] and
+/// [`Database::read_txn`] hold locks until their closure returns, long
+/// batches and transactions should be run on a thread which may block, such
+/// as one of the blocking threads of the runtime.
+pub struct Database {
+    enabled: AtomicBool,
+
+    /// Number of cycles detected while executing queries. See
+    /// [`Database::stats`].
+    cycles: AtomicU64,
+
+    /// Whether queries must be registered before they are executed. See
+    /// [`Database::enable_strict_registration`].
+    strict: AtomicBool,
+    inner: RwLock<DatabaseInner>,
+
+    /// Detector for repeated cache misses, if enabled.
+    stampedes: Mutex<Option<StampedeDetector>>,
+
+    /// Log of results which were slow to compute, if enabled.
+    slow_queries: Mutex<Option<SlowQueryLog>>,
+
+    /// Log of the most recent events within the database, if enabled.
+    ///
+    /// The log is shared with the panic hook installed by
+    /// [`Database::install_panic_dump`], if any.
+    events: Arc<Mutex<Option<EventLog>>>,
+
+    /// Non-deterministic results found by [`Database::execute_query_checked`],
+    /// if determinism checks are enabled.
+    nondeterminism: Mutex<Option<Vec<Nondeterminism>>>,
+
+    /// Whether re-entrant query calls are audited. See
+    /// [`Database::enable_reentrancy_audit`].
+    auditing: AtomicBool,
+
+    /// Audit of queries accessing other queries, if enabled.
+    reentrancy: Mutex<Option<ReentrancyAudit>>,
+
+    /// Middleware which wraps the computation of query results, in the order
+    /// they were added.
+    middleware: RwLock<Vec<Box<dyn Middleware>>>,
+
+    /// Callback which reports memory pressure, if any.
+    memory_monitor: RwLock<Option<Box<dyn MemoryMonitor>>>,
+
+    /// Rules which invalidate results of other queries, when results of a
+    /// query are invalidated.
+    invalidation_rules: RwLock<Vec<InvalidationRule>>,
+
+    /// Dependencies between results, as declared using
+    /// [`Database::declare_dependency`].
+    dependencies: Mutex<DependencyGraph>,
+
+    /// Observers which are notified of changes. See
+    /// [`Database::add_observer`].
+    observers: RwLock<Vec<Box<dyn ChangeObserver>>>,
+
+    /// Batch which is currently open, if any. See [`Database::batch`].
+    batch: Mutex<Batch>,
+
+    /// Lock which is held exclusively while a batch is open, and shared while
+    /// a read transaction is alive, so transactions never observe part of a
+    /// batch. See [`Database::read_txn`].
+    commits: RwLock<()>,
+
+    /// Number of read transactions which are alive on any thread, so threads
+    /// only look up whether they hold one themselves if any exist.
+    txns: AtomicUsize,
+
+    /// Number of interactive queries which are executing on any thread, which
+    /// preempt pumping. See [`Database::execute_with_priority`].
+    interactive: AtomicUsize,
+
+    /// Labels of result keys, if enabled. See
+    /// [`Database::enable_key_labels`].
+    key_labels: Mutex<Option<KeyLabels>>,
+
+    /// Results whose next execution fails with a cycle. See
+    /// [`Database::force_cycle`].
+    #[cfg(feature = "testing")]
+    forced_cycles: Mutex<HashSet<(QueryId, ResultKey)>>,
+
+    /// Interned keys, per type of key. See [`Database::intern`].
+    interners: RwLock<HashMap<TypeId, Box<dyn QueryValue>>>,
+}
+
+impl Database {
+    /// Creates a new empty [`Database`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieves a shared read access to the [`DatabaseInner`]'s inner
+    /// instance.
+    ///
+    /// Without the `sync` feature, the database can only be accessed from a
+    /// single thread, so a lock which is already held indicates a re-entrant
+    /// access and causes a panic, instead of a deadlock.
+    ///
+    /// Within a read transaction on the current thread, the database is
+    /// already read-locked, so it is locked recursively, instead of waiting
+    /// for writers on other threads, which wait for the transaction.
+    #[inline]
+    pub(crate) fn read(&self) -> parking_lot::RwLockReadGuard<'_, DatabaseInner> {
+        if self.holds_txn() {
+            return self.inner.read_recursive();
+        }
+
+        #[cfg(feature = "sync")]
+        return self.inner.read();
+
+        #[cfg(not(feature = "sync"))]
+        self.inner.try_read().unwrap()
+    }
+
+    /// Retrieves an exclusive-write access to the [`DatabaseInner`]'s inner
+    /// instance.
+    ///
+    /// Without the `sync` feature, the database can only be accessed from a
+    /// single thread, so a lock which is already held indicates a re-entrant
+    /// access and causes a panic, instead of a deadlock.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is invoked within a read transaction on the
+    /// current thread, which would otherwise wait for itself. See
+    /// [`Database::read_txn`].
+    #[inline]
+    pub(crate) fn write(&self) -> parking_lot::RwLockWriteGuard<'_, DatabaseInner> {
+        assert!(
+            !self.holds_txn(),
+            "the database cannot be mutated within a read transaction"
+        );
+
+        #[cfg(feature = "sync")]
+        return self.inner.write();
+
+        #[cfg(not(feature = "sync"))]
+        self.inner.try_write().unwrap()
+    }
+
+    /// Determines if the caching mechanism is enabled.
+    #[inline]
+    pub fn caching_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Disables the caching mechanism for all queries.
+    #[inline]
+    pub fn disable_caching(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Enables the caching mechanism for all queries.
+    #[inline]
+    pub fn enable_caching(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Determines if strict registration is enabled.
+    #[inline]
+    pub fn strict_registration(&self) -> bool {
+        self.strict.load(Ordering::Relaxed)
+    }
+
+    /// Enables strict registration, where queries are no longer created on
+    /// the fly by [`Database::ensure_query_exists`] and [`Database::insert`].
+    ///
+    /// Instead, all queries must be registered ahead of time, using
+    /// [`Database::register_query`]. This catches typos in query names and
+    /// enforces a closed set of queries within the database.
+    #[inline]
+    pub fn enable_strict_registration(&self) {
+        self.strict.store(true, Ordering::Relaxed);
+    }
+
+    /// Disables strict registration, so queries are created on the fly again.
+    #[inline]
+    pub fn disable_strict_registration(&self) {
+        self.strict.store(false, Ordering::Relaxed);
+    }
+
+    /// Gets the current revision of the database.
+    ///
+    /// The revision is bumped every time the database is mutated, such as
+    /// when results are inserted or cleared.
+    #[inline]
+    pub fn current_revision(&self) -> Revision {
+        self.read().current_revision()
+    }
+
+    /// Enables detection of cache stampedes, where the same key misses the
+    /// cache at least `threshold` times within `window`.
+    ///
+    /// This is a diagnostic mode, meant to find queries which are recomputed
+    /// over and over, such as when [`QueryFlags::ALWAYS`] is set by mistake or
+    /// when a key contains a non-deterministic component. Offending keys can
+    /// be retrieved using [`Database::stampede_report`].
+    pub fn enable_stampede_detection(&self, window: Duration, threshold: usize) {
+        *self.stampedes.lock() = Some(StampedeDetector::new(window, threshold));
+    }
+
+    /// Disables detection of cache stampedes and discards all recorded misses.
+    pub fn disable_stampede_detection(&self) {
+        *self.stampedes.lock() = None;
+    }
+
+    /// Gets up to `limit` keys which missed the cache repeatedly, ordered by
+    /// the number of misses within a single window.
+    ///
+    /// If stampede detection is not enabled, this method returns an empty list.
+    pub fn stampede_report(&self, limit: usize) -> Vec<Stampede> {
+        self.stampedes
+            .lock()
+            .as_ref()
+            .map(|detector| detector.report(limit))
+            .unwrap_or_default()
+    }
+
+    /// Sets the threshold above which computing a result is considered slow,
+    /// enabling the slow query log if it was disabled.
+    ///
+    /// Every result which takes at least `threshold` to compute is recorded,
+    /// along with the number of its dependencies. Only the most recent 256
+    /// entries are kept, which can be retrieved using
+    /// [`Database::slow_query_log`].
+    pub fn set_slow_query_threshold(&self, threshold: Duration) {
+        let mut log = self.slow_queries.lock();
+
+        match log.as_mut() {
+            Some(log) => log.set_threshold(threshold),
+            None => *log = Some(SlowQueryLog::new(threshold)),
+        }
+    }
+
+    /// Disables the slow query log and discards all recorded entries.
+    pub fn disable_slow_query_log(&self) {
+        *self.slow_queries.lock() = None;
+    }
+
+    /// Gets all entries of the slow query log, from oldest to newest.
+    ///
+    /// If the slow query log is not enabled, this method returns an empty
+    /// list.
+    pub fn slow_query_log(&self) -> Vec<SlowQuery> {
+        self.slow_queries
+            .lock()
+            .as_ref()
+            .map(SlowQueryLog::report)
+            .unwrap_or_default()
+    }
+
+    /// Enables determinism checks for [`Database::execute_query_checked`].
+    ///
+    /// While enabled, every query executed through
+    /// [`Database::execute_query_checked`] is run twice when it is first
+    /// computed, and the hashes of both results are compared. Queries whose
+    /// results differ are recorded and can be retrieved using
+    /// [`Database::nondeterminism_report`].
+    pub fn enable_determinism_checks(&self) {
+        self.nondeterminism.lock().get_or_insert_with(Vec::new);
+    }
+
+    /// Disables determinism checks and discards all recorded results.
+    pub fn disable_determinism_checks(&self) {
+        *self.nondeterminism.lock() = None;
+    }
+
+    /// Gets all results which differed across identical invocations, since
+    /// determinism checks were enabled.
+    pub fn nondeterminism_report(&self) -> Vec<Nondeterminism> {
+        self.nondeterminism.lock().clone().unwrap_or_default()
+    }
+
+    /// Enables auditing of re-entrant query calls.
+    ///
+    /// While enabled, every time a query is accessed during the execution of
+    /// another query, the call is recorded. The resulting call-graph can be
+    /// retrieved using [`Database::reentrancy_report`].
+    pub fn enable_reentrancy_audit(&self) {
+        self.reentrancy.lock().get_or_insert_with(ReentrancyAudit::default);
+        self.auditing.store(true, Ordering::Relaxed);
+    }
+
+    /// Disables auditing of re-entrant query calls and discards all recorded
+    /// calls.
+    pub fn disable_reentrancy_audit(&self) {
+        self.auditing.store(false, Ordering::Relaxed);
+        *self.reentrancy.lock() = None;
+    }
+
+    /// Gets all re-entrant query calls, since auditing was enabled, ordered by
+    /// the number of calls.
+    pub fn reentrancy_report(&self) -> Vec<ReentrantCall> {
+        self.reentrancy
+            .lock()
+            .as_ref()
+            .map(ReentrancyAudit::report)
+            .unwrap_or_default()
+    }
+
+    /// Adds a new [`Query`] with the given name and flags to the database,
+    /// resolving conflicts with existing queries using the given policy.
+    ///
+    /// This allows independent components, such as plugins, to register the
+    /// same query without coordinating with each other.
+    ///
+    /// # Errors
+    ///
+    /// If an alias with the given name exists, or a query with the given name
+    /// exists and the policy doesn't allow it, returns
+    /// [`QueryError::DuplicateQuery`].
+    pub fn add_query(&self, name: &str, flags: QueryFlags, policy: ConflictPolicy) -> QueryResult<()> {
+        let id = QueryId::from_name(name);
+
+        // Results of the replaced query are cleared beforehand, so results
+        // which depend on them are invalidated and observers are notified.
+        if policy == ConflictPolicy::Replace && self.read().queries.contains_key(&id) {
+            self.clear_cascading(id);
+        }
+
+        self.write().try_add_query(name, flags, policy)
+    }
+
+    /// Adds an alias with the given name, which refers to the query with the
+    /// name `target`.
+    ///
+    /// Executing or retrieving the alias is identical to using the target
+    /// query, so a query can be renamed without breaking code or tooling
+    /// which still refers to the old name.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if a query with the given alias name already
+    /// exists.
+    pub fn add_alias(&self, alias: &str, target: &str) {
+        self.write().add_alias(alias, target);
+    }
+
+    /// Adds a middleware, which wraps every computation of a query result, to
+    /// the end of the middleware chain.
+    ///
+    /// Middleware is only invoked when a result is computed, not when it is
+    /// served from the cache. It can be used for cross-cutting concerns, such
+    /// as logging or timing, without modifying individual queries.
+    ///
+    /// Middleware may skip a computation by returning an error, such as
+    /// [`QueryError::Cancelled`], which is returned by fallible methods, such
+    /// as [`Database::try_execute_query`], and causes infallible methods to
+    /// panic.
+    ///
+    /// Middleware must not be added or removed from within a query or another
+    /// middleware, as the chain is locked for the duration of each
+    /// computation.
+    pub fn add_middleware(&self, middleware: impl Middleware + 'static) {
+        self.middleware.write().push(Box::new(middleware));
+    }
+
+    /// Removes all middleware from the database.
+    pub fn clear_middleware(&self) {
+        self.middleware.write().clear();
+    }
+
+    /// Sets the callback which reports whether the process is under memory
+    /// pressure, replacing any existing monitor.
+    ///
+    /// The monitor is consulted every time a result is computed. Whenever it
+    /// reports pressure, all results of queries with [`QueryFlags::SOFT`] are
+    /// discarded, as if by [`Database::release_soft_entries`].
+    pub fn set_memory_monitor(&self, monitor: impl MemoryMonitor + 'static) {
+        *self.memory_monitor.write() = Some(Box::new(monitor));
+    }
+
+    /// Removes the memory monitor, if any.
+    pub fn clear_memory_monitor(&self) {
+        *self.memory_monitor.write() = None;
+    }
+
+    /// Discards all results of queries with [`QueryFlags::SOFT`], which are
+    /// recomputed when they are requested again. Pinned results are kept.
+    /// See [`Database::pin`].
+    ///
+    /// Returns the number of results which were discarded.
+    pub fn release_soft_entries(&self) -> usize {
+        let logging = self.events.lock().is_some();
+        let mut evicted_keys = Vec::new();
+
+        let released = {
+            let mut inner = self.write();
+            let mut released = 0;
+
+            for (id, query) in &mut inner.queries {
+                if query.flags.contains(QueryFlags::SOFT) {
+                    let before = query.results.len();
+                    let pinned = query.flags.contains(QueryFlags::PINNED);
+                    let pinned_keys = &query.pinned;
+
+                    query.results.retain(|key, _| {
+                        let keep = pinned || pinned_keys.contains(key);
+
+                        if !keep && logging {
+                            evicted_keys.push((*id, *key));
+                        }
+
+                        keep
+                    });
+
+                    let evicted = before - query.results.len();
+                    QueryCounters::add(&query.counters.evictions, evicted as u64);
+
+                    released += evicted;
+                }
+            }
+
+            if released > 0 {
+                inner.bump_revision();
+            }
+
+            released
+        };
+
+        for (id, key) in evicted_keys {
+            self.log_event(id, Some(key), EventKind::Evicted);
+        }
+
+        released
+    }
+
+    /// Shrinks the capacity of all internal maps of the database as much as
+    /// possible, such as after clearing many results.
+    ///
+    /// Returns an estimate of the number of bytes which were reclaimed.
+    pub fn compact(&self) -> usize {
+        let mut inner = self.write();
+
+        let mut reclaimed = inner.queries.values_mut().map(Query::shrink_to_fit).sum::<usize>();
+
+        let before = inner.queries.capacity() * size_of::<(QueryId, Query)>();
+        inner.queries.shrink_to_fit();
+        reclaimed += before - inner.queries.capacity() * size_of::<(QueryId, Query)>();
+
+        let before = inner.aliases.capacity() * size_of::<(QueryId, QueryId)>();
+        inner.aliases.shrink_to_fit();
+        reclaimed += before - inner.aliases.capacity() * size_of::<(QueryId, QueryId)>();
+
+        reclaimed
+    }
+
+    /// Discards all soft results, if the memory monitor reports pressure.
+    fn check_memory_pressure(&self) {
+        let under_pressure = self.memory_monitor.read().as_ref().is_some_and(|monitor| monitor());
+
+        if under_pressure {
+            self.release_soft_entries();
+        }
+    }
+
+    /// Gets the identity of the database within the stacks of queries which
+    /// are currently being executed. See [`ACTIVE`].
+    #[inline]
+    fn active_id(&self) -> usize {
+        std::ptr::from_ref(self) as usize
+    }
+
+    /// Gets the query which is currently being executed on this thread.
+    fn current_query(&self) -> Option<ActiveQuery> {
+        let id = self.active_id();
+
+        ACTIVE.with(|active| active.borrow().get(&id)?.last().copied())
+    }
+
+    /// Determines whether the query with the given ID is currently being
+    /// executed with the given key on this thread.
+    fn is_active(&self, query: QueryId, key: ResultKey) -> bool {
+        let id = self.active_id();
+
+        ACTIVE.with(|active| {
+            active
+                .borrow()
+                .get(&id)
+                .is_some_and(|stack| stack.iter().any(|active| active.is(query, key)))
+        })
+    }
+
+    /// Marks the given query as being executed on this thread, until the
+    /// returned guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the query is already being executed with the same key on this
+    /// thread, returns [`QueryError::Cycle`]. If the query, or any query which
+    /// is executing it, exceeds the limits of its sandbox, returns
+    /// [`QueryError::LimitExceeded`].
+    fn enter(&self, query: QueryId, key: ResultKey) -> QueryResult<ActiveGuard<'_>> {
+        let (query, sandbox) = {
+            let inner = self.read();
+            let query = inner.resolve(query);
+
+            (query, inner.get(query).and_then(|found| SandboxState::of(found, key)))
+        };
+
+        #[cfg(feature = "testing")]
+        if let Some(error) = self.take_forced_cycle(query, key) {
+            return Err(error);
+        }
+
+        let id = self.active_id();
+
+        let entered = ACTIVE.with(|active| {
+            let mut active = active.borrow_mut();
+            let stack = active.get(&id).map_or(&[][..], Vec::as_slice);
+
+            if let Some(start) = stack.iter().position(|active| active.is(query, key)) {
+                return Err(Refusal::Cycle(stack[start..].to_vec()));
+            }
+
+            // Sandboxed queries can't be interrupted, so their time limits are
+            // checked whenever they execute other queries.
+            let now = Instant::now();
+
+            if let Some(expired) = stack
+                .iter()
+                .find(|active| active.deadline.is_some_and(|deadline| deadline.at <= now))
+            {
+                let limit = expired.deadline.map_or(Duration::ZERO, |deadline| deadline.limit);
+
+                return Err(Refusal::Limit(expired.query, expired.key, Limit::Duration(limit)));
+            }
+
+            let deadline = sandbox
+                .map(|sandbox| sandbox.check(query, stack))
+                .transpose()
+                .map_err(|limit| Refusal::Limit(query, key, limit))?
+                .flatten();
+
+            active.entry(id).or_default().push(ActiveQuery { query, key, deadline });
+
+            Ok(deadline)
+        });
+
+        match entered {
+            Ok(deadline) => Ok(ActiveGuard { db: self, deadline }),
+            Err(Refusal::Cycle(cycle)) => {
+                self.cycles.fetch_add(1, Ordering::Relaxed);
+
+                let error = {
+                    let inner = self.read();
+                    let name = |id: QueryId| inner.get(id).map(|query| query.name.clone()).unwrap_or_default();
+
+                    QueryError::Cycle {
+                        query: name(query),
+                        key,
+                        label: self.key_label_by_id(query, key),
+                        path: cycle.iter().map(|active| (name(active.query), active.key)).collect(),
+                    }
+                };
+
+                self.log_event(query, Some(key), EventKind::Cycle);
+
+                Err(error)
+            }
+            Err(Refusal::Limit(query, key, limit)) => Err(self.limit_exceeded(query, key, limit)),
+        }
+    }
+
+    /// Records that the given query, which is currently executing on this
+    /// thread, accessed the query with the given ID, if auditing is enabled.
+    fn audit_access(&self, caller: ActiveQuery, query: QueryId) {
+        if !self.auditing.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut audit = self.reentrancy.lock();
+
+        let Some(audit) = audit.as_mut() else {
+            return;
+        };
+
+        let inner = self.read();
+
+        if let (Some(caller), Some(callee)) = (inner.get(caller.query), inner.get(query)) {
+            audit.record(&caller.name, &callee.name);
+        }
+    }
+
+    /// Determines whether cached results of the given query may be reused,
+    /// which is not the case when caching is disabled or the query has
+    /// [`QueryFlags::ALWAYS`].
+    fn reuses_results(&self, query: &Query) -> bool {
+        self.caching_enabled() && !query.flags.contains(QueryFlags::ALWAYS)
+    }
+
+    /// Gets a clone of the cached result with the given key, within the query
+    /// with the given ID, if it may be reused, and records the access to it.
+    ///
+    /// Returns the result along with the status of the lookup. If no result is
+    /// returned, the status describes why the result must be computed.
+    ///
+    /// If the query which is currently executing on this thread accesses the
+    /// result, it is recorded as depending on the result. Results which
+    /// aren't dirty are served while the database and the dependency graph
+    /// are each locked once, along with recording the dependency. Only dirty
+    /// results are verified separately. See [`Database::revalidate`].
+    ///
+    /// # Errors
+    ///
+    /// If the cached result is not of type [`T`], returns
+    /// [`QueryError::TypeMismatch`].
+    fn lookup_cached<T: QueryValue + Clone>(
+        &self,
+        id: QueryId,
+        key: ResultKey,
+    ) -> QueryResult<(Option<T>, CacheStatus)> {
+        let caller = self.current_query();
+
+        if let Some(caller) = caller {
+            self.audit_access(caller, id);
+        }
+
+        {
+            let inner = self.read();
+            let mut graph = self.dependencies.lock();
+            let node = (inner.resolve(id), key);
+
+            if let Some(caller) = caller {
+                graph.record(&inner, (inner.resolve(caller.query), caller.key), node);
+            }
+
+            let untracked = graph.is_untracked(node);
+
+            if untracked || !graph.is_dirty(node) {
+                drop(graph);
+
+                return self.lookup_valid(&inner, id, key, !untracked);
+            }
+        }
+
+        self.check_writable(id)?;
+
+        let valid = self.revalidate(id, key);
+
+        self.lookup_valid(&self.read(), id, key, valid)
+    }
+
+    /// Ensures that results of the query with the given ID may be stored,
+    /// which they can't within a read transaction on the current thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QueryError::ReadOnly`] within a read transaction. See
+    /// [`Database::read_txn`].
+    fn check_writable(&self, id: QueryId) -> QueryResult<()> {
+        if !self.holds_txn() {
+            return Ok(());
+        }
+
+        let query = self.read().get(id).map(|query| query.name.clone()).unwrap_or_default();
+
+        Err(QueryError::ReadOnly { query })
+    }
+
+    /// Gets a clone of the cached result with the given key, within the query
+    /// with the given ID, if it is `valid` and may be reused. See
+    /// [`Database::lookup_cached`].
+    fn lookup_valid<T: QueryValue + Clone>(
+        &self,
+        inner: &DatabaseInner,
+        id: QueryId,
+        key: ResultKey,
+        valid: bool,
+    ) -> QueryResult<(Option<T>, CacheStatus)> {
+        let query = inner.query_by_id(id);
+
+        let (cached, status) = match query.value_of::<T>(key)? {
+            Some(value) if valid && self.reuses_results(query) => (Some(value.clone()), CacheStatus::Hit),
+            // Queries on the active stack are identified by the ID of their
+            // name, instead of any alias used to execute them.
+            Some(value)
+                if query.flags.contains(QueryFlags::RECURSIVE)
+                    && self.is_active(QueryId::from_name(&query.name), key) =>
+            {
+                (Some(value.clone()), CacheStatus::Provisional)
+            }
+            Some(_) => (None, CacheStatus::Recomputed),
+            None => (None, CacheStatus::Miss),
+        };
+
+        if matches!(status, CacheStatus::Hit | CacheStatus::Provisional) {
+            if let Some(slot) = query.results.get(&key) {
+                slot.touch();
+            }
+
+            QueryCounters::add(&query.counters.hits, 1);
+            query.callbacks.hit(key);
+        } else {
+            QueryCounters::add(&query.counters.misses, 1);
+            query.callbacks.miss(key);
+        }
+
+        Ok((cached, status))
+    }
+
+    /// Executes the given closure to compute a result of the query with the
+    /// given ID, which could not be found in the cache.
+    ///
+    /// Returns the computed result, along with the time it took to compute.
+    ///
+    /// # Errors
+    ///
+    /// If the query is already being executed with the same key on this
+    /// thread, returns [`QueryError::Cycle`]. If the query exceeds the limits
+    /// of its sandbox, returns [`QueryError::LimitExceeded`] and discards the
+    /// computed result, along with any outdated result for the key. If any
+    /// middleware returns an error, returns the error and discards any
+    /// outdated result for the key.
+    fn compute<T>(&self, query: QueryId, key: ResultKey, f: impl FnOnce() -> T) -> QueryResult<(T, Duration)> {
+        self.check_writable(query)?;
+
+        let active = self.enter(query, key)?;
+
+        self.check_memory_pressure();
+        self.record_miss(query, key);
+
+        let reason = self.recompute_reason(query, key);
+        self.begin_recompute(query, key);
+
+        let start = Instant::now();
+
+        // Queries computed by the middleware chain may compute other queries,
+        // so the chain must be locked recursively.
+        let chain = self.middleware.read_recursive();
+        let value = if chain.is_empty() {
+            Ok(f())
+        } else {
+            self.intercept(&chain, query, key, f)
+        };
+
+        let duration = start.elapsed();
+        self.record_duration(query, key, duration, reason);
+
+        let value = match value {
+            Ok(value) => value,
+            Err(err) => {
+                self.discard_outdated(query, key);
+
+                return Err(err);
+            }
+        };
+
+        if let Some(deadline) = active.deadline
+            && duration > deadline.limit
+        {
+            self.discard_outdated(query, key);
+
+            return Err(self.limit_exceeded(query, key, Limit::Duration(deadline.limit)));
+        }
+
+        self.record_adaptive(query, duration);
+
+        Ok((value, duration))
+    }
+
+    /// Discards the outdated result with the given key, within the query with
+    /// the given ID, after recomputing it failed.
+    ///
+    /// The outdated result was marked as clean when the recomputation
+    /// started, so it must be discarded, instead of being reused. Results
+    /// which depend on it were computed from the outdated result, so they're
+    /// marked as dirty, and recomputed once they're verified.
+    pub(crate) fn discard_outdated(&self, query: QueryId, key: ResultKey) {
+        let mut inner = self.write();
+        let mut graph = self.dependencies.lock();
+
+        let node = (inner.resolve(query), key);
+        inner.query_mut_by_id(query).remove(key);
+        graph.mark_dirty(node);
+    }
+
+    /// Computes a result of the query with the given ID, wrapped by the given
+    /// chain of middleware.
+    ///
+    /// # Errors
+    ///
+    /// If any of the middleware returns an error, returns the error, without
+    /// computing the result.
+    fn intercept<T>(
+        &self,
+        chain: &[Box<dyn Middleware>],
+        query: QueryId,
+        key: ResultKey,
+        f: impl FnOnce() -> T,
+    ) -> QueryResult<T> {
+        let call = QueryCall {
+            query: self
+                .read()
+                .get(query)
+                .map(|query| query.name.clone())
+                .unwrap_or_default(),
+            key,
+        };
+
+        let mut f = Some(f);
+        let mut value = None;
+
+        Next::new(&call, chain, &mut || {
+            if let Some(f) = f.take() {
+                value = Some(f());
+            }
+        })
+        .run()?;
+
+        // The marker proves that the query was run, unless a middleware
+        // returned a marker of another computation.
+        value.ok_or(QueryError::Cancelled { query: call.query })
+    }
+
+    /// Records a cache miss for the given key, if stampede detection is
+    /// enabled.
+    fn record_miss(&self, query: QueryId, key: ResultKey) {
+        if let Some(detector) = self.stampedes.lock().as_mut()
+            && let Some(query) = self.read().get(query)
+        {
+            detector.record_miss(&query.name, key);
+        }
+    }
+
+    /// Records the time it took to compute the result with the given key,
+    /// within the query with the given ID, along with why it was computed.
+    fn record_duration(&self, query: QueryId, key: ResultKey, duration: Duration, reason: Option<RawReason>) {
+        if let Some(query) = self.read().get(query) {
+            query.counters.durations.record(duration);
+        }
+
+        self.log_event_with_reason(query, Some(key), EventKind::Executed { duration }, reason);
+
+        let mut log = self.slow_queries.lock();
+
+        let Some(log) = log.as_mut().filter(|log| log.is_slow(duration)) else {
+            return;
+        };
+
+        let (id, name, dependencies) = {
+            let inner = self.read();
+            let graph = self.dependencies.lock();
+            let id = inner.resolve(query);
+
+            let Some(found) = inner.get(id) else {
+                return;
+            };
+
+            (id, found.name.clone(), graph.dependencies((id, key)).count())
+        };
+
+        log.record(SlowQuery {
+            query: name,
+            key,
+            label: self.key_label_by_id(id, key),
+            duration,
+            dependencies,
+        });
+    }
+
+    /// Clears all results from the query with the given name, along with all
+    /// queries affected by invalidation rules. See
+    /// [`Database::add_invalidation_rule`].
+    #[inline]
+    pub fn clear(&self, query: &str) {
+        self.clear_cascading(QueryId::from_name(query));
+    }
+
+    /// Clears all results from all queries in the database.
+    #[inline]
+    pub fn clear_all(&self) {
+        let cleared = {
+            let mut inner = self.write();
+            inner.clear_all();
+
+            inner.queries.values().map(|query| query.name.clone()).collect()
+        };
+
+        self.publish(ChangeSet {
+            cleared,
+            ..ChangeSet::default()
+        });
+    }
+
+    /// Retrieves a shared read access to the [`Query`] which matches the given
+    /// query name.
+    pub fn query(&self, name: &str) -> parking_lot::MappedRwLockReadGuard<'_, Query> {
+        parking_lot::RwLockReadGuard::map(self.read(), |db| db.query(name))
+    }
+
+    /// Retrieves an exclusive-write access to the [`Query`] which matches the
+    /// given query name.
+    ///
+    /// The revision of the database is only bumped if results are inserted
+    /// into or removed from the query. See [`DatabaseInner::query_mut`].
+    pub fn query_mut(&self, name: &str) -> parking_lot::MappedRwLockWriteGuard<'_, Query> {
+        parking_lot::RwLockWriteGuard::map(self.write(), |db| db.query_mut(name))
+    }
+
+    /// Retrieves a shared read access to the [`Query`] with the given ID.
+    fn query_by_id(&self, id: QueryId) -> parking_lot::MappedRwLockReadGuard<'_, Query> {
+        parking_lot::RwLockReadGuard::map(self.read(), |db| db.query_by_id(id))
+    }
+
+    /// Retrieves an exclusive-write access to the [`Query`] with the given ID.
+    /// See [`DatabaseInner::query_mut_by_id`].
+    fn query_mut_by_id(&self, id: QueryId) -> parking_lot::MappedRwLockWriteGuard<'_, Query> {
+        parking_lot::RwLockWriteGuard::map(self.write(), |db| db.query_mut_by_id(id))
+    }
+
+    /// Inserts the given result into the query with the given name, indexed by
+    /// the given key.
+    ///
+    /// If the query does not exist, it is created without any flags. If the
+    /// query already contains a result for the key [`key`], the old result is
+    /// overwritten.
+    ///
+    /// All results which depend on the inserted result are marked as dirty, so
+    /// they are revalidated when they are requested again. See
+    /// [`Database::is_dirty`].
+    ///
+    /// # Panics
+    ///
+    /// When strict registration is enabled, this method panics if the query
+    /// does not exist.
+    pub fn insert<K: Hash + 'static, T: QueryValue + Clone>(&self, name: &str, key: &K, value: T) {
+        let (query, key) = {
+            let mut inner = self.write();
+            let mut graph = self.dependencies.lock();
+
+            if !inner.query_exists(name) {
+                assert!(!self.strict_registration(), "{}", QueryError::Unregistered {
+                    query: name.to_string()
+                });
+
+                inner.add_query(name, QueryFlags::empty());
+            }
+
+            let query = inner.query_mut(name);
+            let key = query.hash_key(key);
+            let changed = query.insert_slot(key, value, None);
+            let query = query.name.clone();
+
+            graph.mark_dirty((inner.resolve(QueryId::from_name(name)), key));
+
+            (changed.then_some(query), key)
+        };
+
+        // Results which are unchanged are not reported, so observers aren't
+        // notified of insertions which don't change anything.
+        if let Some(query) = query {
+            self.publish(ChangeSet {
+                inserted: vec![(query, key)],
+                ..ChangeSet::default()
+            });
+        }
+    }
+
+    /// Determines whether the query with the given name contains a result for
+    /// the given key.
+    ///
+    /// If the query does not exist, this method returns `false`. Like
+    /// [`Database::peek`], this doesn't record a dependency.
+    pub fn contains<K: Hash + 'static>(&self, name: &str, key: &K) -> bool {
+        self.read()
+            .get(QueryId::from_name(name))
+            .is_some_and(|query| query.contains(key))
+    }
+
+    /// Gets a clone of the cached result with the given key, within the query
+    /// with the given name.
+    ///
+    /// Unlike [`Database::execute_query`], this never computes the result.
+    /// Like [`Database::peek`], this doesn't record a dependency.
+    ///
+    /// # Returns
+    ///
+    /// If the query does not exist, no value could be found, or the value found
+    /// is not of type [`T`], this method returns [`None`].
+    pub fn get_cached<K: Hash + 'static, T: QueryValue + Clone>(&self, name: &str, key: &K) -> Option<T> {
+        self.peek(name, key, T::clone)
+    }
+
+    /// Gets a reference to the cached result with the given key, within the
+    /// query with the given name, without cloning the result.
+    ///
+    /// Unlike [`Database::execute_query`], this never computes the result.
+    /// The database is read-locked for as long as the returned [`CachedRef`]
+    /// is alive, so it must be dropped before any query is executed. Computing
+    /// a result or mutating the database while the reference is alive
+    /// deadlocks when the `sync` feature is enabled, and panics otherwise.
+    /// Even cached results lock the database again, which deadlocks when the
+    /// `sync` feature is enabled and another thread is waiting to write.
+    ///
+    /// # Returns
+    ///
+    /// If the query does not exist, no value could be found, or the value found
+    /// is not of type [`T`], this method returns [`None`].
+    pub fn get_ref<K: Hash + 'static, T: QueryValue>(&self, name: &str, key: &K) -> Option<CachedRef<'_, T>> {
+        let id = QueryId::from_name(name);
+
+        self.get_ref_by_id(id, self.hash_key(id, key))
+    }
+
+    /// Gets a reference to the cached result with the given key, within the
+    /// query with the given ID. See [`Database::get_ref`].
+    pub(crate) fn get_ref_by_id<T: QueryValue>(&self, query: QueryId, key: ResultKey) -> Option<CachedRef<'_, T>> {
+        parking_lot::RwLockReadGuard::try_map(self.read(), |db| db.get(query)?.lookup(key)?.downcast_ref::<T>())
+            .ok()
+            .map(CachedRef::new)
+    }
+
+    /// Invokes `f` with a reference to the cached result with the given key,
+    /// within the query with the given name, without cloning the result.
+    ///
+    /// Unlike [`Database::execute_query`], this never computes the result.
+    /// The database is locked while `f` is invoked, so `f` must not access
+    /// the database itself.
+    ///
+    /// Peeking doesn't record a dependency of the query which is currently
+    /// executing, and doesn't count as a cache hit or miss, so it can be used
+    /// by heuristics which must not affect invalidation. Results which are
+    /// marked as dirty are not revalidated, so they may be outdated.
+    ///
+    /// # Returns
+    ///
+    /// If the query does not exist, no value could be found, or the value found
+    /// is not of type [`T`], this method returns [`None`]. Otherwise, returns
+    /// the value returned by `f`.
+    pub fn peek<K: Hash + 'static, T: QueryValue + Clone, R>(
+        &self,
+        name: &str,
+        key: &K,
+        f: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        let id = QueryId::from_name(name);
+
+        self.peek_by_id(id, self.hash_key(id, key), f)
     }
 
-    /// Determines if the caching mechanism is enabled.
-    #[inline]
-    pub fn caching_enabled(&self) -> bool {
-        *self.enabled.try_read().unwrap()
+    /// Invokes `f` with a reference to the cached result with the given key,
+    /// within the query with the given ID. See [`Database::peek`].
+    pub(crate) fn peek_by_id<T: QueryValue + Clone, R>(
+        &self,
+        query: QueryId,
+        key: ResultKey,
+        f: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        let inner = self.read();
+        let value = inner.get(query)?.get_by_key::<T>(key)?;
+
+        Some(f(value))
     }
 
-    /// Disables the caching mechanism for all queries.
-    #[inline]
-    pub fn disable_caching(&self) {
-        *self.enabled.try_write().unwrap() = false;
+    /// Gets clones of all results within the database which are of type [`T`],
+    /// across all queries.
+    ///
+    /// This is useful for collecting cross-cutting artifacts, such as
+    /// diagnostics, which are produced by many different queries.
+    pub fn collect_values<T: QueryValue + Clone>(&self) -> Vec<T> {
+        self.read()
+            .queries
+            .values()
+            .flat_map(Query::values::<T>)
+            .cloned()
+            .collect()
     }
 
-    /// Enables the caching mechanism for all queries.
-    #[inline]
-    pub fn enable_caching(&self) {
-        *self.enabled.try_write().unwrap() = true;
+    /// Gets the keys of all results within the query with the given name,
+    /// which were inserted after the given revision.
+    ///
+    /// This can be used to only reprocess results which were recomputed since
+    /// a previously observed [`Database::current_revision`].
+    pub fn entries_changed_since(&self, name: &str, revision: Revision) -> Vec<ResultKey> {
+        self.query(name).changed_since(revision)
     }
 
-    /// Clears all results from the query with the given name.
-    #[inline]
-    pub fn clear(&self, query: &str) {
-        self.write().clear(query);
+    /// Enables checksums for all results of type [`T`] which are inserted into
+    /// the query with the given name from now on. See
+    /// [`Query::enable_checksums`].
+    pub fn enable_checksums<T: QueryValue + Hash>(&self, name: &str) {
+        self.query_mut(name).enable_checksums::<T>();
     }
 
-    /// Clears all results from all queries in the database.
-    #[inline]
-    pub fn clear_all(&self) {
-        self.write().clear_all();
+    /// Enables equality checks for all results of type [`T`] which are
+    /// inserted into the query with the given name from now on. See
+    /// [`Query::enable_equality_checks`].
+    pub fn enable_equality_checks<T: QueryValue + PartialEq>(&self, name: &str) {
+        self.query_mut(name).enable_equality_checks::<T>();
     }
 
-    /// Retrieves a shared read access to the [`Query`] which matches the given
-    /// query name.
-    pub fn query(&self, name: &str) -> parking_lot::MappedRwLockReadGuard<'_, Query> {
-        parking_lot::RwLockReadGuard::map(self.read(), |db| db.query(name))
+    /// Limits the number of dependencies which are tracked for a single result
+    /// of the query with the given name. See [`Query::set_max_dependencies`].
+    pub fn set_max_dependencies(&self, name: &str, limit: usize) {
+        self.query_mut(name).set_max_dependencies(limit);
     }
 
-    /// Retrieves an exclusive-write access to the [`Query`] which matches the
-    /// given query name.
-    pub fn query_mut(&self, name: &str) -> parking_lot::MappedRwLockWriteGuard<'_, Query> {
-        parking_lot::RwLockWriteGuard::map(self.write(), |db| db.query_mut(name))
+    /// Gets the provenance of the result with the given key, within the query
+    /// with the given name.
+    ///
+    /// # Returns
+    ///
+    /// If no value could be found, this method returns [`None`].
+    pub fn provenance<K: Hash + 'static>(&self, name: &str, key: &K) -> Option<EntryProvenance> {
+        self.query(name).provenance(key)
     }
 
     /// Ensures that a [`Query`] with the given name exists. If the query does
     /// not exist, a new [`Query`] is added with the given name, using the
     /// flags returned by `flags`.
     ///
+    /// If the query already exists, the flags returned by `flags` are added to
+    /// the flags of the query. Call sites which disagree on the flags of a
+    /// query therefore agree on the union of their flags, instead of the
+    /// flags depending on which call site added the query first.
+    ///
     /// # Panics
     ///
     /// This method panics if another thread write-locked the query before
     /// this method was invoked, without releasing the lock.
-    pub fn ensure_query_exists(&self, name: &str, flags: impl FnOnce() -> QueryFlags) {
-        if !self.read().query_exists(name) {
-            self.write().add_query(name, flags());
+    ///
+    /// When strict registration is enabled, this method panics if the query
+    /// does not exist. See [`Database::try_ensure_query_exists`].
+    pub fn ensure_query_exists(&self, name: &(impl QueryName + ?Sized), flags: impl FnOnce() -> QueryFlags) {
+        self.try_ensure_query_exists(name, flags)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Ensures that a [`Query`] with the given name exists. See
+    /// [`Database::ensure_query_exists`].
+    ///
+    /// # Errors
+    ///
+    /// If strict registration is enabled and the query does not exist,
+    /// returns [`QueryError::Unregistered`].
+    pub fn try_ensure_query_exists(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        flags: impl FnOnce() -> QueryFlags,
+    ) -> QueryResult<()> {
+        let flags = flags();
+        let exists = match self.read().get(name.query_id()) {
+            Some(query) if query.flags.contains(flags) => return Ok(()),
+            Some(_) => true,
+            None => false,
+        };
+
+        if !exists && self.strict_registration() {
+            return Err(QueryError::Unregistered {
+                query: name.to_query_name(),
+            });
+        }
+
+        self.add_or_merge_query(name, flags);
+
+        Ok(())
+    }
+
+    /// Adds a [`Query`] with the given name and flags if it does not exist
+    /// yet. Otherwise, the given flags are added to the flags of the query.
+    fn add_or_merge_query(&self, name: &(impl QueryName + ?Sized), flags: QueryFlags) {
+        let id = name.query_id();
+
+        if self.read().get(id).is_some_and(|query| query.flags.contains(flags)) {
+            return;
+        }
+
+        // Another thread may have added the query between releasing the read
+        // lock and acquiring the write lock.
+        let mut inner = self.write();
+        let id = inner.resolve(id);
+
+        match inner.queries.get_mut(&id) {
+            Some(query) => query.flags |= flags,
+            None => inner.add_query(&name.to_query_name(), flags),
         }
     }
 
+    /// Ensures that a [`Query`] with the given name exists, using the given
+    /// flags if it is added, and returns a typed handle to it.
+    ///
+    /// If the query already exists, the given flags are added to the flags of
+    /// the query. See [`Database::ensure_query_exists`].
+    ///
+    /// The returned [`QueryHandle`] refers to the query by its [`QueryId`],
+    /// so executing the query through the handle doesn't hash the query name
+    /// again, and the key and result types are checked at compile-time.
+    ///
+    /// The key type must be `'static`, so keys can be normalized before they
+    /// are hashed. See [`QueryHandle`].
+    pub fn register_query<K: Hash + 'static, V: QueryValue + Clone>(
+        &self,
+        name: &str,
+        flags: QueryFlags,
+    ) -> QueryHandle<K, V> {
+        self.add_or_merge_query(name, flags);
+
+        QueryHandle::new(QueryId::from_name(name))
+    }
+
     /// Looks up the given key within the query instance with the given name.
     ///
     /// If a value is found within the query, it is cloned and returned. If
     /// the key could not be found within the instance, `f` is invoked and the
     /// result is cloned and inserted into the instance. After the result is
     /// stored, the original result is returned.
-    pub fn execute_query<K: Hash, T: Clone + 'static>(&self, name: &str, key: &K, f: impl FnOnce() -> T) -> T {
-        let cached = if self.caching_enabled() {
-            self.query(name).get::<K, T>(key).cloned()
-        } else {
-            None
-        };
+    ///
+    /// A cached result which isn't dirty is served while the database is
+    /// read-locked once, along with the dependency graph, to verify and clone
+    /// the result and record it as a dependency of the query which is
+    /// currently executing. The stack of executing queries is kept per thread,
+    /// so it isn't locked at all.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the query contains a result for the key which is
+    /// not of type [`T`], which indicates that two call sites disagree on the
+    /// type of the query. It also panics if the query is already being
+    /// executed with the same key on this thread, or if the query has a key
+    /// normalizer. See [`Database::try_execute_query`].
+    pub fn execute_query<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let id = name.query_id();
+
+        let key = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_by_id(id, key, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that the key isn't
+    /// hashed again, such as keys which `#[cached_query]` hashed using
+    /// [`ResultKey::from_hashable`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_query_by_key<T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: ResultKey,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let id = name.query_id();
+
+        self.check_unnormalized(id).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_by_id(id, key, f)
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that `f` is invoked
+    /// with the database and the key. This allows `f` to be a plain function,
+    /// which executes the query recursively, without capturing any state.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_recursive<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce(&Self, &K) -> T,
+    ) -> T {
+        self.execute_query(name, key, || f(self, key))
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given ID. See [`Database::execute_query`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if no query with the given ID exists.
+    pub(crate) fn execute_query_by_id<T: QueryValue + Clone>(
+        &self,
+        id: QueryId,
+        key: ResultKey,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        self.execute_query_with_status_by_id(id, key, f)
+            .unwrap_or_else(|err| panic!("{err}"))
+            .0
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that errors are
+    /// returned, instead of panicking.
+    ///
+    /// # Errors
+    ///
+    /// If the query contains a result for the key, which is not of type
+    /// [`T`], returns [`QueryError::TypeMismatch`]. If the query is already
+    /// being executed with the same key on this thread, returns
+    /// [`QueryError::Cycle`]. If the query has a key normalizer, returns
+    /// [`QueryError::Unnormalized`].
+    pub fn try_execute_query<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> T,
+    ) -> QueryResult<T> {
+        let id = name.query_id();
+
+        self.execute_query_with_status_by_id(id, self.hash_unnormalized(id, key)?, f)
+            .map(|(value, _)| value)
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that the returned
+    /// result is accompanied by a [`CacheStatus`], describing whether the
+    /// result was found in the cache or computed.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_query_with_status<K: Hash, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> T,
+    ) -> (T, CacheStatus) {
+        let id = name.query_id();
+
+        let key = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_with_status_by_id(id, key, f)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given ID. See [`Database::execute_query_with_status`].
+    ///
+    /// # Errors
+    ///
+    /// If the query contains a result for the key, which is not of type
+    /// [`T`], returns [`QueryError::TypeMismatch`]. If the query is already
+    /// being executed with the same key on this thread, returns
+    /// [`QueryError::Cycle`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if no query with the given ID exists.
+    fn execute_query_with_status_by_id<T: QueryValue + Clone>(
+        &self,
+        id: QueryId,
+        key: ResultKey,
+        f: impl FnOnce() -> T,
+    ) -> QueryResult<(T, CacheStatus)> {
+        let (cached, status) = self.lookup_cached::<T>(id, key)?;
 
         if let Some(cached) = cached {
-            return cached;
+            return Ok((cached, status));
         }
 
-        let value = f();
-        self.query_mut(name).insert::<K, T>(key, value.clone());
+        let (value, duration) = self.compute(id, key, f)?;
+        let value = self.query_mut_by_id(id).store(key, value, Some(duration));
+
+        Ok((value, status))
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that a clone of the
+    /// key is retained along with the computed result, so that it can be
+    /// retrieved using [`Query::keys_typed`].
+    ///
+    /// The key is normalized before it is hashed, if the query has a key
+    /// normalizer. See [`Query::set_key_normalizer`].
+    pub fn execute_query_keyed<K: Hash + QueryValue + Clone, T: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let id = name.query_id();
+        let hashed = self.hash_key(id, key);
+
+        let (value, status) = self
+            .execute_query_with_status_by_id(id, hashed, f)
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        // Cached results already retained their key when they were computed.
+        if status != CacheStatus::Hit {
+            self.query_mut_by_id(id).retain_key(hashed, key);
+        }
 
         value
     }
 
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query`], except that `f` is invoked
+    /// twice when determinism checks are enabled and the key could not be
+    /// found. If the hashes of both results differ, the key is recorded as
+    /// being non-deterministic. See [`Database::enable_determinism_checks`].
+    pub fn execute_query_checked<K: Hash, T: QueryValue + Clone + Hash>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl Fn() -> T,
+    ) -> T {
+        let id = name.query_id();
+        let key = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_checked_by_id(name, id, key, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given name. See [`Database::execute_query_checked`] and
+    /// [`Database::execute_query_by_key`].
+    pub fn execute_query_checked_by_key<T: QueryValue + Clone + Hash>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: ResultKey,
+        f: impl Fn() -> T,
+    ) -> T {
+        let id = name.query_id();
+        self.check_unnormalized(id).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_checked_by_id(name, id, key, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given ID. See [`Database::execute_query_checked`].
+    fn execute_query_checked_by_id<T: QueryValue + Clone + Hash>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        id: QueryId,
+        key: ResultKey,
+        f: impl Fn() -> T,
+    ) -> T {
+        if self.nondeterminism.lock().is_none() {
+            return self.execute_query_by_id(id, key, f);
+        }
+
+        self.execute_query_by_id(id, key, || {
+            let first = f();
+            let second = f();
+
+            if fxhash::hash64(&first) != fxhash::hash64(&second)
+                && let Some(found) = self.nondeterminism.lock().as_mut()
+            {
+                found.push(Nondeterminism {
+                    query: name.to_query_name(),
+                    key,
+                });
+            }
+
+            first
+        })
+    }
+
     /// Looks up the given key within the query instance with the given name.
     ///
     /// If a value is found within the query, it is cloned and returned. If
@@ -343,32 +3154,246 @@ impl Database {
     /// # Errors
     ///
     /// If the given closure returns `Err`, this method will propagate the error
+    /// to the caller. Any outdated result which was cached for the key is
+    /// discarded, so the error isn't masked by the outdated result.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_query_result<K: Hash, T: QueryValue + Clone, E>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let id = name.query_id();
+        let hashed = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_result_by_id(id, hashed, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given name. See [`Database::execute_query_result`] and
+    /// [`Database::execute_query_by_key`].
+    ///
+    /// # Errors
+    ///
+    /// If the given closure returns `Err`, this method will propagate the error
     /// to the caller.
-    pub fn execute_query_result<K: Hash, T: Clone + 'static, E>(
+    pub fn execute_query_result_by_key<T: QueryValue + Clone, E>(
         &self,
-        name: &str,
+        name: &(impl QueryName + ?Sized),
+        key: ResultKey,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let id = name.query_id();
+        self.check_unnormalized(id).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_result_by_id(id, key, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given ID. See [`Database::execute_query_result`].
+    fn execute_query_result_by_id<T: QueryValue + Clone, E>(
+        &self,
+        id: QueryId,
+        hashed: ResultKey,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let (cached, _) = self
+            .lookup_cached::<T>(id, hashed)
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+
+        let (result, duration) = self.compute(id, hashed, f).unwrap_or_else(|err| panic!("{err}"));
+
+        self.store_result(id, hashed, result, duration)
+    }
+
+    /// Stores the given result of the query with the given ID, if it was
+    /// computed successfully.
+    ///
+    /// Otherwise, any previous result for the key is discarded, since it was
+    /// found to be outdated when it was recomputed.
+    fn store_result<T: QueryValue + Clone, E>(
+        &self,
+        id: QueryId,
+        key: ResultKey,
+        result: Result<T, E>,
+        duration: Duration,
+    ) -> Result<T, E> {
+        let mut query = self.query_mut_by_id(id);
+
+        match result {
+            Ok(value) => Ok(query.store(key, value, Some(duration))),
+            Err(error) => {
+                query.remove(key);
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query_result`], except that errors of
+    /// the database, such as a cached result of another type than [`T`], are
+    /// returned instead of panicking. The error is converted into the error
+    /// type of `f`, so both kinds of errors can be handled at once.
+    ///
+    /// # Errors
+    ///
+    /// If the given closure returns `Err`, this method will propagate the error
+    /// to the caller. If the query contains a result for the key, which is not
+    /// of type [`T`], returns [`QueryError::TypeMismatch`], converted into
+    /// [`E`].
+    pub fn try_execute_query_result<K: Hash, T: QueryValue + Clone, E: From<QueryError>>(
+        &self,
+        name: &(impl QueryName + ?Sized),
         key: &K,
         f: impl FnOnce() -> Result<T, E>,
     ) -> Result<T, E> {
-        let cached = if self.caching_enabled() {
-            self.query(name).get::<K, T>(key).cloned()
-        } else {
-            None
-        };
+        let id = name.query_id();
+        let hashed = self.hash_unnormalized(id, key)?;
+
+        let (cached, _) = self.lookup_cached::<T>(id, hashed)?;
+
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+
+        let (result, duration) = self.compute(id, hashed, f)?;
+
+        self.store_result(id, hashed, result, duration)
+    }
+
+    /// Looks up the given key within the query instance with the given name.
+    ///
+    /// Behaves like [`Database::execute_query_result`], except that errors
+    /// returned from `f` are cached as well, according to the given `policy`.
+    /// Once the query has failed more times for the key than allowed by the
+    /// policy, the cached error is cloned and returned without invoking `f`.
+    ///
+    /// # Errors
+    ///
+    /// If the given closure returns `Err`, or a cached error is found, this
+    /// method will return the error to the caller.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same cases as [`Database::execute_query`].
+    pub fn execute_query_result_cached<K: Hash, T: QueryValue + Clone, E: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        policy: ErrorPolicy,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let id = name.query_id();
+        let hashed = self.hash_unnormalized(id, key).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_result_cached_by_id(id, hashed, policy, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given name. See [`Database::execute_query_result_cached`] and
+    /// [`Database::execute_query_by_key`].
+    ///
+    /// # Errors
+    ///
+    /// If the given closure returns `Err`, or a cached error is found, this
+    /// method will return the error to the caller.
+    pub fn execute_query_result_cached_by_key<T: QueryValue + Clone, E: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: ResultKey,
+        policy: ErrorPolicy,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let id = name.query_id();
+        self.check_unnormalized(id).unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_result_cached_by_id(id, key, policy, f)
+    }
+
+    /// Looks up the given, already hashed, key within the query instance with
+    /// the given ID. See [`Database::execute_query_result_cached`].
+    fn execute_query_result_cached_by_id<T: QueryValue + Clone, E: QueryValue + Clone>(
+        &self,
+        id: QueryId,
+        hashed: ResultKey,
+        policy: ErrorPolicy,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let (cached, _) = self
+            .lookup_cached::<T>(id, hashed)
+            .unwrap_or_else(|err| panic!("{err}"));
 
         if let Some(cached) = cached {
             return Ok(cached);
         }
 
-        f().inspect(|v| self.query_mut(name).insert::<K, T>(key, v.clone()))
+        {
+            let query = self.query_by_id(id);
+
+            if self.reuses_results(&query)
+                && let Some(error) = query.get_error_by_key::<E>(hashed, policy)
+            {
+                return Err(error.clone());
+            }
+        }
+
+        let (result, duration) = self.compute(id, hashed, f).unwrap_or_else(|err| panic!("{err}"));
+
+        match result {
+            Ok(value) => {
+                let mut query = self.query_mut_by_id(id);
+
+                query.errors.remove(&hashed);
+
+                Ok(query.store(hashed, value, Some(duration)))
+            }
+            Err(error) => {
+                let mut query = self.query_mut_by_id(id);
+
+                query.remove(hashed);
+                query.insert_error_by_key(hashed, error.clone());
+
+                Err(error)
+            }
+        }
     }
 }
 
 impl Default for Database {
     fn default() -> Self {
         Self {
-            enabled: RwLock::new(true),
+            enabled: AtomicBool::new(true),
+            cycles: AtomicU64::new(0),
+            strict: AtomicBool::new(false),
             inner: RwLock::new(DatabaseInner::default()),
+            stampedes: Mutex::new(None),
+            slow_queries: Mutex::new(None),
+            events: Arc::new(Mutex::new(None)),
+            nondeterminism: Mutex::new(None),
+            auditing: AtomicBool::new(false),
+            reentrancy: Mutex::new(None),
+            middleware: RwLock::new(Vec::new()),
+            memory_monitor: RwLock::new(None),
+            invalidation_rules: RwLock::new(Vec::new()),
+            dependencies: Mutex::new(DependencyGraph::default()),
+            observers: RwLock::new(Vec::new()),
+            batch: Mutex::new(Batch::default()),
+            commits: RwLock::new(()),
+            txns: AtomicUsize::new(0),
+            interactive: AtomicUsize::new(0),
+            key_labels: Mutex::new(None),
+            #[cfg(feature = "testing")]
+            forced_cycles: Mutex::new(HashSet::new()),
+            interners: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -379,3 +3404,21 @@ pub trait DatabaseContext {
     /// [`DatabaseContext`] implementation.
     fn db(&self) -> &Database;
 }
+
+impl DatabaseContext for Database {
+    fn db(&self) -> &Database {
+        self
+    }
+}
+
+/// A trait that provides access to multiple, named [`Database`] instances,
+/// such as databases with different lifetimes.
+pub trait MultiDatabaseContext {
+    /// Retrieves the instance of [`Database`] with the given name, which is
+    /// provided by the [`MultiDatabaseContext`] implementation.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if no database with the given name exists.
+    fn db_named(&self, name: &str) -> &Database;
+}
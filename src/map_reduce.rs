@@ -0,0 +1,106 @@
+use std::hash::Hash;
+
+#[cfg(all(feature = "rayon", feature = "sync"))]
+use rayon::prelude::*;
+
+use crate::{Database, QueryFlags, QueryId, QueryName, QueryValue, ResultKey};
+
+impl Database {
+    /// Evaluates the query with the name `map_query` for every key in `keys`
+    /// and folds the results into an aggregate, which is cached within the
+    /// query with the given name.
+    ///
+    /// Results of `map_query` which could not be found are computed by
+    /// invoking `map`. The aggregate is computed by folding the results, in
+    /// the order of `keys`, into `init` using `reduce`. The results are
+    /// evaluated while the aggregate is computed, so the aggregate depends on
+    /// each of them, and is verified and recomputed whenever any of them
+    /// change, like any other result. The aggregate is cached against `keys`.
+    ///
+    /// Both queries are created without any flags, if they don't exist.
+    pub fn map_reduce<K: Hash, T: QueryValue + Clone, R: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        map_query: &(impl QueryName + ?Sized),
+        keys: &[K],
+        map: impl Fn(&K) -> T,
+        init: R,
+        reduce: impl Fn(R, T) -> R,
+    ) -> R {
+        self.ensure_query_exists(name, QueryFlags::empty);
+        self.ensure_query_exists(map_query, QueryFlags::empty);
+
+        let map_id = map_query.query_id();
+        let hashed = self.hash_entries(map_id, keys);
+
+        self.reduce_entries(name, keys, || {
+            keys.iter()
+                .zip(&hashed)
+                .map(|(key, &hashed)| self.execute_query_by_id(map_id, hashed, || map(key)))
+                .fold(init, reduce)
+        })
+    }
+
+    /// Evaluates the query with the name `map_query` for every key in `keys`
+    /// in parallel, and folds the results into an aggregate. See
+    /// [`Database::map_reduce`].
+    ///
+    /// Results which are evaluated on other threads are not recorded as
+    /// dependencies of the aggregate while they're evaluated, so they're
+    /// recorded once all of them have been evaluated.
+    #[cfg(all(feature = "rayon", feature = "sync"))]
+    pub fn par_map_reduce<K: Hash + Sync, T: QueryValue + Clone, R: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        map_query: &(impl QueryName + ?Sized),
+        keys: &[K],
+        map: impl Fn(&K) -> T + Sync,
+        init: R,
+        reduce: impl Fn(R, T) -> R,
+    ) -> R {
+        self.ensure_query_exists(name, QueryFlags::empty);
+        self.ensure_query_exists(map_query, QueryFlags::empty);
+
+        let map_id = map_query.query_id();
+        let hashed = self.hash_entries(map_id, keys);
+
+        self.reduce_entries(name, keys, || {
+            let values = keys
+                .par_iter()
+                .zip(&hashed)
+                .map(|(key, &hashed)| self.execute_query_by_id(map_id, hashed, || map(key)))
+                .collect::<Vec<_>>();
+
+            self.record_dependencies(map_id, &hashed);
+
+            values.into_iter().fold(init, reduce)
+        })
+    }
+
+    /// Hashes the given keys of the query with the given ID.
+    fn hash_entries<K: Hash>(&self, map_query: QueryId, keys: &[K]) -> Vec<ResultKey> {
+        keys.iter()
+            .map(|key| {
+                self.hash_unnormalized(map_query, key)
+                    .unwrap_or_else(|err| panic!("{err}"))
+            })
+            .collect()
+    }
+
+    /// Gets the aggregate of the results for the given keys, which is cached
+    /// against the keys. If the aggregate is missing or outdated, it is
+    /// computed by invoking `fold`.
+    fn reduce_entries<K: Hash, R: QueryValue + Clone>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        keys: &[K],
+        fold: impl FnOnce() -> R,
+    ) -> R {
+        let query = name.query_id();
+        let key = self
+            .hash_unnormalized(query, &keys)
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        self.execute_query_by_id(query, key, fold)
+    }
+}
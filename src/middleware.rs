@@ -0,0 +1,94 @@
+use crate::{QueryResult, ResultKey};
+
+/// A computation of a query result, which is passed to every [`Middleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCall {
+    /// Name of the query which is being computed.
+    pub query: String,
+
+    /// Key of the result which is being computed.
+    pub key: ResultKey,
+}
+
+/// Function which wraps the computation of query results, registered using
+/// [`Database::add_middleware`].
+///
+/// A middleware is given the [`QueryCall`] which is being computed, along
+/// with a [`Next`], which runs the remaining middleware and eventually the
+/// query itself. A middleware must either return the [`Computed`] marker
+/// returned by [`Next::run`], or an error, such as
+/// [`QueryError::Cancelled`], to skip the computation. The error is reported
+/// to the caller which executed the query.
+///
+/// When the `sync` feature is enabled, middleware must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Database::add_middleware`]: crate::Database::add_middleware
+/// [`QueryError::Cancelled`]: crate::QueryError::Cancelled
+#[cfg(not(feature = "sync"))]
+pub trait Middleware: Fn(&QueryCall, Next<'_>) -> QueryResult<Computed> {}
+
+#[cfg(not(feature = "sync"))]
+impl<F: Fn(&QueryCall, Next<'_>) -> QueryResult<Computed>> Middleware for F {}
+
+/// Function which wraps the computation of query results, registered using
+/// [`Database::add_middleware`].
+///
+/// A middleware is given the [`QueryCall`] which is being computed, along
+/// with a [`Next`], which runs the remaining middleware and eventually the
+/// query itself. A middleware must either return the [`Computed`] marker
+/// returned by [`Next::run`], or an error, such as
+/// [`QueryError::Cancelled`], to skip the computation. The error is reported
+/// to the caller which executed the query.
+///
+/// When the `sync` feature is enabled, middleware must also be [`Send`] and
+/// [`Sync`].
+///
+/// [`Database::add_middleware`]: crate::Database::add_middleware
+/// [`QueryError::Cancelled`]: crate::QueryError::Cancelled
+#[cfg(feature = "sync")]
+pub trait Middleware: Fn(&QueryCall, Next<'_>) -> QueryResult<Computed> + Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<F: Fn(&QueryCall, Next<'_>) -> QueryResult<Computed> + Send + Sync> Middleware for F {}
+
+/// Marker which proves that a query was computed, as returned by
+/// [`Next::run`].
+///
+/// Since it can't be created in any other way, a middleware which returns
+/// successfully must have run the query.
+#[derive(Debug)]
+pub struct Computed(());
+
+/// The remainder of a middleware chain, including the computation of the
+/// query itself.
+pub struct Next<'a> {
+    call: &'a QueryCall,
+    chain: &'a [Box<dyn Middleware>],
+    compute: &'a mut dyn FnMut(),
+}
+
+impl<'a> Next<'a> {
+    /// Creates a new [`Next`], which runs the given chain of middleware
+    /// before invoking `compute`.
+    pub(crate) fn new(call: &'a QueryCall, chain: &'a [Box<dyn Middleware>], compute: &'a mut dyn FnMut()) -> Self {
+        Self { call, chain, compute }
+    }
+
+    /// Runs the remaining middleware, followed by the query itself.
+    ///
+    /// # Errors
+    ///
+    /// If any of the remaining middleware returns an error, the query is not
+    /// computed, and the error is returned.
+    pub fn run(self) -> QueryResult<Computed> {
+        match self.chain.split_first() {
+            Some((middleware, rest)) => middleware(self.call, Next::new(self.call, rest, self.compute)),
+            None => {
+                (self.compute)();
+
+                Ok(Computed(()))
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::OnceLock;
+
+use crate::{QueryId, QueryName};
+
+/// Name of a query, along with its [`QueryId`], which was computed ahead of
+/// time, so the name isn't hashed again whenever the query is executed.
+///
+/// Names of queries with a constant name can be prehashed at compile-time,
+/// using [`PrehashedName::from_static`]. Other names are prehashed once, and
+/// cached, using a [`QueryNameCache`].
+#[derive(Debug, Clone, Copy)]
+pub struct PrehashedName<'n, N: ?Sized> {
+    id: QueryId,
+    name: &'n N,
+}
+
+impl PrehashedName<'static, str> {
+    /// Creates a new [`PrehashedName`] from the given constant name.
+    pub const fn from_static(name: &'static str) -> Self {
+        Self {
+            id: QueryId::from_name(name),
+            name,
+        }
+    }
+}
+
+impl<N: QueryName + ?Sized> QueryName for PrehashedName<'_, N> {
+    #[inline]
+    fn query_id(&self) -> QueryId {
+        self.id
+    }
+
+    fn to_query_name(&self) -> String {
+        self.name.to_query_name()
+    }
+}
+
+/// Cache of the IDs of the names which are built from the same parts, such as
+/// the names of a method which are built from the type of its receiver.
+///
+/// Names are identified by the addresses of their parts, which are constant
+/// for every instance of a generic method, since they are given by
+/// [`std::any::type_name`], so the name is only hashed once per instance.
+/// The first name is looked up without any lock, since most methods only
+/// have a single instance. Names of other instances are looked up while the
+/// cache is read-locked.
+#[derive(Debug)]
+pub struct QueryNameCache {
+    first: OnceLock<Entry>,
+    others: parking_lot::RwLock<Vec<Entry>>,
+}
+
+/// Parts of a name, along with the ID of the name.
+type Entry = (Box<[&'static str]>, QueryId);
+
+impl QueryNameCache {
+    /// Creates a new, empty [`QueryNameCache`].
+    pub const fn new() -> Self {
+        Self {
+            first: OnceLock::new(),
+            others: parking_lot::RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Gets the name which is made up of the given parts, along with its ID,
+    /// which is only computed if the parts weren't given before.
+    #[inline]
+    pub fn get<'p>(&self, parts: &'p [&'static str]) -> PrehashedName<'p, [&'static str]> {
+        PrehashedName {
+            id: self.id_of(parts),
+            name: parts,
+        }
+    }
+
+    fn id_of(&self, parts: &[&'static str]) -> QueryId {
+        fn same(a: &[&str], b: &[&str]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| std::ptr::eq(*a, *b))
+        }
+
+        let (first, id) = self.first.get_or_init(|| (parts.into(), QueryId::from_parts(parts)));
+
+        if same(first, parts) {
+            return *id;
+        }
+
+        if let Some((_, id)) = self.others.read().iter().find(|(other, _)| same(other, parts)) {
+            return *id;
+        }
+
+        let mut others = self.others.write();
+
+        if let Some((_, id)) = others.iter().find(|(other, _)| same(other, parts)) {
+            return *id;
+        }
+
+        let id = QueryId::from_parts(parts);
+        others.push((parts.into(), id));
+
+        id
+    }
+}
+
+impl Default for QueryNameCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
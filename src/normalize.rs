@@ -0,0 +1,155 @@
+use std::any::Any;
+use std::hash::Hash;
+
+use crate::dependency::Node;
+use crate::{Database, DatabaseInner, KeyMap, Query, QueryError, QueryId, QueryResult, ResultKey};
+
+/// Type-erased normalization function, which returns [`None`] if the given
+/// key is not of the type expected by the normalizer.
+///
+/// Shared, so it can be copied into shards of the database.
+#[cfg(not(feature = "sync"))]
+type ErasedNormalizer = std::rc::Rc<dyn Fn(&dyn Any) -> Option<ResultKey>>;
+
+/// Type-erased normalization function, which returns [`None`] if the given
+/// key is not of the type expected by the normalizer.
+///
+/// Shared, so it can be copied into shards of the database.
+#[cfg(feature = "sync")]
+type ErasedNormalizer = std::sync::Arc<dyn Fn(&dyn Any) -> Option<ResultKey> + Send + Sync>;
+
+/// Function which maps keys of a query onto their canonical form, before
+/// they are hashed.
+#[derive(Clone)]
+pub(crate) struct KeyNormalizer {
+    normalize: ErasedNormalizer,
+}
+
+impl KeyNormalizer {
+    /// Creates a new [`KeyNormalizer`], which normalizes keys of type [`K`]
+    /// using `f`.
+    pub(crate) fn new<K: 'static, N: Hash>(f: impl KeyMap<K, N>) -> Self {
+        let normalize = move |key: &dyn Any| key.downcast_ref::<K>().map(|key| ResultKey::from_hashable(&f(key)));
+
+        Self {
+            normalize: ErasedNormalizer::from(Box::new(normalize) as Box<_>),
+        }
+    }
+
+    /// Hashes the normalized form of the given key, if it is of the type
+    /// expected by the normalizer.
+    #[inline]
+    pub(crate) fn hash(&self, key: &dyn Any) -> Option<ResultKey> {
+        (self.normalize)(key)
+    }
+}
+
+impl std::fmt::Debug for KeyNormalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyNormalizer").finish_non_exhaustive()
+    }
+}
+
+impl Query {
+    /// Sets the function which maps keys of type [`K`] onto their canonical
+    /// form before they are hashed, replacing any existing normalizer.
+    ///
+    /// Keys which normalize to the same value share a single cache entry,
+    /// such as paths with different separators, or identifiers which only
+    /// differ in case. Keys of any other type are hashed as-is.
+    ///
+    /// Normalizers are applied by every method which takes a key of a
+    /// `'static` type, such as [`QueryHandle`],
+    /// [`Database::execute_query_keyed`], [`Database::insert`],
+    /// [`Database::invalidate`] and [`Query::get`]. Keys given to
+    /// [`Database::execute_query`] and similar methods may borrow, so they
+    /// can't be normalized. Instead of silently missing the results which are
+    /// cached under the normalized key, these methods fail with
+    /// [`QueryError::Unnormalized`] for a query with a normalizer.
+    ///
+    /// [`QueryHandle`]: crate::QueryHandle
+    pub fn set_key_normalizer<K: 'static, N: Hash>(&mut self, f: impl KeyMap<K, N>) {
+        self.normalizer = Some(KeyNormalizer::new(f));
+    }
+
+    /// Removes the key normalizer of the query, if any.
+    pub fn clear_key_normalizer(&mut self) {
+        self.normalizer = None;
+    }
+
+    /// Hashes the given key into a [`ResultKey`], after normalizing it using
+    /// the normalizer of the query, if any. See
+    /// [`Query::set_key_normalizer`].
+    pub fn hash_key<K: Hash + 'static>(&self, key: &K) -> ResultKey {
+        self.normalizer
+            .as_ref()
+            .and_then(|normalizer| normalizer.hash(key))
+            .unwrap_or_else(|| ResultKey::from_hashable(key))
+    }
+}
+
+impl DatabaseInner {
+    /// Hashes the given key for the query with the given ID, after
+    /// normalizing it, and returns it as a node of the dependency graph. If
+    /// the query doesn't exist, the key is hashed as-is.
+    pub(crate) fn node<K: Hash + 'static>(&self, query: QueryId, key: &K) -> Node {
+        (self.resolve(query), self.hash_key(query, key))
+    }
+
+    /// Hashes the given key for the query with the given ID, after
+    /// normalizing it. If the query doesn't exist, the key is hashed as-is.
+    pub(crate) fn hash_key<K: Hash + 'static>(&self, query: QueryId, key: &K) -> ResultKey {
+        match self.get(query) {
+            Some(query) => query.hash_key(key),
+            None => ResultKey::from_hashable(key),
+        }
+    }
+}
+
+impl Database {
+    /// Sets the function which maps keys of the query with the given name
+    /// onto their canonical form before they are hashed. See
+    /// [`Query::set_key_normalizer`].
+    pub fn set_key_normalizer<K: 'static, N: Hash>(&self, name: &str, f: impl KeyMap<K, N>) {
+        self.query_mut(name).set_key_normalizer(f);
+    }
+
+    /// Hashes the given key for the query with the given ID, after
+    /// normalizing it. If the query doesn't exist, the key is hashed as-is.
+    pub(crate) fn hash_key<K: Hash + 'static>(&self, query: QueryId, key: &K) -> ResultKey {
+        self.read().hash_key(query, key)
+    }
+
+    /// Hashes the given key for the query with the given ID as-is, for
+    /// methods whose key type isn't known to be `'static`, so the key can't be
+    /// normalized.
+    ///
+    /// # Errors
+    ///
+    /// If the query has a key normalizer, returns [`QueryError::Unnormalized`],
+    /// since the key might not match the normalized key of its result. See
+    /// [`Query::set_key_normalizer`].
+    pub(crate) fn hash_unnormalized<K: Hash>(&self, query: QueryId, key: &K) -> QueryResult<ResultKey> {
+        self.check_unnormalized(query)?;
+
+        Ok(ResultKey::from_hashable(key))
+    }
+
+    /// Ensures that keys of the query with the given ID may be hashed as-is,
+    /// such as keys which were already hashed by the caller.
+    ///
+    /// # Errors
+    ///
+    /// If the query has a key normalizer, returns [`QueryError::Unnormalized`].
+    pub(crate) fn check_unnormalized(&self, query: QueryId) -> QueryResult<()> {
+        if let Some(query) = self.read().get(query)
+            && query.normalizer.is_some()
+        {
+            return Err(QueryError::Unnormalized {
+                query: query.name.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+
+use crate::sync::thread_local;
+use crate::{Database, ResultKey, Revision};
+
+/// Callback which is notified of changes to the results within a
+/// [`Database`], as registered using [`Database::add_observer`].
+///
+/// Observers are invoked after the database has been unlocked, so they may
+/// access the database themselves. When the `sync` feature is enabled,
+/// observers must also be [`Send`] and [`Sync`].
+#[cfg(not(feature = "sync"))]
+pub trait ChangeObserver: Fn(&ChangeSet) + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<F: Fn(&ChangeSet) + 'static> ChangeObserver for F {}
+
+/// Callback which is notified of changes to the results within a
+/// [`Database`], as registered using [`Database::add_observer`].
+///
+/// Observers are invoked after the database has been unlocked, so they may
+/// access the database themselves. When the `sync` feature is enabled,
+/// observers must also be [`Send`] and [`Sync`].
+#[cfg(feature = "sync")]
+pub trait ChangeObserver: Fn(&ChangeSet) + Send + Sync + 'static {}
+
+#[cfg(feature = "sync")]
+impl<F: Fn(&ChangeSet) + Send + Sync + 'static> ChangeObserver for F {}
+
+/// Set of results which were changed by a single mutation of a [`Database`],
+/// or by all mutations within a [`Database::batch`].
+///
+/// Results are listed in the order they were changed, so a result which was
+/// changed more than once within a batch is listed more than once.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangeSet {
+    /// Revision of the database after the changes were made.
+    pub revision: Revision,
+
+    /// Results which were inserted using [`Database::insert`], by query name
+    /// and key. Results which replaced a result with the same checksum, or an
+    /// equal result, are left out. See [`Query::enable_equality_checks`].
+    ///
+    /// [`Query::enable_equality_checks`]: crate::Query::enable_equality_checks
+    pub inserted: Vec<(String, ResultKey)>,
+
+    /// Results which were removed by invalidations, including results which
+    /// depend on an invalidated result, by query name and key.
+    pub invalidated: Vec<(String, ResultKey)>,
+
+    /// Names of queries which were cleared entirely.
+    pub cleared: Vec<String>,
+}
+
+impl ChangeSet {
+    /// Determines whether the set doesn't contain any changes.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.invalidated.is_empty() && self.cleared.is_empty()
+    }
+
+    /// Appends all changes of `other` to the set.
+    fn append(&mut self, other: ChangeSet) {
+        self.inserted.extend(other.inserted);
+        self.invalidated.extend(other.invalidated);
+        self.cleared.extend(other.cleared);
+    }
+}
+
+/// State of the batch which is currently open on a database, if any.
+#[derive(Debug, Default)]
+pub(crate) struct Batch {
+    /// Number of nested batches which are currently open.
+    depth: usize,
+
+    /// Changes which were made since the outermost batch was opened.
+    changes: ChangeSet,
+}
+
+/// Kind of lock on the commits of a database. See [`Database::lock_commits`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum CommitLock {
+    /// Lock of a [`Database::batch`].
+    Batch,
+
+    /// Lock of a [`Database::read_txn`].
+    Txn,
+}
+
+/// Batches and read transactions which are open on a thread, for a single
+/// database.
+#[derive(Debug, Default, Clone, Copy)]
+struct Held {
+    batches: usize,
+    txns: usize,
+}
+
+thread_local! {
+    /// Batches and read transactions which are open on this thread, per
+    /// database.
+    ///
+    /// Databases are identified by their address, like the stacks of active
+    /// queries. Entries are removed once nothing is held anymore.
+    static COMMITS: RefCell<HashMap<usize, Held>> = RefCell::new(HashMap::new());
+}
+
+/// Lock on the commits of a database, unless the lock was already held by
+/// the current thread.
+enum Commits<'db> {
+    Shared(#[allow(dead_code, reason = "only held")] RwLockReadGuard<'db, ()>),
+    Exclusive(#[allow(dead_code, reason = "only held")] RwLockWriteGuard<'db, ()>),
+}
+
+/// Releases the commits of a database when dropped. See
+/// [`Database::lock_commits`].
+pub(crate) struct CommitsGuard<'db> {
+    db: &'db Database,
+    kind: CommitLock,
+    _lock: Option<Commits<'db>>,
+}
+
+impl Drop for CommitsGuard<'_> {
+    fn drop(&mut self) {
+        let id = std::ptr::from_ref(self.db) as usize;
+
+        COMMITS.with(|commits| {
+            let mut commits = commits.borrow_mut();
+            let Some(held) = commits.get_mut(&id) else {
+                return;
+            };
+
+            match self.kind {
+                CommitLock::Batch => held.batches -= 1,
+                CommitLock::Txn => held.txns -= 1,
+            }
+
+            if held.batches == 0 && held.txns == 0 {
+                commits.remove(&id);
+            }
+        });
+
+        if matches!(self.kind, CommitLock::Txn) {
+            self.db.txns.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Closes the batch of a database when dropped, even if the batch unwinds.
+struct BatchGuard<'db> {
+    db: &'db Database,
+    commits: Option<CommitsGuard<'db>>,
+}
+
+impl Drop for BatchGuard<'_> {
+    fn drop(&mut self) {
+        let changes = self.db.end_batch();
+
+        // Released before observers are notified, so they may read the
+        // database through a transaction.
+        drop(self.commits.take());
+
+        if let Some(changes) = changes {
+            self.db.notify(&changes);
+        }
+    }
+}
+
+impl Database {
+    /// Adds an observer, which is notified with the [`ChangeSet`] of every
+    /// mutation of the database from now on.
+    ///
+    /// Mutations within a [`Database::batch`] are reported once, when the
+    /// outermost batch is closed.
+    pub fn add_observer(&self, observer: impl ChangeObserver) {
+        self.observers.write().push(Box::new(observer));
+    }
+
+    /// Removes all observers from the database.
+    pub fn clear_observers(&self) {
+        self.observers.write().clear();
+    }
+
+    /// Invokes `f` within a batch, which notifies observers once, with the
+    /// aggregate [`ChangeSet`] of all mutations made by `f`, instead of once
+    /// for every mutation.
+    ///
+    /// All mutations within the batch are committed as a single revision,
+    /// which becomes the current revision of the database once the batch
+    /// makes its first mutation, and is the revision of the reported change
+    /// set. Results which are computed within the batch are still ordered
+    /// before any later mutation within the batch, so they are never mistaken
+    /// for being up-to-date with it.
+    ///
+    /// This is useful when many inputs change at once, such as after
+    /// switching branches. Batches may be nested, in which case observers are
+    /// notified when the outermost batch is closed.
+    ///
+    /// A batch is not scoped to the calling thread: mutations which other
+    /// threads make while the batch is open are stamped with the revision of
+    /// the batch as well. Since mutations are reported after they are made,
+    /// mutations of other threads are reported as part of the batch if they
+    /// are reported while it is open, even if they were made just before it
+    /// was opened, and on their own otherwise.
+    ///
+    /// Batches on other threads wait for the batch to be closed, as do
+    /// transactions created using [`Database::read_txn`], so they observe
+    /// either all or none of the mutations within a batch. Transactions on
+    /// the same thread observe the mutations made so far. Opening a batch
+    /// within a transaction on the same thread panics, since the transaction
+    /// would otherwise wait for itself.
+    ///
+    /// If `f` panics, the batch is closed while unwinding, and observers are
+    /// notified of the mutations which were made before the panic, since
+    /// mutations are never rolled back.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is invoked within a [`Database::read_txn`]
+    /// on the same thread.
+    pub fn batch<R>(&self, f: impl FnOnce(&Self) -> R) -> R {
+        assert!(
+            !self.holds_txn(),
+            "a batch cannot be opened within a read transaction of the same database"
+        );
+
+        let commits = self.lock_commits(CommitLock::Batch);
+
+        {
+            let mut batch = self.batch.lock();
+            batch.depth += 1;
+
+            if batch.depth == 1 {
+                let mut inner = self.write();
+                inner.batch_revision = Some(inner.current_revision().next());
+            }
+        }
+
+        let _guard = BatchGuard {
+            db: self,
+            commits: Some(commits),
+        };
+
+        f(self)
+    }
+
+    /// Locks the commits of the database, exclusively for a batch and shared
+    /// for a read transaction, so transactions never observe part of a batch
+    /// on another thread.
+    ///
+    /// Batches and transactions which are already held by the current thread
+    /// don't lock the commits again, since the thread would otherwise wait
+    /// for itself.
+    pub(crate) fn lock_commits(&self, kind: CommitLock) -> CommitsGuard<'_> {
+        let id = std::ptr::from_ref(self) as usize;
+        let held = COMMITS.with(|commits| commits.borrow().get(&id).copied().unwrap_or_default());
+
+        let lock = if held.batches > 0 || held.txns > 0 {
+            None
+        } else {
+            Some(match kind {
+                CommitLock::Batch => Commits::Exclusive(self.commits.write()),
+                CommitLock::Txn => Commits::Shared(self.commits.read_recursive()),
+            })
+        };
+
+        if matches!(kind, CommitLock::Txn) {
+            self.txns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        COMMITS.with(|commits| {
+            let mut commits = commits.borrow_mut();
+            let held = commits.entry(id).or_default();
+
+            match kind {
+                CommitLock::Batch => held.batches += 1,
+                CommitLock::Txn => held.txns += 1,
+            }
+        });
+
+        CommitsGuard {
+            db: self,
+            kind,
+            _lock: lock,
+        }
+    }
+
+    /// Determines whether the current thread holds a read transaction of the
+    /// database. See [`Database::read_txn`].
+    #[inline]
+    pub(crate) fn holds_txn(&self) -> bool {
+        if self.txns.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+
+        let id = std::ptr::from_ref(self) as usize;
+
+        COMMITS.with(|commits| commits.borrow().get(&id).is_some_and(|held| held.txns > 0))
+    }
+
+    /// Closes the innermost batch of the database, and returns the changes
+    /// made within the batch, if it was the outermost batch.
+    fn end_batch(&self) -> Option<ChangeSet> {
+        let mut batch = self.batch.lock();
+        batch.depth -= 1;
+
+        if batch.depth > 0 {
+            return None;
+        }
+
+        let mut inner = self.write();
+        inner.batch_revision = None;
+
+        let mut changes = std::mem::take(&mut batch.changes);
+        changes.revision = inner.current_revision();
+
+        Some(changes)
+    }
+
+    /// Reports the given changes to all observers, or adds them to the open
+    /// batch, if any.
+    ///
+    /// The database must not be locked when this method is invoked, since
+    /// observers may access the database themselves.
+    pub(crate) fn publish(&self, mut changes: ChangeSet) {
+        if changes.is_empty() {
+            return;
+        }
+
+        self.log_changes(&changes);
+
+        {
+            let mut batch = self.batch.lock();
+
+            if batch.depth > 0 {
+                batch.changes.append(changes);
+                return;
+            }
+        }
+
+        changes.revision = self.current_revision();
+        self.notify(&changes);
+    }
+
+    /// Invokes all observers with the given changes, if there are any.
+    fn notify(&self, changes: &ChangeSet) {
+        if changes.is_empty() {
+            return;
+        }
+
+        for observer in self.observers.read().iter() {
+            observer(changes);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::hash::Hash;
+
+use crate::{Database, Query, QueryFlags, QueryId, ResultKey};
+
+impl Query {
+    /// Pins the result with the given key, so it is never discarded by any
+    /// eviction policy, such as [`Database::release_soft_entries`].
+    ///
+    /// Keys can be pinned before their result is computed. Pinned results can
+    /// still be invalidated or cleared explicitly, since they would otherwise
+    /// become outdated.
+    pub fn pin<K: Hash + 'static>(&mut self, key: &K) {
+        self.pinned.insert(self.hash_key(key));
+    }
+
+    /// Unpins the result with the given key. See [`Query::pin`].
+    pub fn unpin<K: Hash + 'static>(&mut self, key: &K) {
+        self.pinned.remove(&self.hash_key(key));
+    }
+
+    /// Determines whether the result with the given key is pinned, either by
+    /// itself or since the query has [`QueryFlags::PINNED`].
+    pub fn is_pinned<K: Hash + 'static>(&self, key: &K) -> bool {
+        self.is_pinned_by_key(self.hash_key(key))
+    }
+
+    /// Determines whether the result with the given, already hashed, key is
+    /// pinned. See [`Query::is_pinned`].
+    pub(crate) fn is_pinned_by_key(&self, key: ResultKey) -> bool {
+        self.flags.contains(QueryFlags::PINNED) || self.pinned.contains(&key)
+    }
+
+    /// Gets the keys of all results within the query which were pinned by
+    /// themselves, excluding those pinned by [`QueryFlags::PINNED`].
+    pub fn pinned(&self) -> impl Iterator<Item = ResultKey> + '_ {
+        self.pinned.iter().copied()
+    }
+}
+
+impl Database {
+    /// Pins the results with the given keys within the query with the given
+    /// name, such as the files which are currently open in an editor. See
+    /// [`Query::pin`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the query does not exist.
+    pub fn pin<'k, K: Hash + 'static>(&self, name: &str, keys: impl IntoIterator<Item = &'k K>) {
+        self.with_pins(name, |query| keys.into_iter().for_each(|key| query.pin(key)));
+    }
+
+    /// Unpins the results with the given keys within the query with the given
+    /// name. See [`Query::unpin`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the query does not exist.
+    pub fn unpin<'k, K: Hash + 'static>(&self, name: &str, keys: impl IntoIterator<Item = &'k K>) {
+        self.with_pins(name, |query| keys.into_iter().for_each(|key| query.unpin(key)));
+    }
+
+    /// Unpins all results within the query with the given name.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the query does not exist.
+    pub fn unpin_all(&self, name: &str) {
+        self.with_pins(name, |query| query.pinned.clear());
+    }
+
+    /// Invokes `f` with the query with the given name, to change its pinned
+    /// results.
+    ///
+    /// Pinning doesn't change any results, so unlike
+    /// [`Database::query_mut`], this doesn't bump the revision.
+    fn with_pins(&self, name: &str, f: impl FnOnce(&mut Query)) {
+        let mut inner = self.write();
+        let id = inner.resolve(QueryId::from_name(name));
+
+        let query = inner
+            .queries
+            .get_mut(&id)
+            .unwrap_or_else(|| panic!("query `{name}` does not exist"));
+
+        f(query);
+    }
+}
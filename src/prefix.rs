@@ -0,0 +1,79 @@
+use std::any::Any;
+
+use crate::{Query, QueryValue, ResultKey};
+
+/// Prefix of keys of type [`K`], such as the first elements of a tuple key,
+/// which is used to find all results of a query with the same leading key
+/// elements. See [`Database::clear_prefix`].
+///
+/// This trait is implemented for every leading sub-tuple of tuples of up to
+/// six elements, so `(file_id,)` is a prefix of `(file_id, name, kind)`.
+///
+/// [`Database::clear_prefix`]: crate::Database::clear_prefix
+pub trait KeyPrefix<K> {
+    /// Determines whether the given key starts with this prefix.
+    fn is_prefix_of(&self, key: &K) -> bool;
+}
+
+macro_rules! impl_key_prefix {
+    ($(($($t:ident),+) => ($($p:ident $i:tt),+);)+) => {
+        $(
+            impl<$($t),+> KeyPrefix<($($t,)+)> for ($($p,)+)
+            where
+                $($p: PartialEq),+
+            {
+                #[inline]
+                fn is_prefix_of(&self, key: &($($t,)+)) -> bool {
+                    $(self.$i == key.$i)&&+
+                }
+            }
+        )+
+    };
+}
+
+impl_key_prefix! {
+    (A) => (A 0);
+    (A, B) => (A 0);
+    (A, B) => (A 0, B 1);
+    (A, B, C) => (A 0);
+    (A, B, C) => (A 0, B 1);
+    (A, B, C) => (A 0, B 1, C 2);
+    (A, B, C, D) => (A 0);
+    (A, B, C, D) => (A 0, B 1);
+    (A, B, C, D) => (A 0, B 1, C 2);
+    (A, B, C, D) => (A 0, B 1, C 2, D 3);
+    (A, B, C, D, E) => (A 0);
+    (A, B, C, D, E) => (A 0, B 1);
+    (A, B, C, D, E) => (A 0, B 1, C 2);
+    (A, B, C, D, E) => (A 0, B 1, C 2, D 3);
+    (A, B, C, D, E) => (A 0, B 1, C 2, D 3, E 4);
+    (A, B, C, D, E, F) => (A 0);
+    (A, B, C, D, E, F) => (A 0, B 1);
+    (A, B, C, D, E, F) => (A 0, B 1, C 2);
+    (A, B, C, D, E, F) => (A 0, B 1, C 2, D 3);
+    (A, B, C, D, E, F) => (A 0, B 1, C 2, D 3, E 4);
+    (A, B, C, D, E, F) => (A 0, B 1, C 2, D 3, E 4, F 5);
+}
+
+impl Query {
+    /// Gets an iterator over the original keys of all results within the
+    /// query, which were retained on insertion, are of type [`K`] and start
+    /// with the given prefix. See [`Query::keys_typed`].
+    pub fn keys_with_prefix<'a, K: QueryValue>(&'a self, prefix: &'a impl KeyPrefix<K>) -> impl Iterator<Item = &'a K> {
+        self.keys_typed::<K>().filter(|key| prefix.is_prefix_of(key))
+    }
+
+    /// Gets the hashed and original keys of all results within the query,
+    /// which start with the given prefix. See [`Query::keys_with_prefix`].
+    pub(crate) fn prefixed_results<K: QueryValue + Clone>(&self, prefix: &impl KeyPrefix<K>) -> Vec<(ResultKey, K)> {
+        self.results
+            .iter()
+            .filter_map(|(key, slot)| {
+                let original: &dyn Any = &*slot.key.as_ref()?.0;
+                let original = original.downcast_ref::<K>()?;
+
+                prefix.is_prefix_of(original).then(|| (*key, original.clone()))
+            })
+            .collect()
+    }
+}
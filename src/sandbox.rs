@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use crate::{ActiveQuery, Database, Query, QueryError, QueryId, ResultKey};
+
+/// Limits which are enforced on every execution of a query, such as a query
+/// registered by an untrusted compiler plugin. See [`Query::set_sandbox`].
+///
+/// Violations are reported as [`QueryError::LimitExceeded`], so a misbehaving
+/// query fails instead of hanging or exhausting the host.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sandbox {
+    /// Maximum time which computing a single result may take.
+    ///
+    /// Since queries cannot be interrupted, the limit is checked whenever the
+    /// query executes another query, and once the result has been computed.
+    pub max_duration: Option<Duration>,
+
+    /// Maximum number of executions of the query which may be nested within
+    /// each other on a single thread, including the outermost one.
+    pub max_depth: Option<usize>,
+
+    /// Maximum number of results which may be cached for the query.
+    pub max_entries: Option<usize>,
+}
+
+/// Limit of a [`Sandbox`] which was exceeded, as reported by
+/// [`QueryError::LimitExceeded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Computing the result took longer than the given duration.
+    Duration(Duration),
+
+    /// The query was nested within itself more often than the given depth.
+    Depth(usize),
+
+    /// The query already caches the given number of results.
+    Entries(usize),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Duration(limit) => write!(f, "time limit of {limit:?}"),
+            Limit::Depth(limit) => write!(f, "recursion depth limit of {limit}"),
+            Limit::Entries(limit) => write!(f, "entry limit of {limit}"),
+        }
+    }
+}
+
+/// Point in time at which an execution of a sandboxed query exceeds its time
+/// limit.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    pub at: Instant,
+    pub limit: Duration,
+}
+
+/// Sandbox of a query, along with the state of the query which is needed to
+/// check its limits before it is executed.
+pub(crate) struct SandboxState {
+    pub sandbox: Sandbox,
+    pub entries: usize,
+    pub cached: bool,
+}
+
+impl SandboxState {
+    /// Gets the state of the sandbox of the given query, if it has one.
+    pub fn of(query: &Query, key: ResultKey) -> Option<Self> {
+        Some(Self {
+            sandbox: query.sandbox?,
+            entries: query.results.len(),
+            cached: query.results.contains_key(&key),
+        })
+    }
+
+    /// Checks the limits of the sandbox, before the query with the given ID
+    /// is executed on top of the given stack of executing queries.
+    ///
+    /// Returns the deadline of the execution, if the query has a time limit.
+    pub fn check(&self, query: QueryId, stack: &[ActiveQuery]) -> Result<Option<Deadline>, Limit> {
+        if let Some(limit) = self.sandbox.max_depth
+            && stack.iter().filter(|active| active.query == query).count() >= limit
+        {
+            return Err(Limit::Depth(limit));
+        }
+
+        if let Some(limit) = self.sandbox.max_entries
+            && !self.cached
+            && self.entries >= limit
+        {
+            return Err(Limit::Entries(limit));
+        }
+
+        Ok(self.sandbox.max_duration.map(|limit| Deadline {
+            at: Instant::now() + limit,
+            limit,
+        }))
+    }
+}
+
+impl Query {
+    /// Sets the limits which are enforced on every execution of the query,
+    /// replacing any existing sandbox.
+    ///
+    /// Limits are enforced on all methods which execute the query, but only
+    /// those which return a [`QueryResult`] report violations as errors,
+    /// such as [`Database::try_execute_query_result`]. All other methods
+    /// panic, like they do for cycles.
+    ///
+    /// [`QueryResult`]: crate::QueryResult
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = Some(sandbox);
+    }
+
+    /// Removes the sandbox of the query, if any.
+    pub fn clear_sandbox(&mut self) {
+        self.sandbox = None;
+    }
+}
+
+impl Database {
+    /// Sets the limits which are enforced on every execution of the query
+    /// with the given name. See [`Query::set_sandbox`].
+    pub fn set_sandbox(&self, name: &str, sandbox: Sandbox) {
+        self.query_mut(name).set_sandbox(sandbox);
+    }
+
+    /// Creates a [`QueryError::LimitExceeded`] for the result with the given
+    /// key within the query with the given ID.
+    pub(crate) fn limit_exceeded(&self, query: QueryId, key: ResultKey, limit: Limit) -> QueryError {
+        let query = self
+            .read()
+            .get(query)
+            .map(|query| query.name.clone())
+            .unwrap_or_default();
+
+        QueryError::LimitExceeded { query, key, limit }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+
+use crate::{ChangeSet, Database, DatabaseInner, QueryId};
+
+impl Database {
+    /// Creates a new, empty [`Database`] with the same queries and aliases as
+    /// this database, which can be handed to a worker thread.
+    ///
+    /// Queries keep their flags and settings, such as their callbacks, key
+    /// normalizers and sandboxes, but none of their results or statistics.
+    ///
+    /// Once the worker is done, its results can be merged back into this
+    /// database using [`Database::merge`], so that workers don't contend on
+    /// the locks of a single database.
+    pub fn shard(&self) -> Database {
+        let inner = self.read();
+
+        let shard = Database::new();
+
+        if !self.caching_enabled() {
+            shard.disable_caching();
+        }
+
+        {
+            let mut shard_inner = shard.write();
+
+            for (id, query) in &inner.queries {
+                shard_inner.queries.insert(*id, query.clone_config());
+            }
+
+            shard_inner.aliases.clone_from(&inner.aliases);
+        }
+
+        shard
+    }
+
+    /// Creates a new [`Database`], which contains clones of the results of
+    /// only the queries with the given names, such as for a worker which only
+    /// needs the results of the front-end queries.
+    ///
+    /// Aliases of the given queries, and dependencies between their results,
+    /// are cloned as well. Queries keep their flags and settings, like
+    /// [`Database::shard`], but statistics and most settings of this database
+    /// itself are not cloned.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any of the given queries does not exist.
+    pub fn clone_subset(&self, names: &[&str]) -> Database {
+        let inner = self.read();
+        let graph = self.dependencies.lock();
+
+        for name in names {
+            assert!(inner.query_exists(name), "query `{name}` does not exist");
+        }
+
+        let ids = names
+            .iter()
+            .map(|name| inner.resolve(QueryId::from_name(name)))
+            .collect::<HashSet<_>>();
+
+        let mut queries = IndexMap::new();
+
+        // Queries are cloned in the order of this database, rather than the
+        // order they were given in.
+        for (id, query) in inner.queries.iter().filter(|(id, _)| ids.contains(id)) {
+            let mut clone = query.clone_config();
+            clone.results.clone_from(&query.results);
+            clone.errors.clone_from(&query.errors);
+            clone.generation = query.generation;
+            clone.revision = query.revision;
+            clone.tick = query.tick;
+            clone.changed_at = query.changed_at;
+            clone.pinned.clone_from(&query.pinned);
+
+            queries.insert(*id, clone);
+        }
+
+        let subset = Database::new();
+
+        if !self.caching_enabled() {
+            subset.disable_caching();
+        }
+
+        {
+            let mut subset_inner = subset.write();
+
+            subset_inner.queries = queries;
+            subset_inner.revision = inner.current_revision();
+            subset_inner.ticks = inner.ticks;
+            subset_inner.aliases = inner
+                .aliases
+                .iter()
+                .filter(|(_, target)| ids.contains(target))
+                .map(|(alias, target)| (*alias, *target))
+                .collect();
+        }
+
+        *subset.dependencies.lock() = graph.subset(&ids);
+
+        subset
+    }
+
+    /// Merges all results of the given shard into this database.
+    ///
+    /// Queries which don't exist within this database are added, with the
+    /// settings of the shard. Results which already exist within this
+    /// database are kept, while all other results are inserted as if they
+    /// were computed within this database, along with their dependencies.
+    /// Observers are notified of the inserted results.
+    pub fn merge(&self, shard: Database) {
+        let shard_graph = shard.dependencies.into_inner();
+        let shard: DatabaseInner = shard.inner.into_inner();
+
+        let inserted = {
+            let mut inner = self.write();
+            let mut graph = self.dependencies.lock();
+
+            inner.bump_revision();
+            let revision = inner.revision;
+            let tick = inner.next_tick();
+
+            let mut inserted = Vec::new();
+            let mut nodes = HashSet::new();
+
+            for (id, mut shard_query) in shard.queries {
+                let query = inner.queries.entry(id).or_insert_with(|| shard_query.clone_config());
+
+                query.revision = revision;
+                query.tick = tick;
+
+                for (key, slot) in shard_query.results.drain(..) {
+                    if query.results.contains_key(&key) {
+                        continue;
+                    }
+
+                    query.results.insert(key, slot);
+                    query.restamp(key);
+
+                    inserted.push((query.name.clone(), key));
+                    nodes.insert((id, key));
+                }
+            }
+
+            for (alias, target) in shard.aliases {
+                inner.aliases.entry(alias).or_insert(target);
+            }
+
+            graph.merge(&shard_graph, &nodes);
+
+            // Results which depend on the merged results were computed
+            // without them, so they may be outdated.
+            for node in &nodes {
+                graph.mark_dirty(*node);
+            }
+
+            // Dirty results of this database may have changed since the shard
+            // was created, so merged results which depend on them are dirty
+            // as well. Dirty results aren't marked again when they change, so
+            // their new dependents must be marked here.
+            let outdated = nodes
+                .iter()
+                .filter(|node| graph.dependencies(**node).any(|dependency| graph.is_dirty(dependency)))
+                .copied()
+                .collect::<Vec<_>>();
+
+            for node in outdated {
+                graph.mark_outdated(node);
+            }
+
+            inserted
+        };
+
+        self.publish(ChangeSet {
+            inserted,
+            ..ChangeSet::default()
+        });
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Database, QueryId, QueryName};
+
+/// Source location at which a query with a constant name is defined, which is
+/// registered statically by `#[cached_query]`. See [`__define_query`].
+#[doc(hidden)]
+#[repr(C)]
+pub struct QueryDefinition {
+    pub name: &'static str,
+    pub site: &'static str,
+}
+
+/// Source location at which a query is defined, whose name is only known at
+/// runtime, such as the name of a method, which includes the type of its
+/// receiver. The query is registered once, when it's first executed, and
+/// again whenever it's executed with another name, such as by another
+/// instance of a generic function. See [`__define_query`].
+#[doc(hidden)]
+pub struct DefinitionSite {
+    site: &'static str,
+
+    /// ID of the query which was most recently registered for this site, plus
+    /// one, or zero if none was registered yet.
+    registered: AtomicUsize,
+}
+
+impl DefinitionSite {
+    pub const fn new(site: &'static str) -> Self {
+        Self {
+            site,
+            registered: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn register(&self, name: &(impl QueryName + ?Sized)) {
+        let id = name.query_id();
+
+        if self.registered.load(Ordering::Relaxed) == id.0.wrapping_add(1) {
+            return;
+        }
+
+        let mut registered = REGISTERED.lock();
+
+        if !registered
+            .iter()
+            .any(|(other, _, site)| *other == id && *site == self.site)
+        {
+            registered.push((id, name.to_query_name(), self.site));
+        }
+
+        self.registered.store(id.0.wrapping_add(1), Ordering::Relaxed);
+    }
+}
+
+/// Queries which were registered at runtime, along with their names and the
+/// locations at which they're defined.
+static REGISTERED: parking_lot::Mutex<Vec<(QueryId, String, &'static str)>> = parking_lot::Mutex::new(Vec::new());
+
+/// Registers the location at which a query is defined, so two definitions
+/// which map to the same query name can be reported. See
+/// [`Database::check_query_names`].
+///
+/// Queries with a constant name are registered statically, where the linker
+/// supports it, so they're known before any query is executed. Otherwise,
+/// queries are registered when they're first executed.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __define_query {
+    (static $name:expr, $site:expr) => {{
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+        {
+            #[used]
+            #[unsafe(link_section = "lume_architect_queries")]
+            static DEFINITION: $crate::QueryDefinition = $crate::QueryDefinition {
+                name: $name,
+                site: $site,
+            };
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+        {
+            static DEFINITION: $crate::DefinitionSite = $crate::DefinitionSite::new($site);
+            DEFINITION.register($name);
+        }
+    }};
+    ($name:expr, $site:expr) => {{
+        static DEFINITION: $crate::DefinitionSite = $crate::DefinitionSite::new($site);
+        DEFINITION.register($name);
+    }};
+}
+
+/// Definitions which were registered statically, by placing them within a
+/// section of their own, whose bounds are defined by the linker.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn static_definitions() -> &'static [QueryDefinition] {
+    // The linker only defines the bounds of sections which exist, so the
+    // section must exist even if no queries are registered.
+    #[used]
+    #[unsafe(link_section = "lume_architect_queries")]
+    static EMPTY: [QueryDefinition; 0] = [];
+
+    unsafe extern "Rust" {
+        #[link_name = "__start_lume_architect_queries"]
+        static START: QueryDefinition;
+
+        #[link_name = "__stop_lume_architect_queries"]
+        static STOP: QueryDefinition;
+    }
+
+    // SAFETY: the section only contains definitions, which are placed next to
+    // each other, since their size is a multiple of their alignment.
+    unsafe {
+        let start = &raw const START;
+        let stop = &raw const STOP;
+
+        std::slice::from_raw_parts(start, stop.offset_from_unsigned(start))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn static_definitions() -> &'static [QueryDefinition] {
+    &[]
+}
+
+/// Query which is defined at more than one location, as reported by
+/// [`Database::check_query_names`].
+///
+/// Every definition shares the results of the other definitions, so each of
+/// them may return the results of another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateDefinition {
+    /// Name of the query.
+    pub query: String,
+
+    /// Locations at which the query is defined, sorted.
+    pub sites: Vec<&'static str>,
+}
+
+impl Display for DuplicateDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query `{}` is defined at ", self.query)?;
+
+        for (idx, site) in self.sites.iter().enumerate() {
+            match idx {
+                0 => {}
+                idx if idx + 1 == self.sites.len() => write!(f, " and ")?,
+                _ => write!(f, ", ")?,
+            }
+
+            write!(f, "`{site}`")?;
+        }
+
+        write!(f, ", which would share their results")
+    }
+}
+
+/// Groups the given definitions by query, and returns the queries which are
+/// defined at more than one location, sorted by name.
+fn duplicates<'a>(definitions: impl IntoIterator<Item = (QueryId, &'a str, &'static str)>) -> Vec<DuplicateDefinition> {
+    let mut queries = HashMap::<QueryId, DuplicateDefinition>::new();
+
+    for (id, name, site) in definitions {
+        let query = queries.entry(id).or_insert_with(|| DuplicateDefinition {
+            query: name.to_string(),
+            sites: Vec::new(),
+        });
+
+        if !query.sites.contains(&site) {
+            query.sites.push(site);
+        }
+    }
+
+    let mut duplicates = queries
+        .into_values()
+        .filter(|query| query.sites.len() > 1)
+        .map(|mut query| {
+            query.sites.sort_unstable();
+            query
+        })
+        .collect::<Vec<_>>();
+
+    duplicates.sort_by(|a, b| a.query.cmp(&b.query));
+
+    duplicates
+}
+
+impl Database {
+    /// Gets all queries which are defined at more than one location by
+    /// `#[cached_query]`, along with their locations, sorted by name.
+    ///
+    /// Definitions are only registered in debug builds. Queries with a
+    /// constant name, such as free functions, are registered statically on
+    /// platforms whose linker supports it, so they are reported before they
+    /// are executed. Other queries, such as methods, whose names include the
+    /// type of their receiver, are registered when they're first executed, so
+    /// they are only reported once both definitions have been executed.
+    ///
+    /// Duplicates are not reported while executing queries, so this should be
+    /// called explicitly, such as at the end of a test suite.
+    ///
+    /// Aliases of this database are resolved, so definitions of a query and
+    /// of an alias of the query are reported as well.
+    pub fn check_query_names(&self) -> Vec<DuplicateDefinition> {
+        let inner = self.read();
+        let registered = REGISTERED.lock();
+
+        let definitions = static_definitions()
+            .iter()
+            .map(|definition| (definition.name.query_id(), definition.name, definition.site))
+            .chain(registered.iter().map(|(id, name, site)| (*id, name.as_str(), *site)))
+            .map(|(id, name, site)| (inner.resolve(id), name, site));
+
+        duplicates(definitions)
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::{Database, Query, QueryId};
+
+/// Counters of a single [`Query`], which are updated while the query is
+/// executed.
+#[derive(Debug, Default)]
+pub(crate) struct QueryCounters {
+    /// Number of lookups which were served from the cache.
+    pub hits: AtomicU64,
+
+    /// Number of lookups which had to compute the result.
+    pub misses: AtomicU64,
+
+    /// Number of results which were discarded by the database, such as soft
+    /// results under memory pressure.
+    pub evictions: AtomicU64,
+
+    /// Histogram of the time it took to compute results.
+    pub durations: DurationHistogram,
+}
+
+impl QueryCounters {
+    /// Increments the given counter by `count`.
+    #[inline]
+    pub fn add(counter: &AtomicU64, count: u64) {
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+/// Histogram of durations, with buckets of exponentially increasing size.
+///
+/// Bucket `i` holds all durations of less than `2^i` nanoseconds, which
+/// didn't fit into any prior bucket. Percentiles are reported as the upper
+/// bound of the bucket they fall into, so they are accurate within a factor
+/// of two.
+#[derive(Debug)]
+pub(crate) struct DurationHistogram {
+    buckets: [AtomicU64; 65],
+    max: AtomicU64,
+}
+
+impl Default for DurationHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl DurationHistogram {
+    /// Records the given duration in the histogram.
+    pub fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - nanos.leading_zeros()) as usize;
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Gets a summary of all durations recorded in the histogram.
+    pub fn summary(&self) -> DurationStats {
+        let counts = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+
+        let count = counts.iter().sum::<u64>();
+        let max = self.max.load(Ordering::Relaxed);
+
+        // Gets the upper bound of the bucket which contains the given
+        // percentile of all recorded durations.
+        let percentile = |percent: u64| {
+            let rank = (count * percent).div_ceil(100).max(1);
+            let mut seen = 0;
+
+            for (bucket, bucket_count) in counts.iter().enumerate() {
+                seen += bucket_count;
+
+                if seen >= rank {
+                    let upper = 1_u64.checked_shl(bucket as u32).map_or(u64::MAX, |bound| bound - 1);
+
+                    return Duration::from_nanos(upper.min(max));
+                }
+            }
+
+            Duration::ZERO
+        };
+
+        DurationStats {
+            count,
+            p50: percentile(50),
+            p95: percentile(95),
+            max: Duration::from_nanos(max),
+        }
+    }
+}
+
+/// Summary of the time it took to compute the results of a query, as part of
+/// [`QueryStats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DurationStats {
+    /// Number of results which were computed.
+    pub count: u64,
+
+    /// Median duration, accurate within a factor of two.
+    pub p50: Duration,
+
+    /// 95th percentile duration, accurate within a factor of two.
+    pub p95: Duration,
+
+    /// Longest duration.
+    pub max: Duration,
+}
+
+/// Summary of a single [`Query`], as part of [`DatabaseStats`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QueryStats {
+    /// Name of the query.
+    pub name: String,
+
+    /// Number of results stored within the query.
+    pub entries: usize,
+
+    /// Number of errors cached within the query.
+    pub errors: usize,
+
+    /// Number of lookups which were served from the cache.
+    pub hits: u64,
+
+    /// Number of lookups which had to compute the result.
+    pub misses: u64,
+
+    /// Number of results which were discarded by the database, such as soft
+    /// results under memory pressure.
+    pub evictions: u64,
+
+    /// Number of pinned keys, whether their results are cached or not. See
+    /// [`Database::pin`].
+    pub pinned: usize,
+
+    /// Estimate of the number of bytes allocated by the query, excluding the
+    /// results themselves.
+    pub estimated_bytes: usize,
+
+    /// Summary of the time it took to compute the results of the query.
+    pub durations: DurationStats,
+}
+
+impl QueryStats {
+    /// Creates a summary of the given query.
+    fn of(query: &Query) -> Self {
+        Self {
+            name: query.name.clone(),
+            entries: query.results.len(),
+            errors: query.errors.len(),
+            hits: query.counters.hits.load(Ordering::Relaxed),
+            misses: query.counters.misses.load(Ordering::Relaxed),
+            evictions: query.counters.evictions.load(Ordering::Relaxed),
+            pinned: query.pinned.len(),
+            estimated_bytes: query.allocated_bytes(),
+            durations: query.counters.durations.summary(),
+        }
+    }
+}
+
+/// Summary of an entire [`Database`], as returned by [`Database::stats`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DatabaseStats {
+    /// Number of queries within the database.
+    pub queries: usize,
+
+    /// Number of results stored across all queries.
+    pub entries: usize,
+
+    /// Number of lookups which were served from the cache, across all
+    /// queries.
+    pub hits: u64,
+
+    /// Number of lookups which had to compute the result, across all queries.
+    pub misses: u64,
+
+    /// Number of cycles which were detected while executing queries.
+    pub cycles: u64,
+
+    /// Number of results which were discarded by the database, across all
+    /// queries.
+    pub evictions: u64,
+
+    /// Number of pinned keys, across all queries.
+    pub pinned: usize,
+
+    /// Estimate of the number of bytes allocated by the database, excluding
+    /// the results themselves.
+    pub estimated_bytes: usize,
+
+    /// Summaries of each query within the database, sorted by name.
+    pub per_query: Vec<QueryStats>,
+}
+
+impl DatabaseStats {
+    /// Gets the summary of the query with the given name, if it exists.
+    pub fn query(&self, name: &str) -> Option<&QueryStats> {
+        self.per_query.iter().find(|query| query.name == name)
+    }
+}
+
+impl Database {
+    /// Gets a summary of the database, including totals across all queries
+    /// and a breakdown per query.
+    pub fn stats(&self) -> DatabaseStats {
+        let inner = self.read();
+
+        let mut per_query = inner.queries.values().map(QueryStats::of).collect::<Vec<_>>();
+        per_query.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let map_bytes = inner.queries.capacity() * size_of::<(QueryId, Query)>()
+            + inner.aliases.capacity() * size_of::<(QueryId, QueryId)>();
+
+        DatabaseStats {
+            queries: per_query.len(),
+            entries: per_query.iter().map(|query| query.entries).sum(),
+            hits: per_query.iter().map(|query| query.hits).sum(),
+            misses: per_query.iter().map(|query| query.misses).sum(),
+            cycles: self.cycles.load(Ordering::Relaxed),
+            evictions: per_query.iter().map(|query| query.evictions).sum(),
+            pinned: per_query.iter().map(|query| query.pinned).sum(),
+            estimated_bytes: map_bytes + per_query.iter().map(|query| query.estimated_bytes).sum::<usize>(),
+            per_query,
+        }
+    }
+}
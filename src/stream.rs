@@ -0,0 +1,131 @@
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::{Database, QueryName, QueryValue};
+
+/// Iterator which produces the items of a streaming query, as executed by
+/// [`Database::execute_stream`].
+///
+/// By default, any `'static` iterator can be used. When the `sync` feature is
+/// enabled, iterators must also be [`Send`].
+#[cfg(not(feature = "sync"))]
+pub trait StreamSource<T>: Iterator<Item = T> + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<T, I: Iterator<Item = T> + 'static> StreamSource<T> for I {}
+
+/// Iterator which produces the items of a streaming query, as executed by
+/// [`Database::execute_stream`].
+///
+/// By default, any `'static` iterator can be used. When the `sync` feature is
+/// enabled, iterators must also be [`Send`].
+#[cfg(feature = "sync")]
+pub trait StreamSource<T>: Iterator<Item = T> + Send + 'static {}
+
+#[cfg(feature = "sync")]
+impl<T, I: Iterator<Item = T> + Send + 'static> StreamSource<T> for I {}
+
+/// Items of a streaming query, which have been produced so far, along with
+/// the iterator producing the remaining items.
+struct StreamState<T> {
+    items: Vec<T>,
+
+    /// Iterator producing the remaining items, until it is exhausted.
+    source: Option<Box<dyn StreamSource<T>>>,
+}
+
+/// Iterator over the items of a streaming query, which is computed
+/// incrementally as it is pulled.
+///
+/// Every stream returned for the same key shares the items which have already
+/// been produced, so the underlying iterator is only advanced once for every
+/// item, regardless of how many streams are pulled.
+pub struct QueryStream<T> {
+    state: Arc<Mutex<StreamState<T>>>,
+
+    /// Index of the next item which is returned by this stream.
+    position: usize,
+}
+
+impl<T> QueryStream<T> {
+    /// Creates a new [`QueryStream`], which produces the items of `source`.
+    #[allow(
+        clippy::arc_with_non_send_sync,
+        reason = "the state is only shared between threads with the `sync` feature"
+    )]
+    fn new(source: impl StreamSource<T>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(StreamState {
+                items: Vec::new(),
+                source: Some(Box::new(source)),
+            })),
+            position: 0,
+        }
+    }
+}
+
+impl<T: Clone> Iterator for QueryStream<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.state.lock();
+
+        if self.position == state.items.len() {
+            match state.source.as_mut()?.next() {
+                Some(item) => state.items.push(item),
+                None => {
+                    state.source = None;
+                    return None;
+                }
+            }
+        }
+
+        let item = state.items[self.position].clone();
+        self.position += 1;
+
+        Some(item)
+    }
+}
+
+impl<T> Clone for QueryStream<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+            position: self.position,
+        }
+    }
+}
+
+impl Database {
+    /// Looks up the given key within the streaming query instance with the
+    /// given name.
+    ///
+    /// If the key could not be found within the instance, `f` is invoked to
+    /// create an iterator, which is only advanced as the returned
+    /// [`QueryStream`] is pulled. Produced items are cached, so later streams
+    /// for the same key replay them before advancing the iterator further.
+    ///
+    /// This is useful for queries where the caller often only needs the first
+    /// few items, such as finding references to a symbol.
+    ///
+    /// # Dependencies
+    ///
+    /// Dependencies of the stream are only recorded while `f` is invoked,
+    /// since the iterator is advanced after the query has been executed,
+    /// whenever the stream is pulled. Any query executed by the iterator
+    /// itself, such as through a shared handle to the database, is recorded
+    /// as a dependency of the query which pulls the stream, if any, rather
+    /// than of the stream. Changes to such results don't affect the cached
+    /// items, so results which the items are derived from should be
+    /// executed within `f`, and moved into the iterator, or declared using
+    /// [`Database::declare_dependency`].
+    pub fn execute_stream<K: Hash, T: QueryValue + Clone, I: StreamSource<T>>(
+        &self,
+        name: &(impl QueryName + ?Sized),
+        key: &K,
+        f: impl FnOnce() -> I,
+    ) -> QueryStream<T> {
+        self.execute_query(name, key, || QueryStream::new(f()))
+    }
+}
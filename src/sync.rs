@@ -0,0 +1,205 @@
+//! Locks and atomics which guard the state of the database.
+//!
+//! When compiled with `--cfg loom`, the mutexes and atomics are those of
+//! [`loom`], so the coordination between threads, such as between batches
+//! and transactions, can be model-checked across all interleavings of
+//! threads. Read-write locks stay those of [`parking_lot`], since their
+//! guards are mapped and returned to callers, which [`loom`] doesn't
+//! support, but every acquisition of them is made visible to [`loom`].
+//!
+//! With the `testing` and `sync` features, every acquisition of a lock is a
+//! point at which a [`Scheduler`] may switch threads. Otherwise, the locks
+//! are those of [`parking_lot`].
+//!
+//! [`Scheduler`]: crate::Scheduler
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+#[cfg(not(loom))]
+pub(crate) use std::thread_local;
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+#[cfg(loom)]
+pub(crate) use loom::thread_local;
+#[cfg(not(any(loom, all(feature = "testing", feature = "sync"))))]
+pub(crate) use parking_lot::{Mutex, RwLock};
+
+#[cfg(loom)]
+pub(crate) use self::model::RwLock;
+#[cfg(all(not(loom), feature = "testing", feature = "sync"))]
+pub(crate) use self::model::{Mutex, RwLock};
+#[cfg(loom)]
+pub(crate) use self::modeled::Mutex;
+
+/// Mutex of [`loom`], with the subset of the interface of [`parking_lot`]
+/// which is used by the database.
+#[cfg(loom)]
+mod modeled {
+    use loom::sync::MutexGuard;
+
+    pub(crate) struct Mutex<T>(loom::sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(loom::sync::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap()
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            self.0.try_lock().ok()
+        }
+
+        pub(crate) fn into_inner(self) -> T {
+            self.0.into_inner().unwrap()
+        }
+    }
+}
+
+/// Locks of [`parking_lot`], which yield to [`loom`] or to a [`Scheduler`]
+/// instead of blocking the thread, with the subset of their interface which
+/// is used by the database.
+///
+/// The guards are those of [`parking_lot`], so they can be mapped as usual.
+///
+/// [`Scheduler`]: crate::Scheduler
+#[cfg(any(loom, all(feature = "testing", feature = "sync")))]
+mod model {
+    #[cfg(not(loom))]
+    use parking_lot::MutexGuard;
+    use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+
+    /// Counter of attempts to acquire a lock, whose updates are visible to
+    /// [`loom`].
+    #[cfg(loom)]
+    type Attempts = loom::sync::atomic::AtomicUsize;
+
+    #[cfg(not(loom))]
+    type Attempts = ();
+
+    /// Makes an attempt to acquire a lock visible to [`loom`], so it explores
+    /// the interleavings of threads which use the same lock.
+    ///
+    /// Threads which fail to acquire the lock yield to other threads, since
+    /// all threads of a model are run on a single thread, which must never
+    /// block.
+    #[cfg(loom)]
+    fn acquire<G>(attempts: &Attempts, mut attempt: impl FnMut() -> Option<G>, _block: impl FnOnce() -> G) -> G {
+        use loom::sync::atomic::Ordering;
+
+        loop {
+            attempts.fetch_add(1, Ordering::SeqCst);
+
+            if let Some(guard) = attempt() {
+                return guard;
+            }
+
+            loom::thread::yield_now();
+        }
+    }
+
+    /// Acquires a lock, switching threads beforehand if the current thread is
+    /// run by a [`Scheduler`].
+    ///
+    /// Threads which fail to acquire the lock are switched out, since the
+    /// threads of a scheduler are run one at a time, so a thread which blocks
+    /// would never be woken up. Threads which aren't run by a scheduler block
+    /// as usual.
+    ///
+    /// [`Scheduler`]: crate::Scheduler
+    #[cfg(not(loom))]
+    fn acquire<G>(_attempts: &Attempts, mut attempt: impl FnMut() -> Option<G>, block: impl FnOnce() -> G) -> G {
+        use crate::testing::schedule;
+
+        if !schedule::is_scheduled() {
+            return block();
+        }
+
+        schedule::switch(false);
+
+        loop {
+            if let Some(guard) = attempt() {
+                schedule::acquired();
+
+                return guard;
+            }
+
+            schedule::switch(true);
+        }
+    }
+
+    #[cfg(not(loom))]
+    pub(crate) struct Mutex<T> {
+        attempts: Attempts,
+        lock: parking_lot::Mutex<T>,
+    }
+
+    #[cfg(not(loom))]
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                attempts: Attempts::default(),
+                lock: parking_lot::Mutex::new(value),
+            }
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            acquire(&self.attempts, || self.lock.try_lock(), || self.lock.lock())
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            self.lock.try_lock()
+        }
+
+        pub(crate) fn into_inner(self) -> T {
+            self.lock.into_inner()
+        }
+    }
+
+    pub(crate) struct RwLock<T> {
+        attempts: Attempts,
+        lock: parking_lot::RwLock<T>,
+    }
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                attempts: Attempts::default(),
+                lock: parking_lot::RwLock::new(value),
+            }
+        }
+
+        pub(crate) fn read(&self) -> RwLockReadGuard<'_, T> {
+            acquire(&self.attempts, || self.lock.try_read(), || self.lock.read())
+        }
+
+        pub(crate) fn read_recursive(&self) -> RwLockReadGuard<'_, T> {
+            acquire(
+                &self.attempts,
+                || self.lock.try_read_recursive(),
+                || self.lock.read_recursive(),
+            )
+        }
+
+        pub(crate) fn write(&self) -> RwLockWriteGuard<'_, T> {
+            acquire(&self.attempts, || self.lock.try_write(), || self.lock.write())
+        }
+
+        #[cfg(not(feature = "sync"))]
+        pub(crate) fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+            self.lock.try_read()
+        }
+
+        #[cfg(not(feature = "sync"))]
+        pub(crate) fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+            self.lock.try_write()
+        }
+
+        pub(crate) fn into_inner(self) -> T {
+            self.lock.into_inner()
+        }
+    }
+}
@@ -0,0 +1,113 @@
+mod coherence;
+#[cfg(feature = "sync")]
+pub(crate) mod schedule;
+
+use std::hash::Hash;
+use std::sync::atomic::Ordering;
+
+pub use self::coherence::{CoherenceError, Operation};
+#[cfg(feature = "sync")]
+pub use self::schedule::Scheduler;
+use crate::{Database, EventKind, QueryCounters, QueryError, QueryId, ResultKey};
+
+/// Hooks for simulating conditions within the database, meant for testing
+/// code which embeds it.
+impl Database {
+    /// Forces the next execution of the query with the given name to miss the
+    /// cache for the given key, as if the result had never been computed.
+    ///
+    /// Any cached result and cached error for the key are discarded. If the
+    /// query does not exist, this method does nothing.
+    pub fn force_miss<K: Hash + 'static>(&self, name: &str, key: &K) {
+        let id = QueryId::from_name(name);
+        let key = self.hash_key(id, key);
+
+        let mut inner = self.write();
+
+        if inner.get(id).is_none() {
+            return;
+        }
+
+        let query = inner.query_mut_by_id(id);
+        query.remove(key);
+        query.errors.remove(&key);
+    }
+
+    /// Forces the next execution of the query with the given name to fail
+    /// with [`QueryError::Cycle`] for the given key, as if the query had
+    /// executed itself with the same key.
+    ///
+    /// This allows the error recovery of code which embeds the database to
+    /// be tested, without contriving a real cycle. The result is forced to
+    /// miss the cache as well, see [`Database::force_miss`], so the cycle is
+    /// detected when the query is executed. The cycle is reported like a real
+    /// one, so it is counted by [`Database::stats`].
+    pub fn force_cycle<K: Hash + 'static>(&self, name: &str, key: &K) {
+        self.force_miss(name, key);
+
+        let id = self.read().resolve(QueryId::from_name(name));
+        let key = self.hash_key(id, key);
+
+        self.forced_cycles.lock().insert((id, key));
+    }
+
+    /// Evicts the result with the given key from the query with the given
+    /// name, as if the database had evicted it to reclaim memory.
+    ///
+    /// Unlike [`Database::force_miss`], the eviction is counted by
+    /// [`Database::stats`] and logged as [`EventKind::Evicted`], and cached
+    /// errors are kept. Pinned results are never evicted.
+    ///
+    /// Returns whether a result was evicted.
+    pub fn force_evict<K: Hash + 'static>(&self, name: &str, key: &K) -> bool {
+        let id = QueryId::from_name(name);
+        let key = self.hash_key(id, key);
+
+        let evicted = {
+            let mut inner = self.write();
+
+            let evictable = inner
+                .get(id)
+                .is_some_and(|query| query.results.contains_key(&key) && !query.is_pinned_by_key(key));
+
+            if evictable {
+                let query = inner.query_mut_by_id(id);
+                query.remove(key);
+
+                QueryCounters::add(&query.counters.evictions, 1);
+            }
+
+            evictable
+        };
+
+        if evicted {
+            self.log_event(id, Some(key), EventKind::Evicted);
+        }
+
+        evicted
+    }
+
+    /// Takes the forced cycle of the given result, if any, and returns the
+    /// error it should fail with. See [`Database::force_cycle`].
+    pub(crate) fn take_forced_cycle(&self, query: QueryId, key: ResultKey) -> Option<QueryError> {
+        if !self.forced_cycles.lock().remove(&(query, key)) {
+            return None;
+        }
+
+        self.cycles.fetch_add(1, Ordering::Relaxed);
+        self.log_event(query, Some(key), EventKind::Cycle);
+
+        let name = self
+            .read()
+            .get(query)
+            .map(|query| query.name.clone())
+            .unwrap_or_default();
+
+        Some(QueryError::Cycle {
+            query: name.clone(),
+            key,
+            label: self.key_label_by_id(query, key),
+            path: vec![(name, key)],
+        })
+    }
+}
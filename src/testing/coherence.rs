@@ -0,0 +1,348 @@
+use crate::{Database, QueryFlags};
+
+/// Number of inputs which are used by [`Database::check_coherence`].
+const INPUTS: u8 = 8;
+
+const INPUT: &str = "coherence::input";
+const SUM: &str = "coherence::sum";
+const TOTAL: &str = "coherence::total";
+const CHUNKS: &str = "coherence::chunks";
+const REDUCED: &str = "coherence::reduced";
+
+/// Operation which is applied to a database by
+/// [`Database::check_coherence`].
+///
+/// Indices are wrapped around the number of inputs and derived queries, so
+/// any value is valid. This allows operations to be generated by property
+/// testing frameworks, as well as by [`Operation::random_sequence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Inserts the given value for the input with the given index, using
+    /// [`Database::insert`].
+    Insert { input: u8, value: u64 },
+
+    /// Inserts the given value for `count` consecutive inputs, starting at
+    /// the input with the given index, within a single [`Database::batch`].
+    InsertBatch { input: u8, value: u64, count: u8 },
+
+    /// Invalidates the input with the given index, using
+    /// [`Database::invalidate`].
+    Invalidate { input: u8 },
+
+    /// Clears all inputs, using [`Database::clear`].
+    ClearInputs,
+
+    /// Clears all results, using [`Database::clear_all`].
+    ClearAll,
+
+    /// Executes the derived query with the given index, and compares its
+    /// result with the result of recomputing it from scratch.
+    Execute { derived: u8 },
+
+    /// Executes the chunked query with the given index, using
+    /// [`Database::execute_chunked`], and compares the sum of its chunks with
+    /// the result of recomputing it from scratch.
+    ExecuteChunked { derived: u8 },
+
+    /// Invalidates the chunk with the given index, of the chunked query with
+    /// the given index, using [`Database::invalidate_chunk`].
+    InvalidateChunk { derived: u8, chunk: u8 },
+
+    /// Folds the intermediate results which the derived query with the given
+    /// index reads, using [`Database::map_reduce`], and compares the
+    /// aggregate with the result of recomputing it from scratch.
+    MapReduce { derived: u8 },
+
+    /// Invalidates the intermediate result with the given index, using
+    /// [`Database::clear_prefix`].
+    ClearPrefix { derived: u8 },
+
+    /// Evicts the intermediate result with the given index, using
+    /// [`Database::force_evict`].
+    ForceEvict { derived: u8 },
+
+    /// Discards all intermediate results, whose query has
+    /// [`QueryFlags::SOFT`], using [`Database::release_soft_entries`].
+    ReleaseSoft,
+
+    /// Executes the derived query with the given index within a shard, which
+    /// holds the same inputs, and compares its result with the result of
+    /// recomputing it from scratch, before merging the shard back using
+    /// [`Database::merge`]. See [`Database::shard`].
+    Merge { derived: u8 },
+}
+
+impl Operation {
+    /// Generates a pseudo-random sequence of operations of the given length,
+    /// which is the same for every run with the same seed.
+    pub fn random_sequence(seed: u64, len: usize) -> Vec<Operation> {
+        // xorshift64*, which must not be seeded with zero.
+        let mut state = seed | 1;
+        let mut next = || {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+
+            state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        };
+
+        (0..len)
+            .map(|_| {
+                let value = next();
+                let index = (value >> 8) as u8;
+
+                match value % 24 {
+                    0..=4 => Operation::Insert {
+                        input: index,
+                        value: value >> 32,
+                    },
+                    5..=6 => Operation::Invalidate { input: index },
+                    7 => Operation::ClearInputs,
+                    8 => Operation::ClearAll,
+                    9 => Operation::InsertBatch {
+                        input: index,
+                        value: value >> 32,
+                        count: (value >> 16) as u8,
+                    },
+                    10 => Operation::InvalidateChunk {
+                        derived: index,
+                        chunk: (value >> 16) as u8,
+                    },
+                    11 => Operation::ClearPrefix { derived: index },
+                    12 => Operation::ForceEvict { derived: index },
+                    13 => Operation::ReleaseSoft,
+                    14 => Operation::Merge { derived: index },
+                    15 => Operation::MapReduce { derived: index },
+                    16..=17 => Operation::ExecuteChunked { derived: index },
+                    _ => Operation::Execute { derived: index },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Result which differed from the result of recomputing it from scratch, as
+/// reported by [`Database::check_coherence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoherenceError {
+    /// Index of the operation which returned the result.
+    pub step: usize,
+
+    /// Operation which returned the result.
+    pub operation: Operation,
+
+    /// Result of recomputing the query from scratch.
+    pub expected: u64,
+
+    /// Result returned by the database.
+    pub found: u64,
+}
+
+impl std::fmt::Display for CoherenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "incoherent result at step {} ({:?}): expected {}, found {}",
+            self.step, self.operation, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for CoherenceError {}
+
+/// Reads the input with the given index, which defaults to zero.
+fn input(db: &Database, index: u8) -> u64 {
+    db.execute_query(INPUT, &index, || 0_u64)
+}
+
+/// Computes the intermediate result with the given index from scratch, which
+/// reads two consecutive inputs.
+fn compute_sum(db: &Database, index: u8) -> u64 {
+    input(db, index).wrapping_add(input(db, (index + 1) % INPUTS))
+}
+
+/// Gets the intermediate result with the given index, whose key is retained,
+/// so it can be matched by [`Database::clear_prefix`].
+fn sum(db: &Database, index: u8) -> u64 {
+    db.execute_query_keyed(SUM, &(index,), || compute_sum(db, index))
+}
+
+/// Indices of the intermediate results which the derived result with the
+/// given index reads.
+fn sums_of(index: u8) -> [u8; 2] {
+    [index, (index + 1) % INPUTS]
+}
+
+/// Gets the derived result with the given index.
+fn total(db: &Database, index: u8) -> u64 {
+    db.execute_query(TOTAL, &index, || {
+        sums_of(index)
+            .into_iter()
+            .fold(0_u64, |acc, index| acc.wrapping_add(sum(db, index)))
+    })
+}
+
+/// Gets the derived result with the given index, which is stored in one
+/// chunk for each intermediate result it reads.
+fn chunked(db: &Database, index: u8) -> u64 {
+    let sums = sums_of(index);
+
+    db.execute_chunked(CHUNKS, &index, sums.len(), |chunk| vec![sum(db, sums[chunk])])
+        .into_iter()
+        .fold(0, u64::wrapping_add)
+}
+
+/// Gets the derived result with the given index, as the aggregate of the
+/// intermediate results it reads.
+fn reduced(db: &Database, index: u8) -> u64 {
+    let keys = sums_of(index).map(|index| (index,));
+
+    db.map_reduce(
+        REDUCED,
+        SUM,
+        &keys,
+        |&(index,)| compute_sum(db, index),
+        0,
+        u64::wrapping_add,
+    )
+}
+
+/// Computes the derived result with the given index from the model of the
+/// inputs.
+fn expected(inputs: &[u64; INPUTS as usize], index: u8) -> u64 {
+    let model = |offset: u8| inputs[((index + offset) % INPUTS) as usize];
+
+    model(0)
+        .wrapping_add(model(1))
+        .wrapping_add(model(1))
+        .wrapping_add(model(2))
+}
+
+impl Database {
+    /// Applies the given operations to a set of test queries within the
+    /// database, and checks that every executed result matches the result of
+    /// recomputing it from scratch.
+    ///
+    /// The test queries read from a set of inputs, both directly and through
+    /// intermediate queries, so the dependencies between them are recorded as
+    /// usual. Derived results are computed as single results, as chunks, and
+    /// as aggregates of the intermediate results. Since the settings of the
+    /// database apply to the test queries as well, this can be used to check
+    /// that a configured database never returns outdated results. The test
+    /// queries are prefixed with `coherence::`, and are cleared before the
+    /// operations are applied. The query of the intermediate results is
+    /// given [`QueryFlags::SOFT`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CoherenceError`] for the first result which didn't match
+    /// the recomputed result.
+    pub fn check_coherence(&self, operations: impl IntoIterator<Item = Operation>) -> Result<(), CoherenceError> {
+        for name in [INPUT, SUM, TOTAL, CHUNKS, REDUCED] {
+            self.ensure_query_exists(name, QueryFlags::empty);
+            self.clear(name);
+        }
+
+        self.ensure_query_exists(SUM, || QueryFlags::SOFT);
+
+        // Model of the inputs, which absent inputs default to.
+        let mut inputs = [0_u64; INPUTS as usize];
+
+        for (step, operation) in operations.into_iter().enumerate() {
+            let executed = match operation {
+                Operation::Insert { input, value } => {
+                    let input = input % INPUTS;
+
+                    inputs[input as usize] = value;
+                    self.insert(INPUT, &input, value);
+
+                    None
+                }
+                Operation::InsertBatch { input, value, count } => {
+                    self.batch(|db| {
+                        for offset in 0..count % INPUTS {
+                            let input = (input % INPUTS + offset) % INPUTS;
+
+                            inputs[input as usize] = value;
+                            db.insert(INPUT, &input, value);
+                        }
+                    });
+
+                    None
+                }
+                Operation::Invalidate { input } => {
+                    let input = input % INPUTS;
+
+                    inputs[input as usize] = 0;
+                    self.invalidate(INPUT, &input);
+
+                    None
+                }
+                Operation::ClearInputs => {
+                    inputs = [0; INPUTS as usize];
+                    self.clear(INPUT);
+
+                    None
+                }
+                Operation::ClearAll => {
+                    inputs = [0; INPUTS as usize];
+                    self.clear_all();
+
+                    None
+                }
+                Operation::InvalidateChunk { derived, chunk } => {
+                    self.invalidate_chunk(CHUNKS, &(derived % INPUTS), usize::from(chunk % 2));
+
+                    None
+                }
+                Operation::ClearPrefix { derived } => {
+                    self.clear_prefix::<(u8,)>(SUM, &(derived % INPUTS,));
+
+                    None
+                }
+                Operation::ForceEvict { derived } => {
+                    self.force_evict(SUM, &(derived % INPUTS,));
+
+                    None
+                }
+                Operation::ReleaseSoft => {
+                    self.release_soft_entries();
+
+                    None
+                }
+                Operation::Execute { derived } => Some((derived % INPUTS, total(self, derived % INPUTS))),
+                Operation::ExecuteChunked { derived } => Some((derived % INPUTS, chunked(self, derived % INPUTS))),
+                Operation::MapReduce { derived } => Some((derived % INPUTS, reduced(self, derived % INPUTS))),
+                Operation::Merge { derived } => {
+                    let shard = self.shard();
+
+                    for (input, value) in (0..INPUTS).zip(inputs) {
+                        shard.insert(INPUT, &input, value);
+                    }
+
+                    let found = total(&shard, derived % INPUTS);
+                    self.merge(shard);
+
+                    Some((derived % INPUTS, found))
+                }
+            };
+
+            let Some((index, found)) = executed else {
+                continue;
+            };
+
+            let expected = expected(&inputs, index);
+
+            if found != expected {
+                return Err(CoherenceError {
+                    step,
+                    operation,
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
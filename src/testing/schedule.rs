@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+/// Deterministic scheduler, which runs threads one at a time on behalf of a
+/// single logical thread, and switches between them whenever they acquire a
+/// lock of a [`Database`], in an order determined by a seed.
+///
+/// Running the same threads with the same seed produces the same
+/// interleaving, so a race condition which was reported with a seed can be
+/// reproduced, and debugged, as often as needed. Trying many seeds explores
+/// different interleavings, although not exhaustively like `loom`.
+///
+/// Threads which fail to acquire a lock are switched out, until the thread
+/// holding the lock has released it. If every thread which hasn't finished
+/// failed to acquire a lock since any lock was last acquired, the threads
+/// wait for each other, or for themselves, so the scheduler panics, instead
+/// of deadlocking.
+///
+/// [`Database`]: crate::Database
+#[derive(Debug, Clone, Copy)]
+pub struct Scheduler {
+    seed: u64,
+}
+
+/// State which is shared between all threads of a [`Scheduler`].
+struct Shared {
+    state: Mutex<State>,
+    turn: Condvar,
+}
+
+struct State {
+    /// Index of the thread which is currently running.
+    current: usize,
+
+    /// Whether each thread has finished running.
+    finished: Vec<bool>,
+
+    /// Whether each thread failed to acquire a lock since any lock was last
+    /// acquired.
+    blocked: Vec<bool>,
+
+    /// State of the random number generator, which picks the next thread.
+    rng: u64,
+}
+
+impl State {
+    /// Picks the next thread to run, other than `excluded`, using the random
+    /// number generator. Returns [`None`] if no such thread is unfinished.
+    fn pick(&mut self, excluded: Option<usize>) -> Option<usize> {
+        let runnable = (0..self.finished.len())
+            .filter(|index| !self.finished[*index] && Some(*index) != excluded)
+            .collect::<Vec<_>>();
+
+        if runnable.is_empty() {
+            return None;
+        }
+
+        // xorshift64*, which must not be seeded with zero.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+
+        let value = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D);
+
+        Some(runnable[(value % runnable.len() as u64) as usize])
+    }
+}
+
+thread_local! {
+    /// Scheduler which runs the current thread, if any, along with the index
+    /// of the thread within it.
+    static SCHEDULED: RefCell<Option<(Arc<Shared>, usize)>> = const { RefCell::new(None) };
+}
+
+impl Scheduler {
+    /// Creates a new [`Scheduler`], which picks threads in the order
+    /// determined by the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Runs all given threads to completion, one at a time.
+    ///
+    /// # Panics
+    ///
+    /// If any of the threads panics, the first panic is resumed once all
+    /// other threads have finished.
+    pub fn run<'a>(&self, threads: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+        let mut state = State {
+            current: 0,
+            finished: vec![false; threads.len()],
+            blocked: vec![false; threads.len()],
+            rng: self.seed | 1,
+        };
+
+        state.current = state.pick(None).unwrap_or_default();
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(state),
+            turn: Condvar::new(),
+        });
+
+        let panics = std::thread::scope(|scope| {
+            let handles = threads
+                .into_iter()
+                .enumerate()
+                .map(|(index, thread)| {
+                    let shared = Arc::clone(&shared);
+
+                    scope.spawn(move || {
+                        SCHEDULED.with(|scheduled| *scheduled.borrow_mut() = Some((Arc::clone(&shared), index)));
+
+                        wait_for_turn(&shared, index);
+                        let result = catch_unwind(AssertUnwindSafe(thread));
+                        finish(&shared, index);
+
+                        SCHEDULED.with(|scheduled| scheduled.borrow_mut().take());
+
+                        result
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap().err())
+                .collect::<Vec<_>>()
+        });
+
+        if let Some(panic) = panics.into_iter().next() {
+            resume_unwind(panic);
+        }
+    }
+}
+
+/// Blocks the current thread until it is the running thread of the
+/// scheduler.
+fn wait_for_turn(shared: &Shared, index: usize) {
+    let mut state = shared.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+    while state.current != index {
+        state = shared.turn.wait(state).unwrap_or_else(PoisonError::into_inner);
+    }
+}
+
+/// Marks the thread with the given index as finished, and runs the next
+/// thread.
+fn finish(shared: &Shared, index: usize) {
+    let mut state = shared.state.lock().unwrap_or_else(PoisonError::into_inner);
+    state.finished[index] = true;
+
+    if let Some(next) = state.pick(None) {
+        state.current = next;
+    }
+
+    shared.turn.notify_all();
+}
+
+/// Determines whether the current thread is run by a [`Scheduler`].
+#[inline]
+pub(crate) fn is_scheduled() -> bool {
+    SCHEDULED.with(|scheduled| scheduled.borrow().is_some())
+}
+
+/// Switches to the next thread picked by the scheduler of the current
+/// thread, which may be the current thread itself, unless the current thread
+/// is `blocked` on a lock.
+///
+/// # Panics
+///
+/// Panics if the current thread is `blocked`, while all other threads have
+/// either finished or are blocked themselves, since the threads would wait
+/// for each other forever.
+pub(crate) fn switch(blocked: bool) {
+    let Some((shared, index)) = SCHEDULED.with(|scheduled| scheduled.borrow().clone()) else {
+        return;
+    };
+
+    {
+        let mut state = shared.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if blocked {
+            state.blocked[index] = true;
+        }
+
+        let deadlocked =
+            blocked && (0..state.finished.len()).all(|index| state.finished[index] || state.blocked[index]);
+        let next = state.pick(blocked.then_some(index));
+
+        let Some(next) = next.filter(|_| !deadlocked) else {
+            drop(state);
+            panic!("deadlock: all threads wait for a lock which is held by one of them");
+        };
+
+        state.current = next;
+        shared.turn.notify_all();
+    }
+
+    wait_for_turn(&shared, index);
+}
+
+/// Records that the current thread acquired a lock, which may unblock all
+/// other threads.
+pub(crate) fn acquired() {
+    let Some((shared, _)) = SCHEDULED.with(|scheduled| scheduled.borrow().clone()) else {
+        return;
+    };
+
+    let mut state = shared.state.lock().unwrap_or_else(PoisonError::into_inner);
+    state.blocked.fill(false);
+}
@@ -0,0 +1,83 @@
+use lume_architect::*;
+use proptest::prelude::*;
+
+const QUERIES: [&str; 5] = [
+    "coherence::input",
+    "coherence::sum",
+    "coherence::total",
+    "coherence::chunks",
+    "coherence::reduced",
+];
+
+/// Generates operations with few distinct values, so inputs are frequently
+/// replaced by an equal value, which exercises backdating.
+fn operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        5 => (any::<u8>(), 0..4_u64).prop_map(|(input, value)| Operation::Insert { input, value }),
+        2 => (any::<u8>(), 0..4_u64, 0..4_u8).prop_map(|(input, value, count)| Operation::InsertBatch {
+            input,
+            value,
+            count
+        }),
+        2 => any::<u8>().prop_map(|input| Operation::Invalidate { input }),
+        1 => Just(Operation::ClearInputs),
+        1 => Just(Operation::ClearAll),
+        1 => (any::<u8>(), any::<u8>()).prop_map(|(derived, chunk)| Operation::InvalidateChunk { derived, chunk }),
+        1 => any::<u8>().prop_map(|derived| Operation::ClearPrefix { derived }),
+        1 => any::<u8>().prop_map(|derived| Operation::ForceEvict { derived }),
+        1 => Just(Operation::ReleaseSoft),
+        1 => any::<u8>().prop_map(|derived| Operation::Merge { derived }),
+        7 => any::<u8>().prop_map(|derived| Operation::Execute { derived }),
+        3 => any::<u8>().prop_map(|derived| Operation::ExecuteChunked { derived }),
+        3 => any::<u8>().prop_map(|derived| Operation::MapReduce { derived }),
+    ]
+}
+
+/// Creates a database, where the test queries are configured by `f`.
+fn database(f: impl Fn(&mut Query)) -> Database {
+    let db = Database::new();
+
+    for name in QUERIES {
+        db.ensure_query_exists(name, QueryFlags::empty);
+        f(&mut db.query_mut(name));
+    }
+
+    db
+}
+
+proptest! {
+    #[test]
+    fn coherent(operations in prop::collection::vec(operation(), 0..128)) {
+        let db = database(|_| {});
+
+        prop_assert_eq!(db.check_coherence(operations), Ok(()));
+    }
+
+    #[test]
+    fn coherent_within_batch(operations in prop::collection::vec(operation(), 0..128)) {
+        let db = database(|_| {});
+
+        prop_assert_eq!(db.batch(|db| db.check_coherence(operations)), Ok(()));
+    }
+
+    #[test]
+    fn coherent_with_checksums(operations in prop::collection::vec(operation(), 0..128)) {
+        let db = database(Query::enable_checksums::<u64>);
+
+        prop_assert_eq!(db.check_coherence(operations), Ok(()));
+    }
+
+    #[test]
+    fn coherent_with_equality_checks(operations in prop::collection::vec(operation(), 0..128)) {
+        let db = database(Query::enable_equality_checks::<u64>);
+
+        prop_assert_eq!(db.check_coherence(operations), Ok(()));
+    }
+
+    #[test]
+    fn coherent_with_limited_dependencies(operations in prop::collection::vec(operation(), 0..128)) {
+        let db = database(|query| query.set_max_dependencies(1));
+
+        prop_assert_eq!(db.check_coherence(operations), Ok(()));
+    }
+}
@@ -0,0 +1,339 @@
+//! A small compiler for an arithmetic language, where every file defines a
+//! list of named values:
+//!
+//! ```text
+//! x = 1 + 2
+//! y = x - 4
+//! ```
+//!
+//! Files are parsed, names are resolved across all files and definitions are
+//! evaluated, each as a separate query. This exercises inputs, dependency
+//! tracking, revalidation, invalidation, cycle detection and persistence of
+//! inputs, along with the failure paths of each.
+
+use std::cell::Cell;
+use std::sync::Arc;
+
+use lume_architect::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Term {
+    Number(i64),
+    Name(String),
+}
+
+/// Definition of a single named value, as the sum of its signed terms.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Def {
+    name: String,
+    terms: Vec<(i64, Term)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseError {
+    file: &'static str,
+    line: usize,
+    message: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EvalError {
+    Parse(ParseError),
+    Undefined(String),
+    Query(QueryError),
+}
+
+impl From<ParseError> for EvalError {
+    fn from(err: ParseError) -> Self {
+        EvalError::Parse(err)
+    }
+}
+
+impl From<QueryError> for EvalError {
+    fn from(err: QueryError) -> Self {
+        EvalError::Query(err)
+    }
+}
+
+fn parse_line(line: &str) -> Result<Def, &'static str> {
+    let (name, expr) = line.split_once('=').ok_or("expected `=`")?;
+    let name = name.trim();
+
+    if name.is_empty() || !name.chars().all(char::is_alphanumeric) {
+        return Err("expected name");
+    }
+
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut expect_term = true;
+
+    for token in expr.split_whitespace() {
+        match (token, expect_term) {
+            ("+", false) => sign = 1,
+            ("-", false) => sign = -1,
+            (_, true) => {
+                let term = match token.parse::<i64>() {
+                    Ok(number) => Term::Number(number),
+                    Err(_) if token.chars().all(char::is_alphanumeric) => Term::Name(token.to_string()),
+                    Err(_) => return Err("expected term"),
+                };
+
+                terms.push((sign, term));
+            }
+            (_, false) => return Err("expected operator"),
+        }
+
+        expect_term = !expect_term;
+    }
+
+    if expect_term {
+        return Err("expected term");
+    }
+
+    Ok(Def {
+        name: name.to_string(),
+        terms,
+    })
+}
+
+fn parse_source(file: &'static str, source: &str) -> Result<Arc<[Def]>, ParseError> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            parse_line(line).map_err(|message| ParseError {
+                file,
+                line: idx + 1,
+                message,
+            })
+        })
+        .collect()
+}
+
+struct Compiler {
+    db: Database,
+    parses: Cell<usize>,
+    evals: Cell<usize>,
+}
+
+impl Compiler {
+    fn new() -> Self {
+        let db = Database::new();
+
+        for name in ["files", "source", "parse", "resolve", "eval"] {
+            db.ensure_query_exists(name, QueryFlags::empty);
+        }
+
+        // Catches typos in query names, now that all queries are registered.
+        db.enable_strict_registration();
+
+        Self {
+            db,
+            parses: Cell::new(0),
+            evals: Cell::new(0),
+        }
+    }
+
+    fn set_files(&self, files: &[&'static str]) {
+        self.db.insert("files", &(), files.to_vec());
+    }
+
+    fn set_source(&self, file: &'static str, source: &str) {
+        self.db.insert("source", &file, source.to_string());
+    }
+
+    /// Saves the inputs of the compiler, so they can be restored into a new
+    /// database. Derived results aren't saved, since they're recomputed from
+    /// the inputs on demand.
+    fn save(&self) -> String {
+        let sources = self
+            .files()
+            .into_iter()
+            .map(|file| (file, self.source(file)))
+            .collect::<Vec<_>>();
+
+        serde_json::to_string(&sources).unwrap()
+    }
+
+    /// Restores the inputs saved by [`Compiler::save`] into a new compiler.
+    fn restore(saved: &str) -> Self {
+        let sources: Vec<(String, String)> = serde_json::from_str(saved).unwrap();
+        let files = sources
+            .iter()
+            .map(|(file, _)| &*Box::leak(file.clone().into_boxed_str()))
+            .collect::<Vec<_>>();
+
+        let compiler = Self::new();
+        compiler.set_files(&files);
+
+        for (file, (_, source)) in files.iter().zip(&sources) {
+            compiler.set_source(file, source);
+        }
+
+        compiler
+    }
+
+    fn files(&self) -> Vec<&'static str> {
+        self.db.execute_query("files", &(), Vec::new)
+    }
+
+    fn source(&self, file: &'static str) -> String {
+        self.db.execute_query("source", &file, String::new)
+    }
+
+    fn parse(&self, file: &'static str) -> Result<Arc<[Def]>, ParseError> {
+        self.db.execute_query_result("parse", &file, || {
+            self.parses.set(self.parses.get() + 1);
+
+            parse_source(file, &self.source(file))
+        })
+    }
+
+    fn resolve(&self, name: &str) -> Result<Option<Def>, ParseError> {
+        self.db.execute_query_result("resolve", &name, || {
+            for file in self.files() {
+                if let Some(def) = self.parse(file)?.iter().find(|def| def.name == name) {
+                    return Ok(Some(def.clone()));
+                }
+            }
+
+            Ok(None)
+        })
+    }
+
+    fn eval(&self, name: &str) -> Result<i64, EvalError> {
+        self.db.try_execute_query_result("eval", &name, || {
+            self.evals.set(self.evals.get() + 1);
+
+            let def = self
+                .resolve(name)?
+                .ok_or_else(|| EvalError::Undefined(name.to_string()))?;
+
+            def.terms.iter().try_fold(0, |sum, (sign, term)| {
+                let value = match term {
+                    Term::Number(number) => *number,
+                    Term::Name(name) => self.eval(name)?,
+                };
+
+                Ok(sum + sign * value)
+            })
+        })
+    }
+}
+
+/// Creates a compiler with two files, where `z` evaluates to 12.
+fn compiler() -> Compiler {
+    let compiler = Compiler::new();
+    compiler.set_files(&["a.lm", "b.lm"]);
+    compiler.set_source("a.lm", "x = 1 + 2\ny = x - 4");
+    compiler.set_source("b.lm", "z = y + x + 10");
+
+    compiler
+}
+
+#[test]
+fn reuses_results() {
+    let compiler = compiler();
+
+    assert_eq!(compiler.eval("z"), Ok(12));
+    assert_eq!(compiler.eval("z"), Ok(12));
+    assert_eq!(compiler.parses.get(), 2);
+    assert_eq!(compiler.evals.get(), 3);
+    assert!(compiler.db.stats().hits > 0);
+}
+
+#[test]
+fn recomputes_edited_files() {
+    let compiler = compiler();
+    assert_eq!(compiler.eval("z"), Ok(12));
+
+    // Editing a file only recomputes the results which depend on it.
+    compiler.set_source("b.lm", "z = y + x + 20");
+
+    assert!(compiler.db.is_dirty("eval", &"z"));
+    assert_eq!(compiler.eval("z"), Ok(22));
+    assert_eq!(compiler.eval("x"), Ok(3));
+    assert_eq!(compiler.parses.get(), 3);
+}
+
+#[test]
+fn reports_parse_errors() {
+    let compiler = compiler();
+    compiler.set_source("b.lm", "z = y +");
+
+    assert_eq!(
+        compiler.eval("z"),
+        Err(EvalError::Parse(ParseError {
+            file: "b.lm",
+            line: 1,
+            message: "expected term",
+        }))
+    );
+
+    // Parse errors aren't cached, so fixing the file fixes the result.
+    assert!(!compiler.db.contains("parse", &"b.lm"));
+
+    compiler.set_source("b.lm", "z = y + 1");
+    assert_eq!(compiler.eval("z"), Ok(0));
+}
+
+#[test]
+fn reports_undefined_names() {
+    let compiler = compiler();
+    compiler.set_source("b.lm", "z = w");
+
+    assert_eq!(compiler.eval("z"), Err(EvalError::Undefined(String::from("w"))));
+}
+
+#[test]
+fn reports_cycles() {
+    let compiler = compiler();
+
+    // Definitions which refer to themselves are reported as cycles, instead
+    // of overflowing the stack.
+    compiler.set_source("b.lm", "p = q + 1\nq = p");
+
+    match compiler.eval("p") {
+        Err(EvalError::Query(QueryError::Cycle { query, path, .. })) => {
+            assert_eq!(query, "eval");
+            assert_eq!(path.len(), 2);
+        }
+        result => panic!("expected cycle, found {result:?}"),
+    }
+
+    assert!(compiler.db.stats().cycles > 0);
+}
+
+#[test]
+fn invalidates_dependents() {
+    let compiler = compiler();
+    compiler.set_source("b.lm", "z = x");
+    assert_eq!(compiler.eval("z"), Ok(3));
+
+    // Invalidating an input removes every result which depends on it.
+    assert!(compiler.db.invalidate("source", &"a.lm"));
+    assert!(!compiler.db.contains("parse", &"a.lm"));
+    assert!(!compiler.db.contains("eval", &"z"));
+
+    // Without a source, the file is empty.
+    assert_eq!(compiler.eval("z"), Err(EvalError::Undefined(String::from("x"))));
+}
+
+#[test]
+fn restores_saved_inputs() {
+    let compiler = compiler();
+    assert_eq!(compiler.eval("z"), Ok(12));
+
+    let restored = Compiler::restore(&compiler.save());
+
+    // Derived results are recomputed from the restored inputs.
+    assert!(!restored.db.contains("eval", &"z"));
+    assert_eq!(restored.eval("z"), Ok(12));
+    assert_eq!(restored.parses.get(), 2);
+
+    // Edits after restoring are tracked like any other edit.
+    restored.set_source("a.lm", "x = 5\ny = x - 4");
+    assert_eq!(restored.eval("z"), Ok(16));
+    assert_eq!(compiler.eval("z"), Ok(12));
+}
@@ -0,0 +1,129 @@
+//! Model checks of the coordination between threads, which are run with
+//! `RUSTFLAGS="--cfg loom" cargo test --features sync --test loom --release`.
+#![cfg(loom)]
+
+use loom::sync::Arc;
+use lume_architect::*;
+
+#[test]
+fn views_never_observe_part_of_a_batch() {
+    loom::model(|| {
+        let db = Arc::new(Database::new());
+        db.ensure_query_exists("a", QueryFlags::empty);
+        db.ensure_query_exists("b", QueryFlags::empty);
+
+        let writer = {
+            let db = Arc::clone(&db);
+
+            loom::thread::spawn(move || {
+                db.batch(|db| {
+                    db.insert("a", &(), 1);
+                    db.insert("b", &(), 1);
+                });
+            })
+        };
+
+        db.read_txn(|view| {
+            let a = view.get_cached::<_, i32>("a", &());
+            let b = view.get_cached::<_, i32>("b", &());
+
+            assert_eq!(a, b);
+        });
+
+        writer.join().unwrap();
+    });
+}
+
+#[test]
+fn batches_notify_once() {
+    loom::model(|| {
+        let db = Arc::new(Database::new());
+        let notifications = Arc::new(loom::sync::Mutex::new(Vec::new()));
+
+        for name in ["a", "b", "c"] {
+            db.ensure_query_exists(name, QueryFlags::empty);
+        }
+
+        {
+            let notifications = Arc::clone(&notifications);
+
+            db.add_observer(move |changes: &ChangeSet| notifications.lock().unwrap().push(changes.clone()));
+        }
+
+        let before = db.current_revision();
+
+        let writer = {
+            let db = Arc::clone(&db);
+
+            loom::thread::spawn(move || db.insert("a", &(), 1))
+        };
+
+        db.batch(|db| {
+            db.insert("b", &(), 1);
+            db.insert("c", &(), 1);
+        });
+        writer.join().unwrap();
+
+        let notifications = notifications.lock().unwrap();
+        let revision = |name: &str| db.provenance(name, &()).unwrap().revision;
+
+        // The insertion of the other thread is either reported on its own, or
+        // made part of the batch.
+        assert!((1..=2).contains(&notifications.len()));
+        assert_eq!(
+            notifications
+                .iter()
+                .map(|changes| changes.inserted.len())
+                .sum::<usize>(),
+            3
+        );
+
+        // The batch is committed as a single revision, which is shared with
+        // the insertion of the other thread, if it was made while the batch
+        // was open.
+        assert_eq!(revision("b"), revision("c"));
+
+        if revision("a") == revision("b") {
+            assert_eq!(db.current_revision(), before.next());
+        } else {
+            assert_eq!(db.current_revision(), before.next().next());
+        }
+
+        assert!(
+            notifications
+                .iter()
+                .all(|changes| changes.revision <= db.current_revision())
+        );
+    });
+}
+
+#[test]
+fn lookups_within_transactions_never_deadlock() {
+    loom::model(|| {
+        let db = Arc::new(Database::new());
+        db.ensure_query_exists("a", QueryFlags::empty);
+        db.insert("a", &(), 1);
+
+        let writer = {
+            let db = Arc::clone(&db);
+
+            loom::thread::spawn(move || db.insert("a", &(), 2))
+        };
+
+        let value = db.read_txn(|view| {
+            let value = view.get_cached::<_, i32>("a", &());
+
+            // Lookups through the database observe the same revision as the
+            // view, even though the writer may be waiting for the
+            // transaction.
+            assert_eq!(db.try_execute_query("a", &(), || 0).ok(), value);
+
+            value
+        });
+
+        writer.join().unwrap();
+
+        assert!(matches!(value, Some(1 | 2)));
+        assert_eq!(db.get_cached::<_, i32>("a", &()), Some(2));
+    });
+}
@@ -0,0 +1,12 @@
+//! Checks which signatures are accepted by the derive macros, and the
+//! diagnostics of those which are rejected.
+//!
+//! Run with `TRYBUILD=overwrite` to update the expected diagnostics.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,20 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query(result, check_determinism)]
+    pub fn parse(&self, value: &'static str) -> Result<u32, String> {
+        value.parse().map_err(|_| String::from("invalid"))
+    }
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    pub fn double(value: u32) -> u32 {
+        value * 2
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use lume_architect::*;
+
+query_module! {
+    pub trait Frontend {
+        const VERSION: u32;
+
+        fn words(file: usize) -> Vec<String>;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use lume_architect::*;
+
+query_module! {
+    pub trait Frontend {
+        fn span_len((start, end): (usize, usize)) -> usize;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use lume_architect::*;
+
+query_module! {
+    pub trait Frontend {
+        fn words(&self, file: usize) -> Vec<String>;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,48 @@
+use std::fmt::Display;
+use std::hash::Hash;
+
+use lume_architect::*;
+
+struct Context {
+    db: Database,
+}
+
+impl DatabaseContext for Context {
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Context {
+    #[cached_query]
+    pub fn first<'a, T>(&self, items: &'a [T]) -> Option<String>
+    where
+        T: Hash + Display + 'a,
+    {
+        items.first().map(ToString::to_string)
+    }
+
+    #[cached_query]
+    pub fn display(&self, value: impl Hash + Display) -> String {
+        value.to_string()
+    }
+
+    #[cached_query]
+    pub fn join<'a, 'b: 'a>(&'a self, left: &'a str, right: &'b str) -> String {
+        format!("{left}{right}")
+    }
+
+    #[cached_query]
+    pub fn repeat<const N: usize>(&self, value: char) -> String {
+        value.to_string().repeat(N)
+    }
+}
+
+fn main() {
+    let ctx = Context { db: Database::new() };
+
+    ctx.first(&[1, 2]);
+    ctx.display('c');
+    ctx.join("a", "b");
+    ctx.repeat::<2>('a');
+}